@@ -0,0 +1,93 @@
+//! Timings of SLIC and SNIC on the synthetic gradient from the `testing`
+//! module.
+//!
+//! Run with `cargo bench --features testing`. Each case is run several times
+//! and the median of every stage from [`Timings`] is printed, so the cost of
+//! an option can be read off the stage it affects.
+use std::time::Duration;
+
+use simple_clustering::error::ScError;
+use simple_clustering::options::{Connectivity, Options};
+use simple_clustering::seed::Seeding;
+use simple_clustering::testing::test_gradient;
+use simple_clustering::{slic_with_timings, snic_with_timings, Timings};
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+const K: u32 = 5000;
+const M: u8 = 10;
+const RUNS: usize = 7;
+
+fn median(durations: &mut [Duration]) -> Duration {
+    durations.sort_unstable();
+    durations
+        .get(durations.len() / 2)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Run `segment` [`RUNS`] times and print the median time of each stage.
+fn bench(name: &str, mut segment: impl FnMut(&mut Timings) -> Result<Vec<usize>, ScError>) {
+    let mut runs = Vec::with_capacity(RUNS);
+    for _ in 0..RUNS {
+        let mut timings = Timings::default();
+        let labels = segment(&mut timings).expect("segmenting the gradient failed");
+        assert_eq!(labels.len(), (WIDTH * HEIGHT) as usize);
+        runs.push(timings);
+    }
+
+    let stage = |f: fn(&Timings) -> Duration| median(&mut runs.iter().map(f).collect::<Vec<_>>());
+    let seeding = stage(|t| t.seeding);
+    let clustering = stage(|t| t.clustering);
+    let connectivity = stage(|t| t.connectivity);
+    let total = stage(|t| t.smoothing + t.seeding + t.clustering + t.connectivity);
+    println!(
+        "{name:<28} total {total:>10.2?}  seeding {seeding:>10.2?}  \
+         clustering {clustering:>10.2?}  connectivity {connectivity:>10.2?}"
+    );
+}
+
+fn main() {
+    let image = test_gradient(WIDTH, HEIGHT).expect("generating the gradient failed");
+    println!("{WIDTH}x{HEIGHT} gradient, k = {K}, m = {M}, median of {RUNS} runs");
+
+    for perturb in [true, false] {
+        let options = Options {
+            perturb,
+            ..Options::default()
+        };
+        bench(&format!("slic, perturb {perturb}"), |timings| {
+            slic_with_timings(K, M, WIDTH, HEIGHT, None, &options, &image, timings)
+        });
+        bench(&format!("snic, perturb {perturb}"), |timings| {
+            snic_with_timings(K, M, WIDTH, HEIGHT, &options, &image, timings)
+        });
+    }
+
+    for connectivity in [Connectivity::Four, Connectivity::Eight] {
+        let options = Options {
+            connectivity,
+            ..Options::default()
+        };
+        bench(&format!("snic, {connectivity:?} neighbors"), |timings| {
+            snic_with_timings(K, M, WIDTH, HEIGHT, &options, &image, timings)
+        });
+    }
+
+    // A few seeds with a window covering the whole image make superpixels far
+    // larger than `S x S`, so the connectivity queue has to grow many times
+    let options = Options {
+        seeding: Seeding::Points(
+            (0..3)
+                .flat_map(|y| {
+                    (0..4).map(move |x| (WIDTH / 8 + x * WIDTH / 4, HEIGHT / 6 + y * HEIGHT / 3))
+                })
+                .collect(),
+        ),
+        search_window: 200.0,
+        ..Options::default()
+    };
+    bench("slic, 12 large superpixels", |timings| {
+        slic_with_timings(K, M, WIDTH, HEIGHT, Some(2), &options, &image, timings)
+    });
+}
@@ -0,0 +1,59 @@
+//! Run `slic` and `snic` on small images of arbitrary dimensions, parameters,
+//! and colors. Both must return a valid label map or an `ScError`, and never
+//! panic.
+//!
+//! Run with `cargo +nightly fuzz run segment` from the repository root.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use palette::{white_point::D65, Lab};
+use simple_clustering::{image::count_colors, slic, snic};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    width: u8,
+    height: u8,
+    k: u16,
+    m: u8,
+    iter: Option<u8>,
+    /// Leave the image buffer at its arbitrary length instead of resizing it
+    /// to `width * height`.
+    mismatched: bool,
+    colors: Vec<(f64, f64, f64)>,
+}
+
+fuzz_target!(|input: Input| {
+    // Keep the images small so each run is fast
+    let width = u32::from(input.width % 33);
+    let height = u32::from(input.height % 33);
+    let k = u32::from(input.k % 1200);
+    let iter = input.iter.map(|iter| iter % 4);
+    let pixels = (width * height) as usize;
+
+    let mut image: Vec<Lab<D65, f64>> = input
+        .colors
+        .iter()
+        .map(|&(l, a, b)| Lab::new(l, a, b))
+        .collect();
+    if !input.mismatched {
+        let fill = image.first().copied().unwrap_or_default();
+        image.resize(pixels, fill);
+    }
+
+    if let Ok(labels) = slic(k, input.m, width, height, iter, &image) {
+        // Labels are renumbered from 0 without gaps after enforcing
+        // connectivity
+        assert_eq!(labels.len(), pixels);
+        let max = labels.iter().copied().max().expect("empty label map");
+        assert_eq!(count_colors(&labels), max + 1);
+    }
+
+    if let Ok(labels) = snic(k, input.m, width, height, &image) {
+        // Labels start at 1 and there is at most one per seed
+        assert_eq!(labels.len(), pixels);
+        assert!(labels
+            .iter()
+            .all(|&label| (1..=k as usize).contains(&label)));
+    }
+});
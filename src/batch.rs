@@ -0,0 +1,34 @@
+//! Segmentation of multiple images in parallel.
+use crate::error::ScError;
+use crate::options::Algorithm;
+
+use palette::{white_point::D65, Lab};
+use rayon::prelude::*;
+
+/// An image to segment with [`segment_batch`], as `(width, height, image)`.
+pub type BatchInput<'a> = (u32, u32, &'a [Lab<D65, f64>]);
+
+/// Segment each `(width, height, image)` input with `algorithm` in parallel,
+/// using the global `rayon` thread pool.
+///
+/// The results are in the same order as `inputs`. An error for one image
+/// doesn't stop the others from being segmented.
+///
+/// Requires the `rayon` feature.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+pub fn segment_batch(
+    algorithm: Algorithm,
+    k: u32,
+    m: u8,
+    inputs: &[BatchInput<'_>],
+) -> Vec<Result<Vec<usize>, ScError>> {
+    inputs
+        .par_iter()
+        .map(|&(width, height, image)| match algorithm {
+            Algorithm::Slic => crate::slic(k, m, width, height, None, image),
+            Algorithm::Snic => crate::snic(k, m, width, height, image),
+        })
+        .collect()
+}
@@ -3,11 +3,12 @@ use clap::Parser;
 #[derive(Debug, Parser)]
 #[clap(version, about, long_about = None)]
 pub struct Opt {
-    /// Input file.
+    /// Input file, or a directory of images to segment.
     #[clap(short, long, parse(from_os_str))]
     pub input: std::path::PathBuf,
 
-    /// Output file, defaults to PNG image output.
+    /// Output file, defaults to PNG image output. For a directory input, the
+    /// directory where the output images are saved.
     #[clap(short, long, parse(from_os_str))]
     pub output: Option<std::path::PathBuf>,
 
@@ -19,7 +20,8 @@ pub struct Opt {
     #[clap(short, default_value_t = 10)]
     pub m: u8,
 
-    /// Number of iterations to run (SLIC).
+    /// Number of iterations to run (SLIC). 0 assigns each pixel to its nearest
+    /// seed without iterating.
     #[clap(long, default_value_t = 10)]
     pub iter: u8,
 
@@ -28,16 +30,36 @@ pub struct Opt {
     pub no_mean: bool,
 
     /// Segmentation algorithm used, such as SNIC or SLIC.
-    #[clap(short, long, default_value = "snic")]
+    #[clap(
+        short,
+        long,
+        default_value = "snic",
+        parse(try_from_str = crate::utils::parse_algorithm)
+    )]
     pub algorithm: crate::utils::Algorithm,
 
     /// Print the number of segments found and time taken.
     #[clap(short, long)]
     pub verbose: bool,
 
+    /// Print a JSON summary of the segmentation instead of the verbose output.
+    #[clap(long)]
+    pub json: bool,
+
     /// Save as a JPG or PNG file.
     #[clap(long, default_value = "png")]
-    pub format: String,
+    pub format: crate::utils::OutputFormat,
+
+    /// Bit depth of the saved image components, 8 or 16. 16-bit images keep
+    /// the precision of the mean colors. JPG files and --compare images are
+    /// always saved as 8-bit.
+    #[clap(long, default_value_t = 8, possible_values = &["8", "16"])]
+    pub bit_depth: u8,
+
+    /// Run both SLIC and SNIC and save their mean color images side by side,
+    /// SLIC on the left and SNIC on the right.
+    #[clap(long)]
+    pub compare: bool,
 
     /// Development flag for testing speeds of calculation.
     #[clap(long, hide = true)]
@@ -50,4 +72,27 @@ pub struct Opt {
     /// Specify the hexadecimal RGB color for segment contours.
     #[clap(long, default_value = "000")]
     pub segment_color: String,
+
+    /// Read superpixel seeds from a file containing one `x,y` pair per line.
+    #[clap(long, parse(from_os_str))]
+    pub seeds: Option<std::path::PathBuf>,
+
+    /// Write the grid seeds for the image to a file, one `x,y` pair per line.
+    #[clap(long, parse(from_os_str))]
+    pub dump_seeds: Option<std::path::PathBuf>,
+
+    /// Write the label of each pixel to a binary file. The format is described
+    /// in the README.
+    #[clap(long, parse(from_os_str), conflicts_with = "compare")]
+    pub emit_labels: Option<std::path::PathBuf>,
+
+    /// Treat the left and right edges of the image as connected, such as for
+    /// 360° panoramas.
+    #[clap(long)]
+    pub wrap_x: bool,
+
+    /// Skip moving the grid seeds away from edges before clustering. May
+    /// produce some ragged superpixels near strong edges.
+    #[clap(long)]
+    pub no_perturb: bool,
 }
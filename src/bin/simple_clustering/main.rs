@@ -2,13 +2,22 @@ mod args;
 mod utils;
 
 use crate::args::Opt;
-use crate::utils::{generate_filename, save_image, Algorithm};
+use crate::utils::{
+    algorithm_name, generate_filename, read_seeds, save_image, save_image_u16, write_labels,
+    write_seeds, Algorithm, OutputFormat, Rgb16ContourSink, Summary,
+};
 
 use clap::Parser;
 
-use palette::{cast, FromColor, Lab, Srgb};
-use simple_clustering::image::{count_colors, mean_colors, segment_contours};
+use palette::{cast, white_point::D65, FromColor, Lab, Srgb};
+use simple_clustering::image::{
+    count_colors, mean_colors, mean_colors_u16, segment_contours_to_sink, segment_contours_wrapped,
+};
+use simple_clustering::options::Options;
+use simple_clustering::seed::{seeds_with_options, Seeding};
+use simple_clustering::Timings;
 use std::fmt::Write;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 fn main() {
@@ -21,13 +30,80 @@ fn main() {
 fn try_main() -> Result<(), Box<dyn std::error::Error>> {
     let opt = Opt::parse();
 
-    let output_image = if let Some(output) = opt.output {
-        output
+    if opt.input.is_dir() {
+        return process_directory(&opt);
+    }
+
+    let output_image = if let Some(output) = &opt.output {
+        output.clone()
     } else {
-        generate_filename(&opt)?.into()
+        generate_filename(&opt, &opt.input)?.into()
     };
+    // Check the format before segmenting rather than when saving
+    let _ = OutputFormat::from_path(&output_image)?;
+
+    process_image(&opt, &opt.input, &output_image)
+}
+
+// Segment every image in the input directory, writing the results to the
+// output directory with generated file names. Errors for individual images are
+// reported without stopping the rest of the batch.
+fn process_directory(opt: &Opt) -> Result<(), Box<dyn std::error::Error>> {
+    if opt.dump_seeds.is_some() {
+        return Err("Cannot dump seeds for a directory input".into());
+    }
+    if opt.emit_labels.is_some() {
+        return Err("Cannot emit labels for a directory input".into());
+    }
+
+    let output_dir = opt.output.clone().unwrap_or_else(|| ".".into());
+    std::fs::create_dir_all(&output_dir)?;
+
+    let mut inputs = Vec::new();
+    for entry in std::fs::read_dir(&opt.input)? {
+        let path = entry?.path();
+        if path.is_file() && image::ImageFormat::from_path(&path).is_ok() {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+
+    let process = |input: &PathBuf| {
+        let result = generate_filename(opt, input)
+            .and_then(|filename| process_image(opt, input, &output_dir.join(filename)));
+        if let Err(e) = &result {
+            eprintln!("simple_clustering: {}: {}", input.display(), e);
+        }
+        result.is_ok()
+    };
+
+    #[cfg(feature = "rayon")]
+    let succeeded = {
+        use rayon::prelude::*;
+        inputs.par_iter().filter(|input| process(input)).count()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let succeeded = inputs.iter().filter(|input| process(input)).count();
+
+    if succeeded < inputs.len() {
+        return Err(format!(
+            "{} of {} images failed",
+            inputs.len() - succeeded,
+            inputs.len()
+        )
+        .into());
+    }
+
+    Ok(())
+}
 
-    let input_image = image::open(opt.input)?.into_rgb8();
+// Segment a single image and save the output image.
+fn process_image(
+    opt: &Opt,
+    input: &Path,
+    output_image: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_image = image::open(input)?.into_rgb8();
     let (width, height) = input_image.dimensions();
     let input_buffer = cast::from_component_slice::<Srgb<u8>>(input_image.as_raw());
     let mut input_lab: Vec<Lab<_, f64>> = Vec::new();
@@ -38,6 +114,18 @@ fn try_main() -> Result<(), Box<dyn std::error::Error>> {
             .map(|&c| Lab::from_color(c.into_format())),
     );
 
+    let mut options = Options::new();
+    if let Some(seeds) = &opt.seeds {
+        options.seeding = Seeding::Points(read_seeds(seeds)?);
+    }
+    options.wrap_x = opt.wrap_x;
+    options.perturb = !opt.no_perturb;
+
+    if let Some(dump_seeds) = &opt.dump_seeds {
+        let seeds = seeds_with_options(opt.k, width, height, &options, &input_lab)?;
+        write_seeds(dump_seeds, &seeds)?;
+    }
+
     let mut display_string = String::new();
     let mut output_buffer = Vec::new();
     output_buffer.try_reserve_exact(input_image.as_raw().len())?;
@@ -52,37 +140,98 @@ fn try_main() -> Result<(), Box<dyn std::error::Error>> {
         let _ = simple_clustering::snic(opt.k, opt.m, width, height, &input_lab)?;
         writeln!(&mut display_string, "SNIC: {:?}", t0.elapsed())?;
 
+        // Time the segmentation without seed perturbation
+        let mut options = Options::new();
+        options.perturb = false;
+
+        let t0 = std::time::Instant::now();
+        let _ = simple_clustering::slic_with_options(
+            opt.k,
+            opt.m,
+            width,
+            height,
+            Some(opt.iter),
+            &options,
+            &input_lab,
+        )?;
+        writeln!(&mut display_string, "SLIC, no perturb: {:?}", t0.elapsed())?;
+
+        let t0 = std::time::Instant::now();
+        let _ = simple_clustering::snic_with_options(
+            opt.k, opt.m, width, height, &options, &input_lab,
+        )?;
+        writeln!(&mut display_string, "SNIC, no perturb: {:?}", t0.elapsed())?;
+
         print!("{display_string}");
         return Ok(());
     }
 
-    let labels = match opt.algorithm {
-        Algorithm::Snic => {
-            let t0 = std::time::Instant::now();
-            let labels = simple_clustering::snic(opt.k, opt.m, width, height, &input_lab)?;
-            let t1 = t0.elapsed();
-            if opt.verbose {
-                write!(&mut display_string, "SNIC: {:?}", t1)?;
-            }
-            labels
-        }
-        Algorithm::Slic => {
-            let t0 = std::time::Instant::now();
-            let labels =
-                simple_clustering::slic(opt.k, opt.m, width, height, Some(opt.iter), &input_lab)?;
-            let t1 = t0.elapsed();
-            if opt.verbose {
-                write!(&mut display_string, "SLIC: {:?}", t1)?;
-            }
-            labels
-        }
-    };
-
     let segment_color = Srgb::from_str(opt.segment_color.as_str())
         .or(Err("Segment color is invalid hex"))?
         .into();
 
-    if !opt.no_mean {
+    if opt.compare {
+        return compare(
+            opt,
+            &options,
+            &input_lab,
+            width,
+            height,
+            segment_color,
+            output_image,
+        );
+    }
+
+    let mut timings = Timings::default();
+    let t0 = std::time::Instant::now();
+    let labels = match opt.algorithm {
+        Algorithm::Snic => simple_clustering::snic_with_timings(
+            opt.k,
+            opt.m,
+            width,
+            height,
+            &options,
+            &input_lab,
+            &mut timings,
+        )?,
+        Algorithm::Slic => simple_clustering::slic_with_timings(
+            opt.k,
+            opt.m,
+            width,
+            height,
+            Some(opt.iter),
+            &options,
+            &input_lab,
+            &mut timings,
+        )?,
+    };
+    let elapsed = t0.elapsed();
+    if let Some(emit_labels) = &opt.emit_labels {
+        write_labels(emit_labels, &labels, width, height)?;
+    }
+    if opt.verbose {
+        write!(
+            &mut display_string,
+            "{}: {:?} (seeding {:?}, clustering {:?}, connectivity {:?})",
+            algorithm_name(opt.algorithm).to_uppercase(),
+            elapsed,
+            timings.seeding,
+            timings.clustering,
+            timings.connectivity,
+        )?;
+    }
+
+    let segments = if opt.bit_depth == 16 {
+        save_16_bit(
+            opt,
+            &input_image,
+            &input_lab,
+            &labels,
+            (width, height),
+            segment_color,
+            output_image,
+        )?
+    } else if !opt.no_mean {
         let num_segments = mean_colors(
             &mut output_buffer,
             usize::try_from(opt.k)?,
@@ -92,31 +241,175 @@ fn try_main() -> Result<(), Box<dyn std::error::Error>> {
 
         // Draw segment contours over mean image
         if opt.segments {
-            segment_contours(&mut output_buffer, width, height, &labels, segment_color)?;
+            segment_contours_wrapped(
+                &mut output_buffer,
+                width,
+                height,
+                &labels,
+                segment_color,
+                opt.wrap_x,
+            )?;
         }
 
-        save_image(output_image.as_ref(), &output_buffer, width, height)?;
+        save_image(output_image, &output_buffer, width, height)?;
 
-        if opt.verbose {
-            write!(&mut display_string, ", {num_segments} segments")?;
-        }
+        Some(num_segments)
     } else {
         // Save segmented original image
         if opt.segments {
             output_buffer.copy_from_slice(&input_image);
-            segment_contours(&mut output_buffer, width, height, &labels, segment_color)?;
-            save_image(output_image.as_ref(), &output_buffer, width, height)?;
+            segment_contours_wrapped(
+                &mut output_buffer,
+                width,
+                height,
+                &labels,
+                segment_color,
+                opt.wrap_x,
+            )?;
+            save_image(output_image, &output_buffer, width, height)?;
+        }
+
+        None
+    };
+
+    if opt.json {
+        // Count individual labels if the mean colors weren't computed
+        let summary = Summary {
+            algorithm: &opt.algorithm,
+            k: opt.k,
+            segments: segments.unwrap_or_else(|| count_colors(&labels)),
+            m: opt.m,
+            iterations: match opt.algorithm {
+                Algorithm::Slic => Some(opt.iter),
+                Algorithm::Snic => None,
+            },
+            width,
+            height,
+            elapsed,
+        };
+        println!("{}", summary.to_json());
+    } else if opt.verbose {
+        let segments = segments.unwrap_or_else(|| count_colors(&labels));
+        println!("{display_string}, {segments} segments");
+    }
+
+    Ok(())
+}
+
+// Save the mean color image, or the original image with segment contours, with
+// 16-bit components. Returns the number of segments if the mean colors were
+// computed.
+fn save_16_bit(
+    opt: &Opt,
+    input_image: &[u8],
+    input_lab: &[Lab<D65, f64>],
+    labels: &[usize],
+    (width, height): (u32, u32),
+    segment_color: [u8; 3],
+    output_image: &Path,
+) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+    let mut output_buffer = Vec::new();
+    output_buffer.try_reserve_exact(input_image.len())?;
+
+    let segments = if !opt.no_mean {
+        output_buffer.extend((0..input_image.len()).map(|_| 0));
+        Some(mean_colors_u16(
+            &mut output_buffer,
+            usize::try_from(opt.k)?,
+            labels,
+            input_lab,
+        )?)
+    } else if opt.segments {
+        output_buffer.extend(input_image.iter().map(|&c| u16::from(c) * 257));
+        None
+    } else {
+        return Ok(None);
+    };
+
+    if opt.segments {
+        let mut sink = Rgb16ContourSink {
+            buffer: &mut output_buffer,
+            width,
+            color: segment_color.map(|c| u16::from(c) * 257),
+        };
+        segment_contours_to_sink(width, height, labels, opt.wrap_x, &mut sink)?;
+    }
+
+    save_image_u16(output_image, &output_buffer, width, height)?;
+
+    Ok(segments)
+}
+
+// Segment the image with SLIC and SNIC and save their mean color images side
+// by side, SLIC on the left and SNIC on the right.
+fn compare(
+    opt: &Opt,
+    options: &Options,
+    input_lab: &[Lab<D65, f64>],
+    width: u32,
+    height: u32,
+    segment_color: [u8; 3],
+    output_image: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let width_usize = usize::try_from(width)?;
+    let mut halves = Vec::new();
+
+    for algorithm in [Algorithm::Slic, Algorithm::Snic] {
+        let t0 = std::time::Instant::now();
+        let labels = match algorithm {
+            Algorithm::Snic => simple_clustering::snic_with_options(
+                opt.k, opt.m, width, height, options, input_lab,
+            )?,
+            Algorithm::Slic => simple_clustering::slic_with_options(
+                opt.k,
+                opt.m,
+                width,
+                height,
+                Some(opt.iter),
+                options,
+                input_lab,
+            )?,
+        };
+        let elapsed = t0.elapsed();
+
+        let mut buffer = Vec::new();
+        buffer.try_reserve_exact(input_lab.len() * 3)?;
+        buffer.extend((0..input_lab.len() * 3).map(|_| 0));
+        let segments = mean_colors(&mut buffer, usize::try_from(opt.k)?, &labels, input_lab)?;
+        if opt.segments {
+            segment_contours_wrapped(
+                &mut buffer,
+                width,
+                height,
+                &labels,
+                segment_color,
+                opt.wrap_x,
+            )?;
         }
 
-        // Otherwise, count individual labels for verbose output
         if opt.verbose {
-            write!(&mut display_string, ", {} segments", count_colors(&labels))?;
+            println!(
+                "{}: {:?}, {segments} segments",
+                algorithm_name(algorithm).to_uppercase(),
+                elapsed
+            );
         }
+        halves.push(buffer);
     }
 
-    if opt.verbose {
-        println!("{display_string}");
+    // Interleave the rows of both images
+    let mut output_buffer = Vec::new();
+    output_buffer.try_reserve_exact(halves.iter().map(Vec::len).sum())?;
+    for (slic_row, snic_row) in halves[0]
+        .chunks_exact(width_usize * 3)
+        .zip(halves[1].chunks_exact(width_usize * 3))
+    {
+        output_buffer.extend_from_slice(slic_row);
+        output_buffer.extend_from_slice(snic_row);
     }
 
+    let output_width = width.checked_mul(2).ok_or("Comparison image is too wide")?;
+    save_image(output_image, &output_buffer, output_width, height)?;
+
     Ok(())
 }
@@ -1,60 +1,93 @@
 use image::codecs::png::{CompressionType, FilterType, PngEncoder};
 use image::{codecs::jpeg::JpegEncoder, ColorType, ImageEncoder};
 
-#[derive(Debug)]
-pub enum Algorithm {
-    Snic,
-    Slic,
+pub use simple_clustering::options::Algorithm;
+
+// Parse the algorithm name given on the command line, ignoring case.
+pub fn parse_algorithm(s: &str) -> Result<Algorithm, simple_clustering::error::ScError> {
+    match s {
+        s if s.eq_ignore_ascii_case("snic") => Ok(Algorithm::Snic),
+        s if s.eq_ignore_ascii_case("slic") => Ok(Algorithm::Slic),
+        _ => Err(simple_clustering::error::ScError::General(
+            "Invalid algorithm",
+        )),
+    }
+}
+
+// Lowercase name of `algorithm`, as accepted by `parse_algorithm`.
+pub const fn algorithm_name(algorithm: Algorithm) -> &'static str {
+    match algorithm {
+        Algorithm::Snic => "snic",
+        Algorithm::Slic => "slic",
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Jpg,
+}
+
+impl OutputFormat {
+    // Format to save `path` as, based on its extension. Paths without an
+    // extension are saved as PNG.
+    pub fn from_path(path: &std::path::Path) -> Result<Self, simple_clustering::error::ScError> {
+        match path.extension().and_then(std::ffi::OsStr::to_str) {
+            Some(ext) => ext.parse(),
+            None if path.extension().is_some() => Err(Self::UNSUPPORTED),
+            None => Ok(Self::Png),
+        }
+    }
+
+    const UNSUPPORTED: simple_clustering::error::ScError =
+        simple_clustering::error::ScError::General(
+            "Unsupported output format, supported formats are png and jpg",
+        );
 }
 
-impl std::str::FromStr for Algorithm {
+impl std::str::FromStr for OutputFormat {
     type Err = simple_clustering::error::ScError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            s if s.eq_ignore_ascii_case("snic") => Ok(Self::Snic),
-            s if s.eq_ignore_ascii_case("slic") => Ok(Self::Slic),
-            _ => Err(Self::Err::General("Invalid algorithm")),
+            s if s.eq_ignore_ascii_case("png") => Ok(Self::Png),
+            s if s.eq_ignore_ascii_case("jpg") || s.eq_ignore_ascii_case("jpeg") => Ok(Self::Jpg),
+            _ => Err(Self::UNSUPPORTED),
         }
     }
 }
 
-impl std::fmt::Display for Algorithm {
+impl std::fmt::Display for OutputFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Snic => write!(f, "snic"),
-            Self::Slic => write!(f, "slic"),
+            Self::Png => write!(f, "png"),
+            Self::Jpg => write!(f, "jpg"),
         }
     }
 }
 
 // Create a file name displaying the algorithm, `k`, and `m` values used.
-pub fn generate_filename(opt: &crate::args::Opt) -> Result<String, Box<dyn std::error::Error>> {
-    let mut filename = opt
-        .input
+pub fn generate_filename(
+    opt: &crate::args::Opt,
+    input: &std::path::Path,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut filename = input
         .file_stem()
         .ok_or("No file stem")?
         .to_str()
         .ok_or("Could not convert file stem to string")?
         .to_string();
 
-    let format =
-        if opt.format.eq_ignore_ascii_case("jpg") || opt.format.eq_ignore_ascii_case("jpeg") {
-            "jpg"
-        } else {
-            opt.format.as_str()
-        };
-
     use std::fmt::Write;
-    write!(
-        &mut filename,
-        "-{algo}-{k}-{m:02}",
-        algo = opt.algorithm,
-        k = opt.k,
-        m = opt.m
-    )?;
-
-    if opt.no_mean {
+    if opt.compare {
+        write!(&mut filename, "-slic-snic")?;
+    } else {
+        write!(&mut filename, "-{}", algorithm_name(opt.algorithm))?;
+    }
+    write!(&mut filename, "-{k}-{m:02}", k = opt.k, m = opt.m)?;
+
+    // The comparison is always of the mean color images
+    if opt.no_mean && !opt.compare {
         write!(&mut filename, "-orig")?;
     } else {
         write!(&mut filename, "-mean")?;
@@ -63,7 +96,7 @@ pub fn generate_filename(opt: &crate::args::Opt) -> Result<String, Box<dyn std::
     if opt.segments {
         write!(&mut filename, "-segments")?;
     }
-    write!(&mut filename, ".{format}")?;
+    write!(&mut filename, ".{}", opt.format)?;
 
     Ok(filename)
 }
@@ -75,20 +108,18 @@ pub fn save_image(
     width: u32,
     height: u32,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let format = OutputFormat::from_path(output)?;
     let w = std::io::BufWriter::new(std::fs::File::create(output)?);
 
-    // Save as jpg if it matches the extension
-    if let Some(ext) = output.extension() {
-        if ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg") {
-            let mut encoder = JpegEncoder::new_with_quality(w, 90);
-
-            if let Err(err) = encoder.encode(imgbuf, width, height, ColorType::Rgb8) {
-                eprintln!("simple_clustering: {}", err);
-                std::fs::remove_file(output)?;
-            }
+    if format == OutputFormat::Jpg {
+        let mut encoder = JpegEncoder::new_with_quality(w, 90);
 
-            return Ok(());
+        if let Err(err) = encoder.encode(imgbuf, width, height, ColorType::Rgb8) {
+            eprintln!("simple_clustering: {}", err);
+            std::fs::remove_file(output)?;
         }
+
+        return Ok(());
     }
 
     // Sub filter seemed to result in better filesize compared to Adaptive
@@ -102,3 +133,147 @@ pub fn save_image(
 
     Ok(())
 }
+
+// Saves a buffer of 16-bit RGB components to file. JPG doesn't support 16-bit
+// components, so the image is converted to 8-bit if the extension is JPG.
+pub fn save_image_u16(
+    output: &std::path::Path,
+    imgbuf: &[u16],
+    width: u32,
+    height: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if OutputFormat::from_path(output)? == OutputFormat::Jpg {
+        let mut bytes = Vec::new();
+        bytes.try_reserve_exact(imgbuf.len())?;
+        bytes.extend(
+            imgbuf
+                .iter()
+                .map(|&c| u8::try_from(c / 257).unwrap_or(u8::MAX)),
+        );
+        return save_image(output, &bytes, width, height);
+    }
+
+    // The encoder expects the components as native endian bytes
+    let mut bytes = Vec::new();
+    bytes.try_reserve_exact(imgbuf.len() * 2)?;
+    bytes.extend(imgbuf.iter().flat_map(|c| c.to_ne_bytes()));
+
+    let w = std::io::BufWriter::new(std::fs::File::create(output)?);
+    let encoder = PngEncoder::new_with_quality(w, CompressionType::Best, FilterType::Sub);
+
+    // Clean up if file is created but there's a problem writing to it
+    if let Err(err) = encoder.write_image(&bytes, width, height, ColorType::Rgb16) {
+        eprintln!("simple_clustering: {}", err);
+        std::fs::remove_file(output)?;
+    }
+
+    Ok(())
+}
+
+// Draws contours into a buffer of 16-bit RGB components.
+pub struct Rgb16ContourSink<'a> {
+    pub buffer: &'a mut [u16],
+    pub width: u32,
+    pub color: [u16; 3],
+}
+
+impl simple_clustering::image::ContourSink for Rgb16ContourSink<'_> {
+    fn mark(&mut self, x: u32, y: u32, _label: usize) {
+        let idx = (y as usize * self.width as usize + x as usize) * 3;
+        if let Some(chunk) = self.buffer.get_mut(idx..idx + 3) {
+            chunk.copy_from_slice(&self.color);
+        }
+    }
+}
+
+// Reads seed coordinates from a file with one `x,y` pair per line.
+pub fn read_seeds(input: &std::path::Path) -> Result<Vec<(u32, u32)>, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(input)?;
+    let mut seeds = Vec::new();
+
+    for line in contents.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let (x, y) = line
+            .split_once(',')
+            .ok_or_else(|| format!("Invalid seed line: {line}"))?;
+        seeds.push((x.trim().parse()?, y.trim().parse()?));
+    }
+
+    Ok(seeds)
+}
+
+// Writes seed coordinates to a file with one `x,y` pair per line.
+pub fn write_seeds<T>(
+    output: &std::path::Path,
+    seeds: &[simple_clustering::Superpixel<T>],
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    let mut w = std::io::BufWriter::new(std::fs::File::create(output)?);
+    for seed in seeds {
+        let (x, y) = seed.position();
+        writeln!(w, "{x},{y}")?;
+    }
+    w.flush()?;
+
+    Ok(())
+}
+
+// Magic bytes at the start of a label map file.
+const LABEL_MAGIC: &[u8; 4] = b"SCLB";
+
+// Writes a label map file. The header is the magic bytes, the width and height
+// as little endian `u32`, and the size of each label in bytes, followed by the
+// labels as little endian `u32` in row-major order.
+pub fn write_labels(
+    output: &std::path::Path,
+    labels: &[usize],
+    width: u32,
+    height: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    let mut w = std::io::BufWriter::new(std::fs::File::create(output)?);
+    w.write_all(LABEL_MAGIC)?;
+    w.write_all(&width.to_le_bytes())?;
+    w.write_all(&height.to_le_bytes())?;
+    w.write_all(&[4])?;
+    for &label in labels {
+        let label = u32::try_from(label).or(Err("Label does not fit in u32"))?;
+        w.write_all(&label.to_le_bytes())?;
+    }
+    w.flush()?;
+
+    Ok(())
+}
+
+// Summary of a segmentation run for machine-readable output.
+#[derive(Debug)]
+pub struct Summary<'a> {
+    pub algorithm: &'a Algorithm,
+    pub k: u32,
+    pub segments: usize,
+    pub m: u8,
+    pub iterations: Option<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub elapsed: std::time::Duration,
+}
+
+impl Summary<'_> {
+    // Format the summary as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let iterations = self
+            .iterations
+            .map_or_else(|| "null".to_string(), |iter| iter.to_string());
+        format!(
+            "{{\"algorithm\":\"{}\",\"k\":{},\"segments\":{},\"m\":{},\"iterations\":{},\
+             \"width\":{},\"height\":{},\"elapsed_ms\":{}}}",
+            algorithm_name(*self.algorithm),
+            self.k,
+            self.segments,
+            self.m,
+            iterations,
+            self.width,
+            self.height,
+            self.elapsed.as_secs_f64() * 1000.0,
+        )
+    }
+}
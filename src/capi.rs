@@ -0,0 +1,155 @@
+//! C ABI for calling the superpixel functions from other languages.
+//!
+//! Requires the `capi` feature. A shared library can be built with
+//! `cargo rustc --release --lib --no-default-features --features capi
+//! --crate-type cdylib`, which can then be loaded with Python's `ctypes` or
+//! linked from C.
+//!
+//! The functions take a buffer of RGB component bytes and write one `u32`
+//! label per pixel into a caller-allocated buffer. They return [`SC_OK`] on
+//! success or one of the negative `SC_ERR_*` codes.
+#![allow(unsafe_code)]
+
+use crate::error::{ScError, SeedErrorKind};
+
+/// The call succeeded.
+pub const SC_OK: i32 = 0;
+/// The input or output pointer was null.
+pub const SC_ERR_NULL_POINTER: i32 = -1;
+/// The output buffer has fewer elements than the image has pixels.
+pub const SC_ERR_OUTPUT_TOO_SMALL: i32 = -2;
+/// The dimensions, superpixel count, seeds, or input buffer length were
+/// invalid.
+pub const SC_ERR_INVALID_INPUT: i32 = -3;
+/// Memory could not be allocated.
+pub const SC_ERR_ALLOCATION: i32 = -4;
+/// A label didn't fit in `u32`.
+pub const SC_ERR_OVERFLOW: i32 = -5;
+/// Any other error that occurred while clustering.
+pub const SC_ERR_OTHER: i32 = -6;
+
+/// Value of `iter` for [`sc_slic_from_bytes`] which selects the default of `10`
+/// iterations.
+pub const SC_DEFAULT_ITER: u8 = u8::MAX;
+
+/// Calculate SNIC for a buffer of RGB component bytes, writing a label for each
+/// pixel into `out_ptr`.
+///
+/// `len` is the number of bytes at `ptr` and must be `width * height * 3`.
+/// `out_cap` is the number of `u32` elements at `out_ptr` and must be at least
+/// `width * height`. Returns [`SC_OK`] or an error code.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes and `out_ptr` must be valid for
+/// writes of `out_cap` elements. The buffers must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn sc_snic_from_bytes(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    ptr: *const u8,
+    len: usize,
+    out_ptr: *mut u32,
+    out_cap: usize,
+) -> i32 {
+    if ptr.is_null() || out_ptr.is_null() {
+        return SC_ERR_NULL_POINTER;
+    }
+    // SAFETY: The caller guarantees both buffers are valid and don't overlap
+    let (image, output) = unsafe {
+        (
+            std::slice::from_raw_parts(ptr, len),
+            std::slice::from_raw_parts_mut(out_ptr, out_cap),
+        )
+    };
+
+    write_labels(crate::snic_from_bytes(k, m, width, height, image), output)
+}
+
+/// Calculate SLIC for a buffer of RGB component bytes, writing a label for each
+/// pixel into `out_ptr`.
+///
+/// An `iter` of [`SC_DEFAULT_ITER`] uses the default of `10` iterations, and an
+/// `iter` of `0` assigns each pixel to its nearest seed without updating the
+/// centers. `len` is the number of bytes at `ptr` and must be
+/// `width * height * 3`. `out_cap` is the number of `u32` elements at `out_ptr`
+/// and must be at least `width * height`. Returns [`SC_OK`] or an error code.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes and `out_ptr` must be valid for
+/// writes of `out_cap` elements. The buffers must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn sc_slic_from_bytes(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: u8,
+    ptr: *const u8,
+    len: usize,
+    out_ptr: *mut u32,
+    out_cap: usize,
+) -> i32 {
+    if ptr.is_null() || out_ptr.is_null() {
+        return SC_ERR_NULL_POINTER;
+    }
+    // SAFETY: The caller guarantees both buffers are valid and don't overlap
+    let (image, output) = unsafe {
+        (
+            std::slice::from_raw_parts(ptr, len),
+            std::slice::from_raw_parts_mut(out_ptr, out_cap),
+        )
+    };
+    let iter = (iter != SC_DEFAULT_ITER).then_some(iter);
+
+    write_labels(
+        crate::slic_from_bytes(k, m, width, height, iter, image),
+        output,
+    )
+}
+
+/// Copy the labels into `output` and convert the result into an error code.
+fn write_labels(labels: Result<Vec<usize>, ScError>, output: &mut [u32]) -> i32 {
+    let labels = match labels {
+        Ok(labels) => labels,
+        Err(err) => return error_code(&err),
+    };
+    if output.len() < labels.len() {
+        return SC_ERR_OUTPUT_TOO_SMALL;
+    }
+    for (out, &label) in output.iter_mut().zip(&labels) {
+        match u32::try_from(label) {
+            Ok(label) => *out = label,
+            Err(_) => return SC_ERR_OVERFLOW,
+        }
+    }
+
+    SC_OK
+}
+
+/// Error code for an error returned by the clustering functions.
+fn error_code(err: &ScError) -> i32 {
+    match err {
+        ScError::InvalidImageDimension
+        | ScError::ZeroSuperpixelCount
+        | ScError::InvalidSuperpixelCount
+        | ScError::ZeroGridInterval
+        | ScError::InvalidGridInterval
+        | ScError::MismatchedSlicBuffer
+        | ScError::MismatchedSnicBuffer
+        | ScError::InvalidByteBufferLength { .. }
+        | ScError::UnachievableSuperpixelCount { .. }
+        | ScError::SeedError(SeedErrorKind::InvalidImageIndex | SeedErrorKind::EmptySeeds) => {
+            SC_ERR_INVALID_INPUT
+        }
+        ScError::Reserve(_) => SC_ERR_ALLOCATION,
+        ScError::Overflow(_) | ScError::IndexOverflow { .. } => SC_ERR_OVERFLOW,
+        ScError::NanDistance { .. }
+        | ScError::SeedError(SeedErrorKind::InvalidTotalSeeds | SeedErrorKind::PerturbConversion)
+        | ScError::SuperpixelCountMismatch { .. }
+        | ScError::General(_) => SC_ERR_OTHER,
+    }
+}
@@ -0,0 +1,220 @@
+//! Regions of an edge map that superpixels are constrained to.
+use crate::error::ScError;
+use crate::options::{Connectivity, Options};
+use crate::{
+    checked_coords, get_in_bounds, index, index_to_xy, pixel_count, wrap_x, ImageView, Superpixel,
+};
+
+use palette::Lab;
+
+/// Region index of edge pixels.
+const EDGE: usize = usize::MAX;
+
+/// Connected regions of the non-edge pixels of an edge map, and the region of
+/// each cluster.
+///
+/// Non-edge pixels may only join clusters seeded in their region, which stops
+/// clusters from growing across a line of edge pixels. Edge pixels may join
+/// any cluster.
+#[derive(Debug, Clone)]
+pub(crate) struct EdgeRegions {
+    /// Region index of each pixel, or [`EDGE`] for edge pixels.
+    pixels: Vec<usize>,
+    /// Region of each cluster, or `None` if it's surrounded by edge pixels.
+    clusters: Vec<Option<usize>>,
+    /// Number of regions.
+    regions: usize,
+    /// Width of the image in pixels.
+    width: u32,
+}
+
+impl EdgeRegions {
+    /// Find the regions of the non-edge pixels of `edges`, connected through
+    /// the pixel neighborhood of `connectivity`.
+    pub(crate) fn new(
+        edges: &[bool],
+        width: u32,
+        height: u32,
+        connectivity: Connectivity,
+        wrap: bool,
+    ) -> Result<Self, ScError> {
+        if edges.len() != pixel_count(width, height)? {
+            return Err(ScError::General("Edge map does not match image dimensions"));
+        }
+        let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+        let width_i = i64::from(width);
+        let height_i = i64::from(height);
+
+        let mut pixels = Vec::new();
+        pixels.try_reserve_exact(edges.len())?;
+        pixels.extend(edges.iter().map(|&edge| if edge { EDGE } else { 0 }));
+        let mut visited = Vec::new();
+        visited.try_reserve_exact(edges.len())?;
+        visited.extend(edges.iter().copied());
+
+        let mut region = 0;
+        let mut queue = Vec::new();
+        for start in 0..edges.len() {
+            if visited.get(start) != Some(&false) {
+                continue;
+            }
+            if let Some(v) = visited.get_mut(start) {
+                *v = true;
+            }
+            queue.clear();
+            queue.push(start);
+            while let Some(idx) = queue.pop() {
+                if let Some(p) = pixels.get_mut(idx) {
+                    *p = region;
+                }
+                let (x, y) = index_to_xy(idx, width_usize)?;
+                let (x, y) = (i64::from(x), i64::from(y));
+                for &(d_x, d_y) in connectivity.offsets() {
+                    let n_x = wrap_x(width_i, x + d_x, wrap);
+                    let n_y = y + d_y;
+                    if get_in_bounds(width_i, height_i, n_x, n_y, &visited) != Some(&false) {
+                        continue;
+                    }
+                    let (n_x, n_y) = checked_coords(n_x, n_y)?;
+                    let n_idx = index(width, n_x, n_y)?;
+                    if let Some(v) = visited.get_mut(n_idx) {
+                        *v = true;
+                    }
+                    queue.try_reserve(1)?;
+                    queue.push(n_idx);
+                }
+            }
+            region += 1;
+        }
+
+        Ok(Self {
+            pixels,
+            clusters: Vec::new(),
+            regions: region,
+            width,
+        })
+    }
+
+    /// Assign each cluster the region of its seed pixel, or of the first
+    /// non-edge pixel in its 3x3 neighborhood if the seed is on an edge.
+    ///
+    /// Regions without a seed would never be labeled, so a cluster is added at
+    /// the first pixel of each of them.
+    pub(crate) fn assign_clusters<Wp>(
+        &mut self,
+        image: ImageView<'_, Lab<Wp, f64>>,
+        clusters: &mut Vec<Superpixel<Lab<Wp, f64>>>,
+    ) -> Result<(), ScError> {
+        self.clusters.clear();
+        self.clusters.try_reserve_exact(clusters.len())?;
+        for cluster in clusters.iter() {
+            let region = self.seed_region(cluster.x, cluster.y);
+            self.clusters.push(region);
+        }
+
+        let mut seeded = Vec::new();
+        seeded.try_reserve_exact(self.regions)?;
+        seeded.extend((0..self.regions).map(|_| false));
+        for &region in self.clusters.iter().flatten() {
+            if let Some(s) = seeded.get_mut(region) {
+                *s = true;
+            }
+        }
+
+        let width = usize::try_from(self.width).or(Err("Could not convert width to usize"))?;
+        for (idx, &region) in self.pixels.iter().enumerate() {
+            if seeded.get(region) != Some(&false) {
+                continue;
+            }
+            let (x, y) = index_to_xy(idx, width)?;
+            let data = *image
+                .get(i64::from(x), i64::from(y))
+                .ok_or("Region seed out of bounds")?;
+            clusters.try_reserve(1)?;
+            clusters.push(Superpixel { data, x, y });
+            self.clusters.try_reserve(1)?;
+            self.clusters.push(Some(region));
+            if let Some(s) = seeded.get_mut(region) {
+                *s = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update the region of a cluster which was moved to `(x, y)`.
+    pub(crate) fn reassign_cluster(&mut self, cluster: usize, x: u32, y: u32) {
+        let region = self.seed_region(x, y);
+        if let Some(c) = self.clusters.get_mut(cluster) {
+            *c = region;
+        }
+    }
+
+    /// Check whether the pixel at `idx` may join `cluster`.
+    #[inline]
+    pub(crate) fn allows(&self, idx: usize, cluster: usize) -> bool {
+        match self.pixels.get(idx) {
+            Some(&region) if region != EDGE => {
+                self.clusters.get(cluster).copied().flatten() == Some(region)
+            }
+            _ => true,
+        }
+    }
+
+    /// Region of a seed at `(x, y)`.
+    fn seed_region(&self, x: u32, y: u32) -> Option<usize> {
+        let width = i64::from(self.width);
+        let height = i64::try_from(self.pixels.len()).ok()?.checked_div(width)?;
+        [
+            (0, 0),
+            (-1, 0),
+            (0, -1),
+            (1, 0),
+            (0, 1),
+            (-1, -1),
+            (1, -1),
+            (1, 1),
+            (-1, 1),
+        ]
+        .iter()
+        .filter_map(|&(d_x, d_y)| {
+            get_in_bounds(
+                width,
+                height,
+                i64::from(x) + d_x,
+                i64::from(y) + d_y,
+                &self.pixels,
+            )
+        })
+        .copied()
+        .find(|&region| region != EDGE)
+    }
+}
+
+/// Check whether the pixel at `idx` may join `cluster`, which is always the
+/// case without an edge map.
+#[inline]
+pub(crate) fn allows(regions: Option<&EdgeRegions>, idx: usize, cluster: usize) -> bool {
+    match regions {
+        Some(regions) => regions.allows(idx, cluster),
+        None => true,
+    }
+}
+
+/// Find the edge regions of `options.edges` if it's set and assign the
+/// clusters to them, adding clusters for regions without a seed.
+pub(crate) fn edge_regions<Wp>(
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: ImageView<'_, Lab<Wp, f64>>,
+    clusters: &mut Vec<Superpixel<Lab<Wp, f64>>>,
+) -> Result<Option<EdgeRegions>, ScError> {
+    let Some(edges) = &options.edges else {
+        return Ok(None);
+    };
+    let mut regions = EdgeRegions::new(edges, width, height, options.connectivity, options.wrap_x)?;
+    regions.assign_clusters(image, clusters)?;
+
+    Ok(Some(regions))
+}
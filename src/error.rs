@@ -13,19 +13,66 @@ pub enum ScError {
     InvalidSuperpixelCount,
     /// The calculated grid interval is equal to `0`.
     ZeroGridInterval,
-    /// The calculated grid interval is too large.
+    /// The grid interval is too large for the image or doesn't fit in `u32`.
     InvalidGridInterval,
     /// The SLIC image buffer length does not match the dimensions.
     MismatchedSlicBuffer,
     /// The SNIC image buffer length does not match the dimensions.
     MismatchedSnicBuffer,
-    /// A distance calculated during SNIC resulted in a NaN.
-    NanDistance,
+    /// The length of a buffer of color components is not a multiple of the
+    /// number of components per pixel, so it can't hold whole pixels.
+    InvalidByteBufferLength {
+        /// Length of the buffer.
+        len: usize,
+        /// Number of components per pixel.
+        channels: usize,
+    },
+    /// A distance calculated during SNIC resulted in a NaN, such as for a
+    /// pixel with a NaN color component.
+    NanDistance {
+        /// X-coordinate of the pixel whose distance was NaN.
+        x: u32,
+        /// Y-coordinate of the pixel whose distance was NaN.
+        y: u32,
+    },
     /// An error occured while initializing or perturbing superpixel seeds.
     SeedError(SeedErrorKind),
     /// Space could not be reserved for a collection required in superpixel
     /// calculation.
     Reserve(TryReserveError),
+    /// An arithmetic overflow occurred while calculating the named quantity,
+    /// such as a buffer size derived from the image dimensions.
+    Overflow(&'static str),
+    /// The buffer index of the pixel at `(x, y)` doesn't fit in `usize`.
+    IndexOverflow {
+        /// X-coordinate of the pixel.
+        x: u32,
+        /// Y-coordinate of the pixel.
+        y: u32,
+    },
+    /// The number of superpixels found is outside of the tolerance set with
+    /// [`Options::count_tolerance`](crate::options::Options::count_tolerance).
+    SuperpixelCountMismatch {
+        /// The requested number of superpixels, `k`.
+        requested: u32,
+        /// The number of superpixels found.
+        realized: usize,
+    },
+    /// The number of superpixels requested is more than twice the number of
+    /// seeds that fit on the seed grid of the image, so the count can't be
+    /// approached. See [`grid_dimensions`](crate::seed::grid_dimensions).
+    ///
+    /// This is also returned if
+    /// [`Options::count_tolerance`](crate::options::Options::count_tolerance)
+    /// is set and the number requested is more than
+    /// [`max_superpixel_count`](crate::seed::max_superpixel_count).
+    UnachievableSuperpixelCount {
+        /// The requested number of superpixels, `k`.
+        requested: u32,
+        /// The number of seeds on the seed grid for `k`, or the maximum count
+        /// for the image with a count tolerance set.
+        achievable: u32,
+    },
     /// A general error occurred.
     General(&'static str),
 }
@@ -40,16 +87,40 @@ impl std::fmt::Display for ScError {
                 "Number of superpixels greater than or equal to pixels in image"
             ),
             Self::ZeroGridInterval => write!(f, "Grid interval cannot be 0"),
-            Self::InvalidGridInterval => write!(f, "Grid interval larger than u32"),
+            Self::InvalidGridInterval => write!(f, "Grid interval too large"),
             Self::MismatchedSlicBuffer => {
                 write!(f, "SLIC buffer length does not equal image dimensions")
             }
             Self::MismatchedSnicBuffer => {
                 write!(f, "SNIC buffer length does not equal image dimensions")
             }
-            Self::NanDistance => write!(f, "NaN encountered during SNIC"),
+            Self::InvalidByteBufferLength { len, channels } => write!(
+                f,
+                "Buffer length {len} is not a multiple of {channels} components per pixel"
+            ),
+            Self::NanDistance { x, y } => {
+                write!(f, "NaN encountered during SNIC at pixel ({x}, {y})")
+            }
+            Self::IndexOverflow { x, y } => {
+                write!(f, "Index of pixel ({x}, {y}) overflows usize")
+            }
             Self::SeedError(e) => write!(f, "{e}"),
             Self::Reserve(e) => write!(f, "{e}"),
+            Self::Overflow(e) => write!(f, "Overflow while calculating {e}"),
+            Self::SuperpixelCountMismatch {
+                requested,
+                realized,
+            } => write!(
+                f,
+                "Found {realized} superpixels, outside of tolerance for {requested} requested"
+            ),
+            Self::UnachievableSuperpixelCount {
+                requested,
+                achievable,
+            } => write!(
+                f,
+                "Requested {requested} superpixels, but at most {achievable} fit the image"
+            ),
             Self::General(e) => write!(f, "{e}"),
         }
     }
@@ -64,6 +135,8 @@ pub enum SeedErrorKind {
     InvalidTotalSeeds,
     /// An integer conversion error occurred while perturbing superpixel seeds.
     PerturbConversion,
+    /// No seed points were supplied.
+    EmptySeeds,
 }
 
 impl std::fmt::Display for SeedErrorKind {
@@ -74,6 +147,7 @@ impl std::fmt::Display for SeedErrorKind {
             }
             Self::InvalidTotalSeeds => write!(f, "Total number of seeds too large"),
             Self::PerturbConversion => write!(f, "Could not convert integer in seed perturbation"),
+            Self::EmptySeeds => write!(f, "No seed points supplied"),
         }
     }
 }
@@ -89,8 +163,13 @@ impl std::error::Error for ScError {
             | Self::InvalidGridInterval
             | Self::MismatchedSlicBuffer
             | Self::MismatchedSnicBuffer
-            | Self::NanDistance
+            | Self::InvalidByteBufferLength { .. }
+            | Self::NanDistance { .. }
             | Self::SeedError(_)
+            | Self::Overflow(_)
+            | Self::IndexOverflow { .. }
+            | Self::SuperpixelCountMismatch { .. }
+            | Self::UnachievableSuperpixelCount { .. }
             | Self::General(_) => None,
         }
     }
@@ -107,3 +186,37 @@ impl std::convert::From<&'static str> for ScError {
         Self::General(error)
     }
 }
+
+impl std::convert::From<ScError> for std::io::Error {
+    /// Convert into an [`std::io::Error`] of kind
+    /// [`InvalidInput`](std::io::ErrorKind::InvalidInput) for invalid
+    /// parameters or buffers, or [`Other`](std::io::ErrorKind::Other) for
+    /// failures during calculation.
+    fn from(error: ScError) -> Self {
+        let kind = match error {
+            ScError::InvalidImageDimension
+            | ScError::ZeroSuperpixelCount
+            | ScError::InvalidSuperpixelCount
+            | ScError::ZeroGridInterval
+            | ScError::InvalidGridInterval
+            | ScError::MismatchedSlicBuffer
+            | ScError::MismatchedSnicBuffer
+            | ScError::InvalidByteBufferLength { .. }
+            | ScError::UnachievableSuperpixelCount { .. }
+            | ScError::SeedError(SeedErrorKind::InvalidImageIndex | SeedErrorKind::EmptySeeds) => {
+                std::io::ErrorKind::InvalidInput
+            }
+            ScError::NanDistance { .. }
+            | ScError::SeedError(
+                SeedErrorKind::InvalidTotalSeeds | SeedErrorKind::PerturbConversion,
+            )
+            | ScError::Reserve(_)
+            | ScError::Overflow(_)
+            | ScError::IndexOverflow { .. }
+            | ScError::SuperpixelCountMismatch { .. }
+            | ScError::General(_) => std::io::ErrorKind::Other,
+        };
+
+        Self::new(kind, error)
+    }
+}
@@ -1,16 +1,65 @@
 //! Functions for interacting with image labels and manipulating images.
-use crate::{error::ScError, get_in_bounds};
-use fxhash::{FxHashMap, FxHashSet};
-use palette::{encoding, rgb::Rgb, white_point::WhitePoint, IntoColor, Lab, Srgb};
+use crate::options::{Algorithm, Connectivity, GamutMapping, WorkingImage};
+use crate::{
+    check_component_buffer, checked_coords, distance_lab, distance_s, distance_xy, error::ScError,
+    get_in_bounds, index, index_to_xy, m_div_s, pixel_count, seed::gradient,
+    srgb_components_to_lab, wrap_x, ImageView,
+};
+use num_traits::ToPrimitive;
+use palette::convert::IntoColorUnclamped;
+use palette::{
+    encoding, rgb::Rgb, white_point::WhitePoint, Clamp, FromColor, Hsv, IntoColor, Lab, Srgb,
+};
+use std::fmt::Write;
+
+/// Hash map used for per-label data in this module.
+///
+/// This is `FxHashMap` from `fxhash` if the `fxhash` feature is enabled, which
+/// is the default. Otherwise, or if the `std-hash` feature is enabled, it is the
+/// standard library `HashMap` with its default SipHash hasher.
+#[cfg(all(feature = "fxhash", not(feature = "std-hash")))]
+pub type HashMap<K, V> = fxhash::FxHashMap<K, V>;
+/// Hash map used for per-label data in this module.
+///
+/// This is `FxHashMap` from `fxhash` if the `fxhash` feature is enabled, which
+/// is the default. Otherwise, or if the `std-hash` feature is enabled, it is the
+/// standard library `HashMap` with its default SipHash hasher.
+#[cfg(any(not(feature = "fxhash"), feature = "std-hash"))]
+pub type HashMap<K, V> = std::collections::HashMap<K, V>;
+
+/// Hash set used for per-label data in this module, using the same hasher as
+/// [`HashMap`].
+#[cfg(all(feature = "fxhash", not(feature = "std-hash")))]
+pub type HashSet<T> = fxhash::FxHashSet<T>;
+/// Hash set used for per-label data in this module, using the same hasher as
+/// [`HashMap`].
+#[cfg(any(not(feature = "fxhash"), feature = "std-hash"))]
+pub type HashSet<T> = std::collections::HashSet<T>;
 
 /// Count the number of unique labels in a slice of superpixel labels.
 pub fn count_colors(labels: &[usize]) -> usize {
-    labels.iter().copied().collect::<FxHashSet<usize>>().len()
+    labels.iter().copied().collect::<HashSet<usize>>().len()
+}
+
+/// Count the number of pixels with each label in a slice of superpixel labels.
+pub fn superpixel_sizes(labels: &[usize]) -> Result<HashMap<usize, usize>, ScError> {
+    let mut sizes = HashMap::<usize, usize>::default();
+    for &label in labels {
+        if !sizes.contains_key(&label) {
+            sizes.try_reserve(1)?;
+        }
+        *sizes.entry(label).or_default() += 1;
+    }
+
+    Ok(sizes)
 }
 
 /// Modify `output` to contain an RGB image of superpixel segments filled with
 /// the mean color of that region. The return value is the count of superpixels
 /// in the image.
+///
+/// Mean colors outside of the sRGB gamut have their RGB components clipped,
+/// see [`mean_colors_with_gamut`] to reduce their chroma instead.
 pub fn mean_colors<Wp>(
     output: &mut [u8],
     k: usize,
@@ -19,32 +68,382 @@ pub fn mean_colors<Wp>(
 ) -> Result<usize, ScError>
 where
     Wp: WhitePoint<f64>,
-    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
 {
-    if Some(output.len()) != image.len().checked_mul(3) {
+    mean_colors_with_gamut(output, k, labels, image, GamutMapping::Clip)
+}
+
+/// Modify `output` to contain an image of superpixel segments filled with the
+/// mean color of that region, like [`mean_colors`] but with one `Srgb<u8>` per
+/// pixel instead of three component bytes. The return value is the count of
+/// superpixels in the image.
+///
+/// `output` must have the same length as `image`.
+///
+/// ```
+/// use palette::{FromColor, Lab, Srgb};
+/// use simple_clustering::image::mean_colors_srgb;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [Lab::from_color(Srgb::new(1.0, 0.5, 0.0)); 2];
+/// let mut output = [Srgb::new(0, 0, 0); 2];
+/// assert_eq!(mean_colors_srgb(&mut output, 1, &[0, 0], &image)?, 1);
+/// assert_eq!(output[0], Srgb::new(255, 128, 0));
+/// # Ok(())
+/// # }
+/// ```
+pub fn mean_colors_srgb<Wp>(
+    output: &mut [Srgb<u8>],
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+{
+    if output.len() != image.len() {
         return Err(ScError::General(
             "Mean color buffer does not match image length",
         ));
     }
 
-    let mut map = FxHashMap::<usize, (Lab<Wp, f64>, f64)>::default();
-    map.try_reserve(k)?;
+    mean_colors(
+        palette::cast::into_component_slice_mut(output),
+        k,
+        labels,
+        image,
+    )
+}
 
-    for (&idx, &color) in labels.iter().zip(image.iter()) {
-        let _ = map
-            .entry(idx)
-            .and_modify(|e| {
-                e.0 += color;
-                e.1 += 1.0;
-            })
-            .or_insert((color, 1.0));
+/// Call `f` with the label and mean color of each superpixel region instead of
+/// writing an image. The return value is the count of superpixels in the
+/// image.
+///
+/// `f` is called once per label, in increasing label order, after every pixel
+/// has been accumulated. This lets the caller paint or stream each region into
+/// an output that isn't a contiguous RGB buffer. Mean colors outside of the
+/// sRGB gamut have their RGB components clipped, like [`mean_colors`].
+///
+/// `labels` must be the same length as `image`.
+///
+/// ```
+/// use palette::{FromColor, Lab, Srgb};
+/// use simple_clustering::image::mean_colors_for_each;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [
+///     Lab::from_color(Srgb::new(1.0, 0.0, 0.0)),
+///     Lab::from_color(Srgb::new(0.0, 0.0, 1.0)),
+/// ];
+/// let mut regions = Vec::new();
+/// let count = mean_colors_for_each(2, &[4, 1], &image, |label, color| {
+///     regions.push((label, color));
+/// })?;
+/// assert_eq!(count, 2);
+/// assert_eq!(regions, [(1, Srgb::new(0, 0, 255)), (4, Srgb::new(255, 0, 0))]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn mean_colors_for_each<Wp, F>(
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    mut f: F,
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+    F: FnMut(usize, Srgb<u8>),
+{
+    if labels.len() != image.len() {
+        return Err(ScError::General("Label buffer does not match image length"));
+    }
+
+    let mut map = HashMap::default();
+    accumulate_label_colors(&mut map, k, labels, image)?;
+
+    let mut regions = Vec::new();
+    regions.try_reserve_exact(map.len())?;
+    regions.extend(
+        map.iter()
+            .map(|(&label, &(color, count))| (label, color / count)),
+    );
+    regions.sort_unstable_by_key(|&(label, _)| label);
+
+    for (label, mean) in regions {
+        f(label, map_to_gamut(mean, GamutMapping::Clip).into_format());
+    }
+
+    Ok(map.len())
+}
+
+/// Modify `output` to contain an RGB image where pixels within `band` pixels of
+/// a superpixel boundary are filled with the mean color of their region, and
+/// the other pixels keep their original color. The return value is the count
+/// of superpixels in the image.
+///
+/// A pixel with a 4-connected neighbor of a different label is `1` pixel from
+/// a boundary, and the distance of the other pixels is counted in 4-connected
+/// steps from the nearest of those. The edges of the image are not boundaries.
+/// A `band` of `0` keeps every original color, and a `band` as wide as the
+/// regions recolors the whole image like [`mean_colors`]. Colors outside of the
+/// sRGB gamut have their RGB components clipped.
+///
+/// `labels` and `image` must both have a length of `width * height`, and
+/// `output` must have a length of `width * height * 3`.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::image::mean_colors_boundary_band;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let labels = [0, 0, 0, 1];
+/// let image = [
+///     Lab::<D65, f64>::new(0.0, 0.0, 0.0),
+///     Lab::new(100.0, 0.0, 0.0),
+///     Lab::new(100.0, 0.0, 0.0),
+///     Lab::new(100.0, 0.0, 0.0),
+/// ];
+/// let mut output = [0; 12];
+/// assert_eq!(mean_colors_boundary_band(&mut output, 4, 1, &labels, &image, 1)?, 2);
+/// // Only the pixel next to label `1` takes the mean of label `0`
+/// assert_eq!(output[..6], [0, 0, 0, 255, 255, 255]);
+/// assert_ne!(output[6..9], [255, 255, 255]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn mean_colors_boundary_band<Wp>(
+    output: &mut [u8],
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    band: u32,
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+{
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || image.len() != len {
+        return Err(ScError::General(
+            "Label or image buffer does not match image dimensions",
+        ));
+    }
+    if Some(output.len()) != len.checked_mul(3) {
+        return Err(ScError::General(
+            "Mean color buffer does not match image length",
+        ));
+    }
+
+    let mut map = HashMap::default();
+    accumulate_label_colors(&mut map, 0, labels, image)?;
+    let mut rgb_map = HashMap::<usize, Srgb<u8>>::default();
+    rgb_map.try_reserve(map.len())?;
+    rgb_map.extend(map.iter().map(|(&key, &(color, count))| {
+        let rgb: Srgb<u8> = map_to_gamut(color / count, GamutMapping::Clip).into_format();
+        (key, rgb)
+    }));
+
+    // Distance of each pixel from a boundary, up to `band`
+    let mut distances = Vec::new();
+    distances.try_reserve_exact(len)?;
+    distances.extend((0..len).map(|_| u32::MAX));
+    let mut queue = std::collections::VecDeque::new();
+    let width_i = i64::from(width);
+    let height_i = i64::from(height);
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+
+    if band > 0 {
+        for (idx, (&label, distance)) in labels.iter().zip(distances.iter_mut()).enumerate() {
+            let (x, y) = index_to_xy(idx, width_usize)?;
+            let on_boundary = Connectivity::Four.offsets().iter().any(|&(d_x, d_y)| {
+                get_in_bounds(
+                    width_i,
+                    height_i,
+                    i64::from(x) + d_x,
+                    i64::from(y) + d_y,
+                    labels,
+                )
+                .is_some_and(|&neighbor| neighbor != label)
+            });
+            if on_boundary {
+                *distance = 1;
+                queue.try_reserve(1)?;
+                queue.push_back((x, y));
+            }
+        }
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = *distances
+            .get(index(width, x, y)?)
+            .ok_or("Label index out of bounds")?;
+        if distance >= band {
+            continue;
+        }
+        for &(d_x, d_y) in Connectivity::Four.offsets() {
+            let n_x = i64::from(x) + d_x;
+            let n_y = i64::from(y) + d_y;
+            if get_in_bounds(width_i, height_i, n_x, n_y, &distances) != Some(&u32::MAX) {
+                continue;
+            }
+            let (n_x, n_y) = checked_coords(n_x, n_y)?;
+            if let Some(d) = distances.get_mut(index(width, n_x, n_y)?) {
+                *d = distance + 1;
+            }
+            queue.try_reserve(1)?;
+            queue.push_back((n_x, n_y));
+        }
+    }
+
+    for (chunk, ((&label, &color), &distance)) in output
+        .chunks_exact_mut(3)
+        .zip(labels.iter().zip(image).zip(&distances))
+    {
+        let rgb = if distance <= band {
+            rgb_map.get(&label).copied()
+        } else {
+            Some(map_to_gamut(color, GamutMapping::Clip).into_format())
+        };
+        if let Some(rgb) = rgb {
+            chunk.copy_from_slice((&rgb).into());
+        }
+    }
+
+    Ok(map.len())
+}
+
+/// Modify `output` to contain an RGB image of superpixel segments filled with
+/// the mean color of that region, converting out of gamut mean colors with
+/// `gamut_mapping`. The return value is the count of superpixels in the image.
+///
+/// ```
+/// use palette::{FromColor, Lab, Srgb};
+/// use simple_clustering::{image::mean_colors_with_gamut, options::GamutMapping};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // A region of saturated red and blue averages outside of the gamut
+/// let image = [
+///     Lab::from_color(Srgb::new(1.0, 0.0, 0.0)),
+///     Lab::from_color(Srgb::new(0.0, 0.0, 1.0)),
+/// ];
+/// let labels = [0, 0];
+/// let mut clipped = [0; 6];
+/// let mut mapped = [0; 6];
+/// mean_colors_with_gamut(&mut clipped, 1, &labels, &image, GamutMapping::Clip)?;
+/// mean_colors_with_gamut(&mut mapped, 1, &labels, &image, GamutMapping::ReduceChroma)?;
+/// assert_ne!(clipped, mapped);
+/// # Ok(())
+/// # }
+/// ```
+pub fn mean_colors_with_gamut<Wp>(
+    output: &mut [u8],
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    gamut_mapping: GamutMapping,
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+{
+    mean_colors_with_scratch(
+        output,
+        k,
+        labels,
+        image,
+        gamut_mapping,
+        &mut MeanColorScratch::new(),
+    )
+}
+
+/// Reusable maps for [`mean_colors_with_scratch`].
+///
+/// The maps are cleared on each call but keep their capacity, so recoloring a
+/// sequence of frames only allocates when a frame has more superpixels than
+/// the previous ones.
+#[derive(Debug, Clone)]
+pub struct MeanColorScratch<Wp> {
+    /// Accumulated color and pixel count of each label.
+    map: HashMap<usize, (Lab<Wp, f64>, f64)>,
+    /// Mean color of each label.
+    rgb_map: HashMap<usize, Srgb<u8>>,
+}
+
+impl<Wp> Default for MeanColorScratch<Wp> {
+    fn default() -> Self {
+        Self {
+            map: HashMap::default(),
+            rgb_map: HashMap::default(),
+        }
+    }
+}
+
+impl<Wp> MeanColorScratch<Wp> {
+    /// Create an empty [`MeanColorScratch`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Modify `output` to contain an RGB image of superpixel segments filled with
+/// the mean color of that region like [`mean_colors_with_gamut`], reusing the
+/// maps of `scratch` between calls. The return value is the count of
+/// superpixels in the image.
+///
+/// ```
+/// use palette::Lab;
+/// use simple_clustering::image::{mean_colors_with_scratch, MeanColorScratch};
+/// use simple_clustering::options::GamutMapping;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let mut scratch = MeanColorScratch::new();
+/// let mut output = [0; 12];
+/// for frame in 0..3 {
+///     let image = [Lab::new(f64::from(frame) * 10.0, 0.0, 0.0); 4];
+///     let labels = [0, 0, 1, 1];
+///     let count = mean_colors_with_scratch(
+///         &mut output,
+///         2,
+///         &labels,
+///         &image,
+///         GamutMapping::Clip,
+///         &mut scratch,
+///     )?;
+///     assert_eq!(count, 2);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn mean_colors_with_scratch<Wp>(
+    output: &mut [u8],
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    gamut_mapping: GamutMapping,
+    scratch: &mut MeanColorScratch<Wp>,
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+{
+    if Some(output.len()) != image.len().checked_mul(3) {
+        return Err(ScError::General(
+            "Mean color buffer does not match image length",
+        ));
     }
 
-    let mut rgb_map = FxHashMap::<usize, Srgb<u8>>::default();
+    let MeanColorScratch { map, rgb_map } = scratch;
+    accumulate_label_colors(map, k, labels, image)?;
+
+    rgb_map.clear();
     rgb_map.try_reserve(map.len())?;
 
     rgb_map.extend(map.iter().map(|(&key, &(color, count))| {
-        let rgb: Srgb<u8> = (color / count).into_color().into_format();
+        let rgb: Srgb<u8> = map_to_gamut(color / count, gamut_mapping).into_format();
         (key, rgb)
     }));
 
@@ -56,65 +455,3550 @@ where
     Ok(map.len())
 }
 
-/// Modify `output` to contain an RGB image with colored contours based on
-/// superpixel labels.
-pub fn segment_contours(
+/// Modify `output` to contain an RGB image of superpixel segments filled with
+/// the mean color of that region, at a resolution of `out_width` x
+/// `out_height` instead of the resolution of `image`. The return value is the
+/// count of superpixels in the image.
+///
+/// The mean colors are calculated from every pixel of `image`, then each output
+/// pixel takes the mean color of the source pixel nearest to its center. This
+/// avoids recoloring at full resolution before downscaling a preview. With
+/// nearest sampling, region boundaries stay hard-edged rather than blended, and
+/// regions narrower than the scaling factor can be skipped entirely or appear
+/// jagged. Mean colors outside of the sRGB gamut have their RGB components
+/// clipped, like [`mean_colors`].
+///
+/// `labels` and `image` must both have a length of `width * height`, and
+/// `output` must have a length of `out_width * out_height * 3`.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::image::mean_colors_resized;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // A black left half and a white right half, downscaled from 4x2 to 2x1
+/// let labels = [0, 0, 1, 1, 0, 0, 1, 1];
+/// let image: Vec<_> = labels
+///     .iter()
+///     .map(|&l| Lab::<D65, f64>::new(if l == 0 { 0.0 } else { 100.0 }, 0.0, 0.0))
+///     .collect();
+/// let mut output = [0; 6];
+/// let count = mean_colors_resized(&mut output, 2, 1, 4, 2, &labels, &image)?;
+/// assert_eq!(count, 2);
+/// assert_eq!(output, [0, 0, 0, 255, 255, 255]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn mean_colors_resized<Wp>(
     output: &mut [u8],
+    out_width: u32,
+    out_height: u32,
     width: u32,
     height: u32,
     labels: &[usize],
-    segment_color: [u8; 3],
-) -> Result<(), ScError> {
-    let mut segment = Vec::new();
-    segment.try_reserve_exact(labels.len())?;
-    segment.extend((0..labels.len()).map(|_| false));
+    image: &[Lab<Wp, f64>],
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+{
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || image.len() != len {
+        return Err(ScError::General(
+            "Label or image buffer does not match image dimensions",
+        ));
+    }
+    if Some(output.len()) != pixel_count(out_width, out_height)?.checked_mul(3) {
+        return Err(ScError::General(
+            "Mean color buffer does not match output dimensions",
+        ));
+    }
 
-    let width_i = i64::from(width);
-    let height_i = i64::from(height);
+    let mut map = HashMap::default();
+    accumulate_label_colors(&mut map, 0, labels, image)?;
+    let mut rgb_map = HashMap::<usize, Srgb<u8>>::default();
+    rgb_map.try_reserve(map.len())?;
+    rgb_map.extend(map.iter().map(|(&key, &(color, count))| {
+        let rgb: Srgb<u8> = map_to_gamut(color / count, GamutMapping::Clip).into_format();
+        (key, rgb)
+    }));
 
-    let mut chunks_iter = output.chunks_exact_mut(3).enumerate();
-    let mut label_iter = labels.iter();
-    for y in 0..height_i {
-        for x in 0..width_i {
-            let label = label_iter.next().ok_or("Labels exhausted")?;
-            let (chunk_idx, chunk) = chunks_iter.next().ok_or("Chunks exhausted")?;
-            let neighbors = [
-                get_in_bounds(width_i, height_i, x - 1, y, labels),
-                get_in_bounds(width_i, height_i, x - 1, y - 1, labels),
-                get_in_bounds(width_i, height_i, x, y - 1, labels),
-                get_in_bounds(width_i, height_i, x + 1, y - 1, labels),
-                get_in_bounds(width_i, height_i, x + 1, y, labels),
-                get_in_bounds(width_i, height_i, x + 1, y + 1, labels),
-                get_in_bounds(width_i, height_i, x, y + 1, labels),
-                get_in_bounds(width_i, height_i, x - 1, y + 1, labels),
-            ];
-            let neighbor_segments = [
-                get_in_bounds(width_i, height_i, x - 1, y, &segment),
-                get_in_bounds(width_i, height_i, x - 1, y - 1, &segment),
-                get_in_bounds(width_i, height_i, x, y - 1, &segment),
-                get_in_bounds(width_i, height_i, x + 1, y - 1, &segment),
-                get_in_bounds(width_i, height_i, x + 1, y, &segment),
-                get_in_bounds(width_i, height_i, x + 1, y + 1, &segment),
-                get_in_bounds(width_i, height_i, x, y + 1, &segment),
-                get_in_bounds(width_i, height_i, x - 1, y + 1, &segment),
-            ];
-
-            // Count neighboring labels that are different from current label
-            // and aren't already a border segment
-            if neighbors
-                .iter()
-                .zip(neighbor_segments.iter())
-                .filter(|(&n, &ns)| ns == Some(&false) && n != Some(label))
-                .count()
-                >= 2
-            {
-                chunk.copy_from_slice(&segment_color);
-                if let Some(s) = segment.get_mut(chunk_idx) {
-                    *s = true;
-                }
+    // Source coordinate nearest to the center of output coordinate `o`
+    let nearest = |o: u32, out: u32, src: u32| {
+        let s = (2 * u64::from(o) + 1) * u64::from(src) / (2 * u64::from(out));
+        u32::try_from(s).or(Err("Invalid source coordinate"))
+    };
+
+    let mut chunks = output.chunks_exact_mut(3);
+    for o_y in 0..out_height {
+        let y = nearest(o_y, out_height, height)?;
+        for o_x in 0..out_width {
+            let x = nearest(o_x, out_width, width)?;
+            let idx = index(width, x, y)?;
+            let chunk = chunks.next().ok_or("Output exhausted")?;
+            if let Some(color) = labels.get(idx).and_then(|label| rgb_map.get(label)) {
+                chunk.copy_from_slice(color.into());
             }
         }
     }
 
+    Ok(map.len())
+}
+
+/// Modify `output` to contain a 16-bit RGB image of superpixel segments filled
+/// with the mean color of that region. The return value is the count of
+/// superpixels in the image.
+///
+/// This is [`mean_colors`] with `u16` components, which keeps the precision of
+/// the mean colors for saving high bit depth images. Mean colors outside of the
+/// sRGB gamut have their RGB components clipped.
+///
+/// ```
+/// use palette::{FromColor, Lab, Srgb};
+/// use simple_clustering::image::{mean_colors, mean_colors_u16};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [
+///     Lab::from_color(Srgb::new(0.2, 0.4, 0.6)),
+///     Lab::from_color(Srgb::new(0.3, 0.5, 0.7)),
+/// ];
+/// let mut output = [0; 6];
+/// let mut output_u8 = [0; 6];
+/// mean_colors_u16(&mut output, 1, &[0, 0], &image)?;
+/// mean_colors(&mut output_u8, 1, &[0, 0], &image)?;
+/// for (&c, &c_u8) in output.iter().zip(&output_u8) {
+///     assert!(c.abs_diff(u16::from(c_u8) * 257) <= 128);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn mean_colors_u16<Wp>(
+    output: &mut [u16],
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+{
+    if Some(output.len()) != image.len().checked_mul(3) {
+        return Err(ScError::General(
+            "Mean color buffer does not match image length",
+        ));
+    }
+
+    let mut map = HashMap::default();
+    accumulate_label_colors(&mut map, k, labels, image)?;
+
+    let mut rgb_map = HashMap::<usize, Srgb<u16>>::default();
+    rgb_map.try_reserve(map.len())?;
+    rgb_map.extend(map.iter().map(|(&key, &(color, count))| {
+        let rgb: Srgb<u16> = map_to_gamut(color / count, GamutMapping::Clip).into_format();
+        (key, rgb)
+    }));
+
+    output
+        .chunks_exact_mut(3)
+        .zip(labels.iter().filter_map(|a| rgb_map.get(a)))
+        .for_each(|(chunk, color)| chunk.copy_from_slice(color.into()));
+
+    Ok(map.len())
+}
+
+/// Modify `output` to contain an RGB image of superpixel segments filled with
+/// the mean color of that region, for an image converted with
+/// [`WorkingSpace::convert_bytes`](crate::options::WorkingSpace::convert_bytes).
+/// The return value is the count of superpixels in the image.
+///
+/// The colors are averaged in the space that `image` was converted to before
+/// conversion to sRGB. Mean colors outside of the sRGB gamut have their RGB
+/// components clipped.
+///
+/// ```
+/// use simple_clustering::image::mean_colors_in_space;
+/// use simple_clustering::options::WorkingSpace;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let bytes = [255, 0, 0, 0, 255, 0];
+/// let image = WorkingSpace::OkLab.convert_bytes(&bytes)?;
+/// let mut output = [0; 6];
+/// mean_colors_in_space(&mut output, 1, &[0, 1], &image)?;
+/// assert_eq!(output, bytes);
+/// # Ok(())
+/// # }
+/// ```
+pub fn mean_colors_in_space(
+    output: &mut [u8],
+    k: usize,
+    labels: &[usize],
+    image: &WorkingImage,
+) -> Result<usize, ScError> {
+    let space = image.space();
+    let image = image.colors();
+    if Some(output.len()) != image.len().checked_mul(3) {
+        return Err(ScError::General(
+            "Mean color buffer does not match image length",
+        ));
+    }
+
+    let mut map = HashMap::default();
+    accumulate_label_colors(&mut map, k, labels, image)?;
+
+    let mut rgb_map = HashMap::<usize, Srgb<u8>>::default();
+    rgb_map.try_reserve(map.len())?;
+    rgb_map.extend(map.iter().map(|(&key, &(color, count))| {
+        let rgb: Srgb<u8> = space.to_srgb(color / count).clamp().into_format();
+        (key, rgb)
+    }));
+
+    output
+        .chunks_exact_mut(3)
+        .zip(labels.iter().filter_map(|a| rgb_map.get(a)))
+        .for_each(|(chunk, color)| chunk.copy_from_slice(color.into()));
+
+    Ok(map.len())
+}
+
+/// Clear `map` and fill it with the sum of the colors and the pixel count of
+/// each label.
+fn accumulate_label_colors<Wp>(
+    map: &mut HashMap<usize, (Lab<Wp, f64>, f64)>,
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+) -> Result<(), ScError> {
+    map.clear();
+    map.try_reserve(k)?;
+
+    for (&idx, &color) in labels.iter().zip(image.iter()) {
+        let _ = map
+            .entry(idx)
+            .and_modify(|e| {
+                e.0 += color;
+                e.1 += 1.0;
+            })
+            .or_insert((color, 1.0));
+    }
+
     Ok(())
 }
+
+/// Convert a `Lab` color to sRGB, bringing it into the gamut according to
+/// `gamut_mapping`.
+fn map_to_gamut<Wp>(color: Lab<Wp, f64>, gamut_mapping: GamutMapping) -> Srgb<f64>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+{
+    // Allow for rounding errors at the edge of the gamut
+    const EPSILON: f64 = 1e-9;
+    let in_gamut = |rgb: &Srgb<f64>| {
+        [rgb.red, rgb.green, rgb.blue]
+            .iter()
+            .all(|c| (-EPSILON..=1.0 + EPSILON).contains(c))
+    };
+
+    let rgb: Srgb<f64> = color.into_color_unclamped();
+    if gamut_mapping == GamutMapping::Clip || in_gamut(&rgb) {
+        return rgb.clamp();
+    }
+
+    // Binary search for the largest chroma scale which is in gamut. Lightness
+    // outside of the gamut can't be fixed by chroma and is clipped.
+    let l = color.l.clamp(0.0, 100.0);
+    let scaled = |t: f64| -> Srgb<f64> {
+        Lab::<Wp, f64>::new(l, color.a * t, color.b * t).into_color_unclamped()
+    };
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..32 {
+        let mid = 0.5 * (low + high);
+        if in_gamut(&scaled(mid)) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    scaled(low).clamp()
+}
+
+/// Segment a buffer of RGB component bytes with `algorithm` and return an RGB
+/// image of the superpixel segments filled with their mean colors.
+///
+/// This combines the `Lab` conversion, segmentation, and [`mean_colors`] steps
+/// into one call.
+///
+/// ```
+/// use simple_clustering::{image::segment_and_recolor_bytes, options::Algorithm};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let (width, height) = (8, 8);
+/// let image = [127u8; 8 * 8 * 3];
+/// let output = segment_and_recolor_bytes(Algorithm::Snic, 4, 10, width, height, &image)?;
+/// assert_eq!(output.len(), image.len());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn segment_and_recolor_bytes(
+    algorithm: Algorithm,
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    image: &[u8],
+) -> Result<Vec<u8>, ScError> {
+    let mismatch = match algorithm {
+        Algorithm::Slic => ScError::MismatchedSlicBuffer,
+        Algorithm::Snic => ScError::MismatchedSnicBuffer,
+    };
+    check_component_buffer(image.len(), 3, width, height, mismatch)?;
+    let input_lab = srgb_components_to_lab(image)?;
+
+    let labels = match algorithm {
+        Algorithm::Slic => crate::slic(k, m, width, height, None, &input_lab)?,
+        Algorithm::Snic => crate::snic(k, m, width, height, &input_lab)?,
+    };
+
+    let len = input_lab
+        .len()
+        .checked_mul(3)
+        .ok_or(ScError::Overflow("output buffer length"))?;
+    let mut output = Vec::new();
+    output.try_reserve_exact(len)?;
+    output.extend((0..len).map(|_| 0));
+
+    let k = usize::try_from(k).or(Err(ScError::Overflow("superpixel count")))?;
+    let _ = mean_colors(&mut output, k, &labels, &input_lab)?;
+
+    Ok(output)
+}
+
+/// Modify `output` to contain an RGB image of superpixel segments filled with
+/// the per-channel median color of that region. The return value is the count
+/// of superpixels in the image.
+///
+/// The median of each `Lab` channel is less affected than the mean by pixels
+/// from across a sharp edge, so regions straddling an edge are washed out
+/// less. Unlike [`mean_colors`], which keeps one accumulator per region, the
+/// color of every pixel is collected per region, so this takes memory
+/// proportional to the image size in addition to `output`.
+///
+/// `output` must be 3 times the length of `image`, as in [`mean_colors`].
+pub fn median_colors<Wp>(
+    output: &mut [u8],
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    if Some(output.len()) != image.len().checked_mul(3) {
+        return Err(ScError::General(
+            "Median color buffer does not match image length",
+        ));
+    }
+
+    let mut regions = HashMap::<usize, Vec<Lab<Wp, f64>>>::default();
+    regions.try_reserve(k)?;
+
+    for (&label, &color) in labels.iter().zip(image.iter()) {
+        let region = regions.entry(label).or_default();
+        if region.capacity() == region.len() {
+            region.try_reserve(region.len().max(4))?;
+        }
+        region.push(color);
+    }
+
+    let mut rgb_map = HashMap::<usize, Srgb<u8>>::default();
+    rgb_map.try_reserve(regions.len())?;
+
+    // Reused for selecting the median of each channel
+    let mut channel = Vec::new();
+    for (&label, colors) in &regions {
+        channel.clear();
+        channel.try_reserve(colors.len())?;
+        channel.extend(colors.iter().map(|c| c.l));
+        let l = median(&mut channel);
+
+        channel.clear();
+        channel.extend(colors.iter().map(|c| c.a));
+        let a = median(&mut channel);
+
+        channel.clear();
+        channel.extend(colors.iter().map(|c| c.b));
+        let b = median(&mut channel);
+
+        let rgb: Srgb<u8> = Lab::<Wp, f64>::new(l, a, b).into_color().into_format();
+        let _ = rgb_map.insert(label, rgb);
+    }
+
+    output
+        .chunks_exact_mut(3)
+        .zip(labels.iter().filter_map(|a| rgb_map.get(a)))
+        .for_each(|(chunk, color)| chunk.copy_from_slice(color.into()));
+
+    Ok(regions.len())
+}
+
+/// Modify `output` to contain an RGB image of superpixel segments filled with
+/// the center of the region's most populated color bin. The return value is
+/// the count of superpixels in the image.
+///
+/// Each `Lab` axis is quantized into `bins` equal bins over `0..=100` for `L`
+/// and `-128..=128` for `a` and `b`. Ties between bins go to the bin with the
+/// lowest `L`, then `a`, then `b`. Unlike [`mean_colors`] and
+/// [`median_colors`], the color is close to one that occurs in the region,
+/// avoiding muddy averages across textured regions. Coarse bins merge similar
+/// colors while fine bins keep more detail. An odd number of `bins` centers a
+/// bin on `a = b = 0`, so neutral grays aren't tinted by the quantization.
+///
+/// The histograms are sparse and store one entry for every occupied bin in
+/// each region. They take at most memory proportional to the image size, in
+/// addition to `output`, but are much smaller for small `bins` or uniform
+/// regions.
+///
+/// `bins` must not be `0`. `output` must be 3 times the length of `image`, as in
+/// [`mean_colors`].
+pub fn dominant_colors<Wp>(
+    output: &mut [u8],
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    bins: u32,
+) -> Result<usize, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    if Some(output.len()) != image.len().checked_mul(3) {
+        return Err(ScError::General(
+            "Dominant color buffer does not match image length",
+        ));
+    }
+    if bins == 0 {
+        return Err(ScError::General("Histogram bins cannot be 0"));
+    }
+    let _ = u64::from(bins)
+        .checked_pow(3)
+        .ok_or(ScError::Overflow("histogram bin count"))?;
+
+    // Minimum and maximum values of the L, a, and b axes
+    const RANGES: [(f64, f64); 3] = [(0.0, 100.0), (-128.0, 128.0), (-128.0, 128.0)];
+    let bins_f = f64::from(bins);
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let quantize = |value: f64, (min, max): (f64, f64)| -> u64 {
+        let bin = ((value - min) / (max - min) * bins_f).floor();
+        // NaN is cast to 0
+        (bin.clamp(0.0, bins_f - 1.0) as u32).into()
+    };
+    let center = |bin: u64, (min, max): (f64, f64)| -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let bin = bin as f64;
+        min + (bin + 0.5) * (max - min) / bins_f
+    };
+
+    let mut histograms = HashMap::<usize, HashMap<u64, u32>>::default();
+    histograms.try_reserve(k)?;
+
+    let bins_u64 = u64::from(bins);
+    for (&label, color) in labels.iter().zip(image.iter()) {
+        let key = (quantize(color.l, RANGES[0]) * bins_u64 + quantize(color.a, RANGES[1]))
+            * bins_u64
+            + quantize(color.b, RANGES[2]);
+        let count = histograms.entry(label).or_default().entry(key).or_insert(0);
+        *count = count.saturating_add(1);
+    }
+
+    let mut rgb_map = HashMap::<usize, Srgb<u8>>::default();
+    rgb_map.try_reserve(histograms.len())?;
+
+    for (&label, histogram) in &histograms {
+        let key = histogram
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map_or(0, |(&key, _)| key);
+
+        let l = center(key / (bins_u64 * bins_u64), RANGES[0]);
+        let a = center(key / bins_u64 % bins_u64, RANGES[1]);
+        let b = center(key % bins_u64, RANGES[2]);
+        let rgb: Srgb<u8> = Lab::<Wp, f64>::new(l, a, b).into_color().into_format();
+        let _ = rgb_map.insert(label, rgb);
+    }
+
+    output
+        .chunks_exact_mut(3)
+        .zip(labels.iter().filter_map(|a| rgb_map.get(a)))
+        .for_each(|(chunk, color)| chunk.copy_from_slice(color.into()));
+
+    Ok(histograms.len())
+}
+
+/// Find the median of `values`, averaging the two middle values for an even
+/// length. The order of `values` is not preserved. Returns `0.0` if `values`
+/// is empty.
+fn median(values: &mut [f64]) -> f64 {
+    let len = values.len();
+    if len == 0 {
+        return 0.0;
+    }
+
+    let (lower, &mut upper, _) = values.select_nth_unstable_by(len / 2, f64::total_cmp);
+    if len % 2 == 1 {
+        return upper;
+    }
+
+    // The lower middle value is the largest of the lower partition
+    let lower = lower.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    0.5 * (lower + upper)
+}
+
+/// Reduce the superpixel segments to a palette of at most `n` colors, such as
+/// for indexed-color image formats. The return value is the palette and the
+/// palette index of each pixel.
+///
+/// The mean colors of the regions are grouped into `n` clusters with k-means,
+/// weighting each region by its pixel count. The clusters start from the mean
+/// of the largest region and then repeatedly from the region mean farthest
+/// from the chosen clusters, so the result is deterministic. Fewer than `n`
+/// colors are returned if there are fewer distinct region colors.
+///
+/// `n` must be between `1` and `256`.
+///
+/// ```
+/// use palette::{Lab, Srgb};
+/// use simple_clustering::image::reduce_to_palette;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [
+///     Lab::new(20.0, 0.0, 0.0),
+///     Lab::new(22.0, 0.0, 0.0),
+///     Lab::new(80.0, 0.0, 0.0),
+///     Lab::new(82.0, 0.0, 0.0),
+/// ];
+/// let labels = [0, 1, 2, 3];
+/// let (palette, indices) = reduce_to_palette(4, &labels, &image, 2)?;
+/// assert_eq!(palette.len(), 2);
+/// assert_eq!(indices[0], indices[1]);
+/// assert_eq!(indices[2], indices[3]);
+/// assert_ne!(indices[0], indices[2]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn reduce_to_palette<Wp>(
+    k: usize,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    n: usize,
+) -> Result<(Vec<Srgb<u8>>, Vec<u8>), ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    // Refinement iterations for the palette clusters
+    const ITERATIONS: usize = 10;
+
+    if !(1..=256).contains(&n) {
+        return Err(ScError::General("Palette size must be between 1 and 256"));
+    }
+    if labels.len() != image.len() {
+        return Err(ScError::General("Label buffer does not match image length"));
+    }
+
+    let mut map = HashMap::<usize, (Lab<Wp, f64>, f64)>::default();
+    map.try_reserve(k)?;
+    for (&label, &color) in labels.iter().zip(image.iter()) {
+        let entry = map.entry(label).or_insert((Lab::new(0.0, 0.0, 0.0), 0.0));
+        entry.0 += color;
+        entry.1 += 1.0;
+    }
+
+    // Sort the regions so the palette doesn't depend on the hasher
+    let mut regions = Vec::new();
+    regions.try_reserve_exact(map.len())?;
+    regions.extend(
+        map.iter()
+            .map(|(&label, &(sum, count))| (label, sum / count, count)),
+    );
+    regions.sort_unstable_by_key(|&(label, _, _)| label);
+
+    // Farthest point initialization, starting from the largest region
+    let mut centers = Vec::new();
+    centers.try_reserve_exact(n.min(regions.len()))?;
+    if let Some(&(_, color, _)) = regions.iter().max_by(|lhs, rhs| lhs.2.total_cmp(&rhs.2)) {
+        centers.push(color);
+    }
+    while centers.len() < n {
+        let farthest = regions
+            .iter()
+            .map(|&(_, color, _)| {
+                let nearest = centers
+                    .iter()
+                    .map(|&c| distance_lab(color, c))
+                    .fold(f64::INFINITY, f64::min);
+                (color, nearest)
+            })
+            .max_by(|lhs, rhs| lhs.1.total_cmp(&rhs.1));
+        match farthest {
+            Some((color, distance)) if distance > 0.0 => centers.push(color),
+            _ => break,
+        }
+    }
+
+    let nearest_center = |color: Lab<Wp, f64>, centers: &[Lab<Wp, f64>]| {
+        centers
+            .iter()
+            .enumerate()
+            .map(|(idx, &c)| (idx, distance_lab(color, c)))
+            .min_by(|lhs, rhs| lhs.1.total_cmp(&rhs.1))
+            .map_or(0, |(idx, _)| idx)
+    };
+
+    let mut sums = Vec::new();
+    sums.try_reserve_exact(centers.len())?;
+    for _ in 0..ITERATIONS {
+        sums.clear();
+        sums.extend(
+            centers
+                .iter()
+                .map(|_| (Lab::<Wp, f64>::new(0.0, 0.0, 0.0), 0.0)),
+        );
+        for &(_, color, count) in &regions {
+            if let Some(sum) = sums.get_mut(nearest_center(color, &centers)) {
+                sum.0 += color * count;
+                sum.1 += count;
+            }
+        }
+        for (center, &(sum, count)) in centers.iter_mut().zip(sums.iter()) {
+            if count > 0.0 {
+                *center = sum / count;
+            }
+        }
+    }
+
+    let mut palette = Vec::new();
+    palette.try_reserve_exact(centers.len())?;
+    palette.extend(
+        centers
+            .iter()
+            .map(|&c| -> Srgb<u8> { c.into_color().into_format() }),
+    );
+
+    let mut palette_map = HashMap::<usize, u8>::default();
+    palette_map.try_reserve(regions.len())?;
+    for &(label, color, _) in &regions {
+        // `n` is at most 256, so every index fits in a `u8`
+        let idx = u8::try_from(nearest_center(color, &centers))
+            .or(Err(ScError::Overflow("palette index")))?;
+        let _ = palette_map.insert(label, idx);
+    }
+
+    let mut indices = Vec::new();
+    indices.try_reserve_exact(labels.len())?;
+    indices.extend(labels.iter().filter_map(|label| palette_map.get(label)));
+
+    Ok((palette, indices))
+}
+
+/// Modify `output` to contain an RGB image with colored contours based on
+/// superpixel labels.
+pub fn segment_contours(
+    output: &mut [u8],
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    segment_color: [u8; 3],
+) -> Result<(), ScError> {
+    segment_contours_wrapped(output, width, height, labels, segment_color, false)
+}
+
+/// Modify `output` to contain an RGB image with colored contours based on
+/// superpixel labels, optionally treating the left and right image edges as
+/// connected.
+///
+/// Set `wrap` for labels created with
+/// [`Options::wrap_x`](crate::options::Options::wrap_x) so that regions
+/// crossing the seam aren't outlined at the image edges.
+pub fn segment_contours_wrapped(
+    output: &mut [u8],
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    segment_color: [u8; 3],
+    wrap: bool,
+) -> Result<(), ScError> {
+    segment_contours_by_label(output, width, height, labels, wrap, |_| segment_color)
+}
+
+/// Modify `output` to contain an RGB image with colored contours around the
+/// superpixels with at least `min_size` pixels.
+///
+/// Contours are only drawn where two regions of at least `min_size` pixels
+/// meet, so boundaries with or inside of smaller regions are left out. This
+/// declutters overlays of busy images without changing the labels. A
+/// `min_size` of `0` draws the same contours as [`segment_contours_wrapped`].
+///
+/// ```
+/// use simple_clustering::image::segment_contours_min_size;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // Two large regions split down the middle with a single pixel region
+/// let mut labels = [0, 0, 0, 1, 1, 1].repeat(6);
+/// labels[12] = 2;
+/// let mut output = [255; 6 * 6 * 3];
+/// segment_contours_min_size(&mut output, 6, 6, &labels, [0; 3], false, 2)?;
+///
+/// // Only the boundary between the two large regions is drawn
+/// let drawn: Vec<_> = output.chunks_exact(3).map(|rgb| rgb[0] == 0).collect();
+/// assert!(drawn.iter().enumerate().all(|(i, &drawn)| drawn == (i % 6 == 2)));
+/// # Ok(())
+/// # }
+/// ```
+pub fn segment_contours_min_size(
+    output: &mut [u8],
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    segment_color: [u8; 3],
+    wrap: bool,
+    min_size: usize,
+) -> Result<(), ScError> {
+    if labels
+        .len()
+        .checked_mul(3)
+        .filter(|&len| output.len() >= len)
+        .is_none()
+    {
+        return Err(ScError::General("Contour buffer is smaller than the image"));
+    }
+
+    let sizes = superpixel_sizes(labels)?;
+    let mut sink = MinSizeContourSink {
+        sink: RgbContourSink::new(output, width, |_| segment_color),
+        labels,
+        sizes: &sizes,
+        min_size,
+        width,
+        height,
+        wrap,
+    };
+    segment_contours_to_sink(width, height, labels, wrap, &mut sink)
+}
+
+/// [`ContourSink`] which only passes on contour pixels between two regions
+/// of at least `min_size` pixels, see [`segment_contours_min_size`].
+struct MinSizeContourSink<'a, S> {
+    sink: S,
+    labels: &'a [usize],
+    sizes: &'a HashMap<usize, usize>,
+    min_size: usize,
+    width: u32,
+    height: u32,
+    wrap: bool,
+}
+
+impl<S: ContourSink> ContourSink for MinSizeContourSink<'_, S> {
+    fn mark(&mut self, x: u32, y: u32, label: usize) {
+        let large = |label| {
+            self.sizes
+                .get(&label)
+                .is_some_and(|&size| size >= self.min_size)
+        };
+        if !large(label) {
+            return;
+        }
+
+        let width_i = i64::from(self.width);
+        let height_i = i64::from(self.height);
+        let borders_large = Connectivity::Eight.offsets().iter().any(|&(d_x, d_y)| {
+            let n_x = wrap_x(width_i, i64::from(x) + d_x, self.wrap);
+            get_in_bounds(width_i, height_i, n_x, i64::from(y) + d_y, self.labels)
+                .is_some_and(|&n| n != label && large(n))
+        });
+        if borders_large {
+            self.sink.mark(x, y, label);
+        }
+    }
+}
+
+/// Modify `output` to contain an RGB image with contours based on superpixel
+/// labels, where each contour pixel is colored by `contour_color`.
+///
+/// `contour_color` is called for every contour pixel with the label of that
+/// pixel, so each region can be outlined in its own color, such as a darkened
+/// version of its mean color. Set `wrap` for labels created with
+/// [`Options::wrap_x`](crate::options::Options::wrap_x).
+///
+/// ```
+/// use simple_clustering::image::segment_contours_by_label;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // Two regions split down the middle of a 4x4 image
+/// let (width, height) = (4, 4);
+/// let labels = [0, 0, 1, 1].repeat(4);
+/// let mut output = [255; 4 * 4 * 3];
+///
+/// let colors = [[200, 0, 0], [0, 0, 200]];
+/// segment_contours_by_label(&mut output, width, height, &labels, false, |label| {
+///     colors[label].map(|c| c / 2)
+/// })?;
+///
+/// // The boundary is drawn on the left region in its darkened color
+/// assert_eq!(output[..12], [255, 255, 255, 100, 0, 0, 255, 255, 255, 255, 255, 255]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn segment_contours_by_label<F>(
+    output: &mut [u8],
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    wrap: bool,
+    contour_color: F,
+) -> Result<(), ScError>
+where
+    F: FnMut(usize) -> [u8; 3],
+{
+    if labels
+        .len()
+        .checked_mul(3)
+        .filter(|&len| output.len() >= len)
+        .is_none()
+    {
+        return Err(ScError::General("Contour buffer is smaller than the image"));
+    }
+
+    let mut sink = RgbContourSink::new(output, width, contour_color);
+    segment_contours_to_sink(width, height, labels, wrap, &mut sink)
+}
+
+/// Destination for the contour pixels found by [`segment_contours_to_sink`].
+///
+/// Implement this to draw contours onto targets other than an RGB byte buffer,
+/// such as a GPU texture or a vector canvas.
+pub trait ContourSink {
+    /// Mark the pixel at `(x, y)`, which belongs to `label`, as a contour
+    /// pixel. Every pixel is marked at most once.
+    fn mark(&mut self, x: u32, y: u32, label: usize);
+}
+
+impl<S: ContourSink + ?Sized> ContourSink for &mut S {
+    #[inline]
+    fn mark(&mut self, x: u32, y: u32, label: usize) {
+        (**self).mark(x, y, label);
+    }
+}
+
+/// [`ContourSink`] which writes contour pixels into a buffer of RGB component
+/// bytes, colored by the label of the pixel. This is used by
+/// [`segment_contours_by_label`].
+#[derive(Debug)]
+pub struct RgbContourSink<'a, F> {
+    buffer: &'a mut [u8],
+    width: u32,
+    contour_color: F,
+}
+
+impl<'a, F> RgbContourSink<'a, F>
+where
+    F: FnMut(usize) -> [u8; 3],
+{
+    /// Create a sink drawing into `buffer`, an RGB image `width` pixels wide,
+    /// where `contour_color` gives the color of the contour pixels of each
+    /// label. Pixels outside of `buffer` are ignored.
+    pub fn new(buffer: &'a mut [u8], width: u32, contour_color: F) -> Self {
+        Self {
+            buffer,
+            width,
+            contour_color,
+        }
+    }
+}
+
+impl<F> ContourSink for RgbContourSink<'_, F>
+where
+    F: FnMut(usize) -> [u8; 3],
+{
+    fn mark(&mut self, x: u32, y: u32, label: usize) {
+        let idx = u64::from(y)
+            .checked_mul(u64::from(self.width))
+            .and_then(|idx| idx.checked_add(u64::from(x)))
+            .and_then(|idx| usize::try_from(idx).ok())
+            .and_then(|idx| idx.checked_mul(3));
+        if let Some(chunk) = idx.and_then(|idx| self.buffer.get_mut(idx..idx.checked_add(3)?)) {
+            chunk.copy_from_slice(&(self.contour_color)(label));
+        }
+    }
+}
+
+/// Find the contour pixels of superpixel labels and pass them to `sink`.
+///
+/// A pixel is on a contour if at least two of its 8-connected neighbors have a
+/// different label and aren't contour pixels themselves, which keeps the
+/// contours one pixel wide. Set `wrap` for labels created with
+/// [`Options::wrap_x`](crate::options::Options::wrap_x).
+///
+/// ```
+/// use simple_clustering::image::{segment_contours_to_sink, ContourSink};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // Collect the contour coordinates instead of drawing them
+/// struct Points(Vec<(u32, u32)>);
+///
+/// impl ContourSink for Points {
+///     fn mark(&mut self, x: u32, y: u32, _label: usize) {
+///         self.0.push((x, y));
+///     }
+/// }
+///
+/// let labels = [0, 0, 1, 1].repeat(4);
+/// let mut points = Points(Vec::new());
+/// segment_contours_to_sink(4, 4, &labels, false, &mut points)?;
+/// assert!(points.0.iter().all(|&(x, _)| x == 1));
+/// # Ok(())
+/// # }
+/// ```
+pub fn segment_contours_to_sink<S: ContourSink>(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    wrap: bool,
+    sink: &mut S,
+) -> Result<(), ScError> {
+    if labels.len() < pixel_count(width, height)? {
+        return Err(ScError::General("Labels exhausted"));
+    }
+
+    for (x, y) in BoundaryPixels::new(width, height, labels, wrap) {
+        let idx = usize::try_from(u64::from(y) * u64::from(width) + u64::from(x))
+            .or(Err("Contour index out of bounds"))?;
+        sink.mark(x, y, *labels.get(idx).ok_or("Labels exhausted")?);
+    }
+
+    Ok(())
+}
+
+/// Lazily find the `(x, y)` coordinates of the contour pixels of superpixel
+/// labels, in row-major order.
+///
+/// These are the same pixels drawn by [`segment_contours`], but only two rows
+/// of contour state are kept in memory, so the contours of very large images
+/// can be drawn or counted without an output buffer. Iteration stops early if
+/// `labels` has fewer than `width * height` elements.
+///
+/// ```
+/// use simple_clustering::image::boundary_pixels;
+///
+/// let labels = [0, 0, 1, 1].repeat(4);
+/// let boundary: Vec<_> = boundary_pixels(4, 4, &labels).collect();
+/// assert_eq!(boundary, [(1, 0), (1, 1), (1, 2), (1, 3)]);
+/// ```
+pub fn boundary_pixels(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+) -> impl Iterator<Item = (u32, u32)> + '_ {
+    BoundaryPixels::new(width, height, labels, false)
+}
+
+/// Calculate the fraction of edge pixels in `edges` that lie within
+/// `tolerance` pixels of a superpixel contour, along both axes.
+///
+/// The contours are the pixels found by [`boundary_pixels`]. A recall of `1.0`
+/// means every edge is covered by a superpixel boundary.
+///
+/// ```
+/// use simple_clustering::image::boundary_recall;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let labels = [0, 0, 1, 1].repeat(4);
+/// let mut edges = [false, true, false, false].repeat(4);
+/// assert_eq!(boundary_recall(4, 4, &labels, &edges, 0)?, 1.0);
+///
+/// edges[3] = true;
+/// assert_eq!(boundary_recall(4, 4, &labels, &edges, 0)?, 0.8);
+/// assert_eq!(boundary_recall(4, 4, &labels, &edges, 2)?, 1.0);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `edges` must both have a length of `width * height`, and
+/// `edges` must have at least one edge pixel.
+pub fn boundary_recall(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    edges: &[bool],
+    tolerance: u32,
+) -> Result<f64, ScError> {
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || edges.len() != len {
+        return Err(ScError::General(
+            "Label or edge buffer does not match image dimensions",
+        ));
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+    let tolerance = usize::try_from(tolerance).or(Err("Could not convert tolerance to usize"))?;
+
+    // Mark every pixel within `tolerance` of a contour pixel
+    let mut near = Vec::new();
+    near.try_reserve_exact(len)?;
+    near.extend((0..len).map(|_| false));
+    for (x, y) in boundary_pixels(width, height, labels) {
+        let x = usize::try_from(x).or(Err("Invalid boundary x"))?;
+        let y = usize::try_from(y).or(Err("Invalid boundary y"))?;
+        let rows = y.saturating_sub(tolerance)..=y.saturating_add(tolerance);
+        for row in near
+            .chunks_exact_mut(width_usize)
+            .take(*rows.end() + 1)
+            .skip(*rows.start())
+        {
+            let end = x.saturating_add(tolerance).min(width_usize - 1);
+            if let Some(span) = row.get_mut(x.saturating_sub(tolerance)..=end) {
+                span.fill(true);
+            }
+        }
+    }
+
+    let (total, hits) = edges
+        .iter()
+        .zip(&near)
+        .filter(|(&edge, _)| edge)
+        .fold((0_usize, 0_usize), |(total, hits), (_, &near)| {
+            (total + 1, hits + usize::from(near))
+        });
+    if total == 0 {
+        return Err(ScError::General("Edge map has no edge pixels"));
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    Ok(hits as f64 / total as f64)
+}
+
+/// Iterator over contour pixels, see [`boundary_pixels`].
+struct BoundaryPixels<'a> {
+    labels: &'a [usize],
+    width: u32,
+    height: u32,
+    wrap: bool,
+    x: u32,
+    y: u32,
+    /// Contour state of the previous row
+    prev: Vec<bool>,
+    /// Contour state of the current row, `false` for pixels not yet visited
+    cur: Vec<bool>,
+}
+
+impl<'a> BoundaryPixels<'a> {
+    fn new(width: u32, height: u32, labels: &'a [usize], wrap: bool) -> Self {
+        let row_len = usize::try_from(width).unwrap_or_default();
+        Self {
+            labels,
+            width,
+            height,
+            wrap,
+            x: 0,
+            y: 0,
+            prev: vec![false; row_len],
+            cur: vec![false; row_len],
+        }
+    }
+
+    /// Check whether the pixel at `(x, y)` is a contour pixel. A pixel is on a
+    /// contour if at least two of its 8-connected neighbors have a different
+    /// label and aren't contour pixels themselves.
+    fn is_boundary(&self, x: i64, y: i64, label: usize) -> bool {
+        let width_i = i64::from(self.width);
+        let height_i = i64::from(self.height);
+        let differing = Connectivity::Eight
+            .offsets()
+            .iter()
+            .filter(|&&(d_x, d_y)| {
+                let n_x = wrap_x(width_i, x + d_x, self.wrap);
+                let n_y = y + d_y;
+                if !(0..height_i).contains(&n_y) {
+                    return false;
+                }
+                let row = match d_y {
+                    -1 => &self.prev,
+                    0 => &self.cur,
+                    // Rows below haven't been visited yet
+                    _ => {
+                        return get_in_bounds(width_i, height_i, n_x, n_y, self.labels)
+                            .is_some_and(|&n| n != label)
+                    }
+                };
+                usize::try_from(n_x).ok().and_then(|n_x| row.get(n_x)) == Some(&false)
+                    && get_in_bounds(width_i, height_i, n_x, n_y, self.labels) != Some(&label)
+            })
+            .count();
+
+        differing >= 2
+    }
+}
+
+impl Iterator for BoundaryPixels<'_> {
+    type Item = (u32, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.y < self.height {
+            let (x, y) = (self.x, self.y);
+            let idx = usize::try_from(u64::from(y) * u64::from(self.width) + u64::from(x)).ok()?;
+            let &label = self.labels.get(idx)?;
+            let boundary = self.is_boundary(i64::from(x), i64::from(y), label);
+
+            let x_usize = usize::try_from(x).ok()?;
+            if let Some(state) = self.cur.get_mut(x_usize) {
+                *state = boundary;
+            }
+            self.x += 1;
+            if self.x == self.width {
+                self.x = 0;
+                self.y += 1;
+                std::mem::swap(&mut self.prev, &mut self.cur);
+                self.cur.fill(false);
+            }
+
+            if boundary {
+                return Some((x, y));
+            }
+        }
+
+        None
+    }
+}
+
+/// Find the labels which border each label in a slice of superpixel labels.
+///
+/// Two labels are adjacent if any of their pixels are 4-connected neighbors.
+pub fn region_adjacency(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+) -> Result<HashMap<usize, HashSet<usize>>, ScError> {
+    if labels.len() != pixel_count(width, height)? {
+        return Err(ScError::General(
+            "Label buffer does not match image dimensions",
+        ));
+    }
+
+    let mut adjacency = HashMap::<usize, HashSet<usize>>::default();
+    for_each_neighbor_pair(width, height, labels, |label, neighbor| {
+        let entry = adjacency.entry(label).or_default();
+        if let Some(neighbor) = neighbor {
+            let _ = entry.insert(neighbor);
+            let _ = adjacency.entry(neighbor).or_default().insert(label);
+        }
+    })?;
+
+    Ok(adjacency)
+}
+
+/// Find the pairs of adjacent regions with a weight for each, as
+/// `(a, b, weight)` with `a < b`, sorted by `a` and then `b`.
+///
+/// Two labels are adjacent if any of their pixels are 4-connected neighbors.
+/// The weight is an affinity for graph-based methods:
+/// `boundary / (1 + delta_e)`, where `boundary` is the number of neighboring
+/// pixel pairs split between the regions and `delta_e` is the Euclidean `Lab`
+/// distance between their mean colors. Regions with long shared boundaries
+/// and similar colors have the largest weights.
+///
+/// ```
+/// use palette::Lab;
+/// use simple_clustering::image::weighted_adjacency;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let labels = [
+///     0, 0, 1,
+///     0, 0, 1,
+///     2, 2, 2,
+/// ];
+/// let image: Vec<_> = labels
+///     .iter()
+///     .map(|&l| {
+///         let lightness = if l == 1 { 60.0 } else { 50.0 };
+///         Lab::<palette::white_point::D65, f64>::new(lightness, 0.0, 0.0)
+///     })
+///     .collect();
+/// let edges = weighted_adjacency(3, 3, &labels, &image)?;
+/// assert_eq!(edges.len(), 3);
+/// assert_eq!(edges[0], (0, 1, 2.0 / 11.0));
+/// assert_eq!(edges[1], (0, 2, 2.0));
+/// assert_eq!(edges[2], (1, 2, 1.0 / 11.0));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `image` must both have a length of `width * height`.
+pub fn weighted_adjacency<Wp>(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<(usize, usize, f64)>, ScError> {
+    if image.len() != labels.len() {
+        return Err(ScError::General("Label buffer does not match image length"));
+    }
+
+    let mut boundaries = HashMap::<(usize, usize), usize>::default();
+    for_each_neighbor_pair(width, height, labels, |label, neighbor| {
+        if let Some(neighbor) = neighbor {
+            *boundaries
+                .entry((label.min(neighbor), label.max(neighbor)))
+                .or_default() += 1;
+        }
+    })?;
+
+    let mut sums = HashMap::default();
+    accumulate_label_colors(&mut sums, 0, labels, image)?;
+    let mean = |label| sums.get(&label).map(|&(color, count)| color / count);
+
+    let mut edges = Vec::new();
+    edges.try_reserve_exact(boundaries.len())?;
+    for ((a, b), boundary) in boundaries {
+        let (Some(mean_a), Some(mean_b)) = (mean(a), mean(b)) else {
+            continue;
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let weight = boundary as f64 / (1.0 + distance_lab(mean_a, mean_b).sqrt());
+        edges.push((a, b, weight));
+    }
+    edges.sort_unstable_by_key(|&(a, b, _)| (a, b));
+
+    Ok(edges)
+}
+
+/// Sparse matrix in coordinate format as `(row, col, data)`, see
+/// [`adjacency_coo`].
+pub type CooMatrix = (Vec<u32>, Vec<u32>, Vec<f64>);
+
+/// Find the weighted adjacency of the regions in a slice of superpixel labels
+/// as a symmetric sparse matrix in coordinate (COO) format.
+///
+/// The edges and weights are those of [`weighted_adjacency`], with each edge
+/// stored in both directions, so `data[i]` is the weight between labels
+/// `row[i]` and `col[i]`. The entries are sorted by row, then column. The
+/// arrays can be passed directly to `scipy.sparse.coo_matrix((data, (row,
+/// col)))`.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::image::adjacency_coo;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [Lab::<D65, f64>::new(50.0, 0.0, 0.0); 2];
+/// let (row, col, data) = adjacency_coo(2, 1, &[0, 1], &image)?;
+/// assert_eq!(row, [0, 1]);
+/// assert_eq!(col, [1, 0]);
+/// assert_eq!(data, [1.0, 1.0]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `image` must both have a length of `width * height`, and every
+/// label must fit in a `u32`.
+pub fn adjacency_coo<Wp>(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+) -> Result<CooMatrix, ScError> {
+    let edges = weighted_adjacency(width, height, labels, image)?;
+    let len = edges
+        .len()
+        .checked_mul(2)
+        .ok_or(ScError::Overflow("adjacency entry count"))?;
+
+    let mut entries = Vec::new();
+    entries.try_reserve_exact(len)?;
+    for (a, b, weight) in edges {
+        let a = u32::try_from(a).or(Err(ScError::Overflow("adjacency label")))?;
+        let b = u32::try_from(b).or(Err(ScError::Overflow("adjacency label")))?;
+        entries.push((a, b, weight));
+        entries.push((b, a, weight));
+    }
+    entries.sort_unstable_by_key(|&(a, b, _)| (a, b));
+
+    let (mut row, mut col, mut data) = (Vec::new(), Vec::new(), Vec::new());
+    row.try_reserve_exact(len)?;
+    col.try_reserve_exact(len)?;
+    data.try_reserve_exact(len)?;
+    for (a, b, weight) in entries {
+        row.push(a);
+        col.push(b);
+        data.push(weight);
+    }
+
+    Ok((row, col, data))
+}
+
+/// Call `f` with the label of every pixel, and again with the label of each
+/// East and South neighbor with a different label. Every pair of 4-connected
+/// pixels with different labels is visited once.
+fn for_each_neighbor_pair<F>(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    mut f: F,
+) -> Result<(), ScError>
+where
+    F: FnMut(usize, Option<usize>),
+{
+    let width_i = i64::from(width);
+    let height_i = i64::from(height);
+
+    let mut label_iter = labels.iter();
+    for y in 0..height_i {
+        for x in 0..width_i {
+            let &label = label_iter.next().ok_or("Labels exhausted")?;
+            f(label, None);
+
+            // Only look East and South, the other directions are covered when
+            // visiting the neighboring pixel
+            for (n_x, n_y) in [(x + 1, y), (x, y + 1)] {
+                if let Some(&neighbor) = get_in_bounds(width_i, height_i, n_x, n_y, labels) {
+                    if neighbor != label {
+                        f(label, Some(neighbor));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Count the boundary pixels of each region in a slice of superpixel labels.
+///
+/// A pixel is on the boundary of its region if one of its 4-connected
+/// neighbors has a different label or lies outside of the image, so pixels
+/// along the image border count toward the perimeter.
+///
+/// ```
+/// use simple_clustering::image::region_perimeters;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // A 2x2 region in the middle of a 4x4 region
+/// let labels = [
+///     0, 0, 0, 0,
+///     0, 1, 1, 0,
+///     0, 1, 1, 0,
+///     0, 0, 0, 0,
+/// ];
+/// let perimeters = region_perimeters(4, 4, &labels)?;
+/// assert_eq!(perimeters[&0], 12);
+/// assert_eq!(perimeters[&1], 4);
+/// # Ok(())
+/// # }
+/// ```
+pub fn region_perimeters(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+) -> Result<HashMap<usize, usize>, ScError> {
+    if labels.len() != pixel_count(width, height)? {
+        return Err(ScError::General(
+            "Label buffer does not match image dimensions",
+        ));
+    }
+
+    let mut perimeters = HashMap::<usize, usize>::default();
+    let width_i = i64::from(width);
+    let height_i = i64::from(height);
+
+    let mut label_iter = labels.iter();
+    for y in 0..height_i {
+        for x in 0..width_i {
+            let &label = label_iter.next().ok_or("Labels exhausted")?;
+            let perimeter = perimeters.entry(label).or_default();
+
+            if Connectivity::Four.offsets().iter().any(|&(d_x, d_y)| {
+                get_in_bounds(width_i, height_i, x + d_x, y + d_y, labels) != Some(&label)
+            }) {
+                *perimeter += 1;
+            }
+        }
+    }
+
+    Ok(perimeters)
+}
+
+/// Calculate a histogram of the boundary orientations of each label, such as
+/// for describing the shape of superpixels as feature vectors.
+///
+/// A pixel is on the boundary of its region if one of its 4-connected
+/// neighbors has a different label. The boundary tangent at each of these
+/// pixels is found from the Sobel gradient of the region's 3x3 membership
+/// mask, and its undirected orientation in `[0, π)` is counted into one of
+/// `bins` equal bins, where bin `0` starts at horizontal. The image is
+/// extended by repeating its edge pixels, so the image border doesn't count as
+/// a boundary. Each histogram is normalized to sum to `1`, or is all
+/// zeros for a region without boundary pixels.
+///
+/// ```
+/// use simple_clustering::image::region_boundary_orientations;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // Two regions split by a vertical boundary
+/// let labels = [0, 0, 1, 1].repeat(4);
+/// let histograms = region_boundary_orientations(&labels, 4, 4, 4)?;
+/// // All boundary tangents are vertical, in the bin covering `[π/2, 3π/4)`
+/// assert_eq!(histograms[&0], [0.0, 0.0, 1.0, 0.0]);
+/// assert_eq!(histograms[&1], [0.0, 0.0, 1.0, 0.0]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` must have a length of `width * height`.
+/// `bins` must not be `0`.
+pub fn region_boundary_orientations(
+    labels: &[usize],
+    width: u32,
+    height: u32,
+    bins: usize,
+) -> Result<HashMap<usize, Vec<f32>>, ScError> {
+    if labels.len() != pixel_count(width, height)? {
+        return Err(ScError::General(
+            "Label buffer does not match image dimensions",
+        ));
+    }
+    if bins == 0 {
+        return Err(ScError::General("Orientation bin count must not be 0"));
+    }
+
+    let width_i = i64::from(width);
+    let height_i = i64::from(height);
+    // Membership of the pixel at `(x, y)` in `label`, extending the image by
+    // repeating its edge pixels
+    let member = |x: i64, y: i64, label: usize| -> f64 {
+        let (x, y) = (x.clamp(0, width_i - 1), y.clamp(0, height_i - 1));
+        match get_in_bounds(width_i, height_i, x, y, labels) {
+            Some(&n) if n == label => 1.0,
+            _ => 0.0,
+        }
+    };
+
+    let mut histograms = HashMap::<usize, Vec<f64>>::default();
+    let mut label_iter = labels.iter();
+    for y in 0..height_i {
+        for x in 0..width_i {
+            let &label = label_iter.next().ok_or("Labels exhausted")?;
+            let histogram = histograms.entry(label).or_default();
+            if histogram.is_empty() {
+                histogram.try_reserve_exact(bins)?;
+                histogram.extend((0..bins).map(|_| 0.0));
+            }
+
+            let on_boundary = Connectivity::Four
+                .offsets()
+                .iter()
+                .any(|&(d_x, d_y)| member(x + d_x, y + d_y, label) == 0.0);
+            if !on_boundary {
+                continue;
+            }
+
+            let m = |d_x: i64, d_y: i64| member(x + d_x, y + d_y, label);
+            let g_x =
+                (m(1, -1) + 2.0 * m(1, 0) + m(1, 1)) - (m(-1, -1) + 2.0 * m(-1, 0) + m(-1, 1));
+            let g_y =
+                (m(-1, 1) + 2.0 * m(0, 1) + m(1, 1)) - (m(-1, -1) + 2.0 * m(0, -1) + m(1, -1));
+            if g_x == 0.0 && g_y == 0.0 {
+                continue;
+            }
+
+            // The tangent is perpendicular to the gradient
+            let orientation =
+                (g_y.atan2(g_x) + std::f64::consts::FRAC_PI_2).rem_euclid(std::f64::consts::PI);
+            #[allow(clippy::cast_precision_loss)]
+            let bin = (orientation / std::f64::consts::PI * bins as f64)
+                .to_usize()
+                .unwrap_or_default()
+                .min(bins - 1);
+            if let Some(count) = histogram.get_mut(bin) {
+                *count += 1.0;
+            }
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    Ok(histograms
+        .into_iter()
+        .map(|(label, histogram)| {
+            let total: f64 = histogram.iter().sum();
+            let histogram = histogram
+                .iter()
+                .map(|&count| {
+                    if total > 0.0 {
+                        (count / total) as f32
+                    } else {
+                        0.0
+                    }
+                })
+                .collect();
+            (label, histogram)
+        })
+        .collect())
+}
+
+/// Calculate the centroid of each label as `(x, y)` pixel coordinates, such as
+/// for placing a text anchor for the label of each region.
+///
+/// The centroid is the mean position of the pixels of a label, so it can fall
+/// outside of regions that aren't convex.
+///
+/// ```
+/// use simple_clustering::image::label_centroids;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let labels = [
+///     0, 0, 1, 1,
+///     0, 0, 1, 1,
+/// ];
+/// let centroids = label_centroids(&labels, 4, 2)?;
+/// assert_eq!(centroids[&0], (0.5, 0.5));
+/// assert_eq!(centroids[&1], (2.5, 0.5));
+/// # Ok(())
+/// # }
+/// ```
+pub fn label_centroids(
+    labels: &[usize],
+    width: u32,
+    height: u32,
+) -> Result<HashMap<usize, (f32, f32)>, ScError> {
+    if labels.len() != pixel_count(width, height)? {
+        return Err(ScError::General(
+            "Label buffer does not match image dimensions",
+        ));
+    }
+
+    // Sum of x, sum of y, and pixel count of each label
+    let mut sums = HashMap::<usize, (f64, f64, f64)>::default();
+    let mut label_iter = labels.iter();
+    for y in 0..height {
+        for x in 0..width {
+            let &label = label_iter.next().ok_or("Labels exhausted")?;
+            let sum = sums.entry(label).or_default();
+            sum.0 += f64::from(x);
+            sum.1 += f64::from(y);
+            sum.2 += 1.0;
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    Ok(sums
+        .into_iter()
+        .map(|(label, (x, y, count))| (label, ((x / count) as f32, (y / count) as f32)))
+        .collect())
+}
+
+/// Modify `output` to contain an RGB image where each superpixel segment is
+/// filled with a color from a generated categorical palette.
+///
+/// Palette entries are spaced around the hue wheel by the golden ratio.
+/// Adjacent segments are always assigned different palette entries, so
+/// neighboring regions are easy to tell apart.
+pub fn colorize_labels(
+    labels: &[usize],
+    width: u32,
+    height: u32,
+    output: &mut [u8],
+) -> Result<(), ScError> {
+    if Some(output.len()) != labels.len().checked_mul(3) {
+        return Err(ScError::General(
+            "Colorize buffer does not match label length",
+        ));
+    }
+
+    let adjacency = region_adjacency(width, height, labels)?;
+
+    // Greedily color the adjacency graph in label order, picking the lowest
+    // palette index that no already colored neighbor uses
+    let mut keys = Vec::new();
+    keys.try_reserve_exact(adjacency.len())?;
+    keys.extend(adjacency.keys().copied());
+    keys.sort_unstable();
+
+    let mut palette_indices = HashMap::<usize, u32>::default();
+    palette_indices.try_reserve(keys.len())?;
+    let mut used = HashSet::<u32>::default();
+    for key in &keys {
+        used.clear();
+        if let Some(neighbors) = adjacency.get(key) {
+            used.extend(neighbors.iter().filter_map(|n| palette_indices.get(n)));
+        }
+        let index = (0..).find(|i| !used.contains(i)).unwrap_or_default();
+        let _ = palette_indices.insert(*key, index);
+    }
+
+    // The conjugate of the golden ratio spreads consecutive hues far apart
+    let golden_ratio_conjugate = 0.618_033_988_749_895;
+    let mut rgb_map = HashMap::<usize, Srgb<u8>>::default();
+    rgb_map.try_reserve(palette_indices.len())?;
+    rgb_map.extend(palette_indices.iter().map(|(&key, &index)| {
+        let hue = (f64::from(index) * golden_ratio_conjugate).fract() * 360.0;
+        let rgb: Srgb<u8> = Srgb::from_color(Hsv::new(hue, 0.65, 0.95)).into_format();
+        (key, rgb)
+    }));
+
+    output
+        .chunks_exact_mut(3)
+        .zip(labels.iter().filter_map(|a| rgb_map.get(a)))
+        .for_each(|(chunk, color)| chunk.copy_from_slice(color.into()));
+
+    Ok(())
+}
+
+/// Modify `output` to contain a grayscale heatmap of per-pixel distances, such
+/// as those returned by [`slic_with_distances`](crate::slic_with_distances).
+///
+/// The finite distances are scaled from their minimum at `0` to their maximum
+/// at `255`, so brighter pixels fit their superpixel more poorly. Infinite and
+/// `NaN` distances, such as those of unassigned pixels, are `255`. If all
+/// finite distances are equal, they are `0`.
+///
+/// ```
+/// use simple_clustering::image::distances_to_gray;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let distances = [2.0, 4.0, 6.0, f64::INFINITY];
+/// let mut output = [0; 4];
+/// distances_to_gray(&distances, &mut output)?;
+/// assert_eq!(output, [0, 128, 255, 255]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `output` must have the same length as `distances`.
+pub fn distances_to_gray(distances: &[f64], output: &mut [u8]) -> Result<(), ScError> {
+    if output.len() != distances.len() {
+        return Err(ScError::General(
+            "Grayscale buffer does not match distance length",
+        ));
+    }
+
+    let (min, max) = distances
+        .iter()
+        .filter(|d| d.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &d| {
+            (min.min(d), max.max(d))
+        });
+    let range = max - min;
+
+    for (out, &distance) in output.iter_mut().zip(distances) {
+        *out = if !distance.is_finite() {
+            u8::MAX
+        } else if range > 0.0 {
+            ((distance - min) / range * 255.0)
+                .round()
+                .to_u8()
+                .unwrap_or(u8::MAX)
+        } else {
+            0
+        };
+    }
+
+    Ok(())
+}
+
+/// Create an SVG document outlining each superpixel region with a `<path>`.
+///
+/// Each path traces the boundary of one region along the pixel corners, so all
+/// coordinates are integers between `0` and the image dimensions. Regions at
+/// the image border follow the image edge. A region with holes or several
+/// disconnected parts has a closed subpath for each boundary. The paths are
+/// ordered by label and drawn unfilled with the `stroke` color.
+///
+/// ```
+/// use simple_clustering::image::segments_to_svg;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let labels = [0, 0, 1, 1];
+/// let svg = segments_to_svg(2, 2, &labels, [0, 0, 0])?;
+/// assert!(svg.contains(r#"<path d="M0 0L2 0L2 1L0 1Z""#));
+/// assert!(svg.contains(r#"<path d="M0 1L2 1L2 2L0 2Z""#));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` must have a length of `width * height`.
+pub fn segments_to_svg(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    stroke: [u8; 3],
+) -> Result<String, ScError> {
+    if labels.len() != pixel_count(width, height)? {
+        return Err(ScError::General("Label buffer does not match image length"));
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+
+    // Directed boundary edges of each region between pixel corners, keyed by
+    // their start corner. Edges run clockwise around the region.
+    type Corner = (u32, u32);
+    let mut edges = HashMap::<usize, HashMap<Corner, Vec<Corner>>>::default();
+    let label_at = |x: u32, y: u32| {
+        labels.get(usize::try_from(u64::from(y) * u64::from(width) + u64::from(x)).ok()?)
+    };
+
+    if width_usize != 0 {
+        for (y, row) in (0..height).zip(labels.chunks_exact(width_usize)) {
+            for (x, &label) in (0..width).zip(row) {
+                let differs = |other: Option<&usize>| other != Some(&label);
+                let region = edges.entry(label).or_default();
+                let mut add =
+                    |start: Corner, end: Corner| region.entry(start).or_default().push(end);
+
+                if y == 0 || differs(label_at(x, y - 1)) {
+                    add((x, y), (x + 1, y));
+                }
+                if differs(label_at(x + 1, y).filter(|_| x + 1 < width)) {
+                    add((x + 1, y), (x + 1, y + 1));
+                }
+                if differs(label_at(x, y + 1)) {
+                    add((x + 1, y + 1), (x, y + 1));
+                }
+                if x == 0 || differs(label_at(x - 1, y)) {
+                    add((x, y + 1), (x, y));
+                }
+            }
+        }
+    }
+
+    let [r, g, b] = stroke;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n"
+    );
+
+    let mut keys = Vec::new();
+    keys.try_reserve_exact(edges.len())?;
+    keys.extend(edges.keys().copied());
+    keys.sort_unstable();
+
+    let mut path = String::new();
+    for key in keys {
+        let Some(region) = edges.get_mut(&key) else {
+            continue;
+        };
+        path.clear();
+
+        // Follow unused edges from the top left corner until each loop closes
+        let mut starts: Vec<Corner> = region.keys().copied().collect();
+        starts.sort_unstable_by_key(|&(x, y)| (y, x));
+        for start in starts {
+            while let Some(mut end) = region.get_mut(&start).and_then(Vec::pop) {
+                let mut direction = (
+                    i64::from(end.0) - i64::from(start.0),
+                    i64::from(end.1) - i64::from(start.1),
+                );
+                let _ = write!(path, "M{} {}", start.0, start.1);
+
+                while end != start {
+                    let Some(next) = region.get_mut(&end).and_then(Vec::pop) else {
+                        break;
+                    };
+                    let next_direction = (
+                        i64::from(next.0) - i64::from(end.0),
+                        i64::from(next.1) - i64::from(end.1),
+                    );
+                    // Only emit the corners where the boundary turns
+                    if next_direction != direction {
+                        let _ = write!(path, "L{} {}", end.0, end.1);
+                        direction = next_direction;
+                    }
+                    end = next;
+                }
+                path.push('Z');
+            }
+        }
+
+        let _ = writeln!(
+            svg,
+            "<path d=\"{path}\" fill=\"none\" stroke=\"#{r:02x}{g:02x}{b:02x}\"/>"
+        );
+    }
+    svg.push_str("</svg>\n");
+
+    Ok(svg)
+}
+
+/// Iterate over the `(x, y)` coordinates of the pixels assigned to `label`.
+///
+/// Every call scans the whole label slice. For repeated per-region queries,
+/// build a [`LabelIndex`] once instead.
+pub fn pixels_of_label(
+    labels: &[usize],
+    width: u32,
+    label: usize,
+) -> impl Iterator<Item = (u32, u32)> + '_ {
+    let width_usize = usize::try_from(width).unwrap_or_default();
+    labels
+        .iter()
+        .enumerate()
+        .filter(move |&(_, &l)| l == label && width_usize != 0)
+        .filter_map(move |(idx, _)| index_to_xy(idx, width_usize).ok())
+}
+
+/// Index mapping each superpixel label to the buffer indices of its pixels.
+///
+/// The index is built in one pass over the labels. It stores one `usize` for
+/// every pixel in addition to a vector allocation per label, so it takes about
+/// as much memory as the label slice itself. In exchange, queries only visit
+/// the pixels of the requested label.
+#[derive(Debug, Clone, Default)]
+pub struct LabelIndex {
+    /// Width of the labeled image.
+    width: u32,
+    /// Pixel indices of each label, in increasing order.
+    indices: HashMap<usize, Vec<usize>>,
+}
+
+impl LabelIndex {
+    /// Build a [`LabelIndex`] from a slice of superpixel labels.
+    ///
+    /// `width` must not be `0` unless `labels` is empty.
+    pub fn new(labels: &[usize], width: u32) -> Result<Self, ScError> {
+        if width == 0 && !labels.is_empty() {
+            return Err(ScError::InvalidImageDimension);
+        }
+
+        let mut indices = HashMap::<usize, Vec<usize>>::default();
+        for (idx, &label) in labels.iter().enumerate() {
+            let entry = indices.entry(label).or_default();
+            if entry.capacity() == entry.len() {
+                entry.try_reserve(entry.len().max(4))?;
+            }
+            entry.push(idx);
+        }
+
+        Ok(Self { width, indices })
+    }
+
+    /// The number of distinct labels in the index.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns `true` if the index contains no labels.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Iterate over the distinct labels in the index, in arbitrary order.
+    pub fn labels(&self) -> impl Iterator<Item = usize> + '_ {
+        self.indices.keys().copied()
+    }
+
+    /// The buffer indices of the pixels assigned to `label`, in increasing
+    /// order. The slice is empty if the label does not exist.
+    #[must_use]
+    pub fn indices(&self, label: usize) -> &[usize] {
+        self.indices.get(&label).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterate over the `(x, y)` coordinates of the pixels assigned to `label`.
+    pub fn pixels(&self, label: usize) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let width = usize::try_from(self.width).unwrap_or_default();
+        self.indices(label)
+            .iter()
+            .filter_map(move |&idx| index_to_xy(idx, width).ok())
+    }
+}
+
+/// Superpixel labels bundled with the dimensions of the labeled image.
+///
+/// The methods forward to the free functions of this module, so the width and
+/// height don't have to be passed alongside the labels each time. A
+/// [`Labeling`] can be built from the output of [`slic`](crate::slic) or
+/// [`snic`](crate::snic) with [`Labeling::new`].
+///
+/// ```
+/// use simple_clustering::image::Labeling;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let labeling = Labeling::new(vec![0, 0, 1, 1], 2, 2)?;
+/// assert_eq!(labeling.sizes()?[&0], 2);
+/// assert!(labeling.adjacency()?[&0].contains(&1));
+///
+/// let mut output = [0; 12];
+/// labeling.contours(&mut output, [255, 0, 0])?;
+/// assert_eq!(labeling.into_labels(), vec![0, 0, 1, 1]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Labeling {
+    /// Label of each pixel.
+    labels: Vec<usize>,
+    /// Width of the labeled image.
+    width: u32,
+    /// Height of the labeled image.
+    height: u32,
+}
+
+impl Labeling {
+    /// Bundle `labels` with the dimensions of the image they were calculated
+    /// for.
+    ///
+    /// `labels` must have a length of `width * height`.
+    pub fn new(labels: Vec<usize>, width: u32, height: u32) -> Result<Self, ScError> {
+        if labels.len() != pixel_count(width, height)? {
+            return Err(ScError::General(
+                "Label buffer does not match image dimensions",
+            ));
+        }
+
+        Ok(Self {
+            labels,
+            width,
+            height,
+        })
+    }
+
+    /// The label of each pixel.
+    #[must_use]
+    pub fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    /// Width of the labeled image.
+    #[must_use]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the labeled image.
+    #[must_use]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Unwrap the label of each pixel.
+    #[must_use]
+    pub fn into_labels(self) -> Vec<usize> {
+        self.labels
+    }
+
+    /// The number of pixels of each label, see [`superpixel_sizes`].
+    pub fn sizes(&self) -> Result<HashMap<usize, usize>, ScError> {
+        superpixel_sizes(&self.labels)
+    }
+
+    /// The labels which border each label, see [`region_adjacency`].
+    pub fn adjacency(&self) -> Result<HashMap<usize, HashSet<usize>>, ScError> {
+        region_adjacency(self.width, self.height, &self.labels)
+    }
+
+    /// Draw the contours of the labels into an RGB `output`, see
+    /// [`segment_contours`].
+    pub fn contours(&self, output: &mut [u8], segment_color: [u8; 3]) -> Result<(), ScError> {
+        segment_contours(output, self.width, self.height, &self.labels, segment_color)
+    }
+
+    /// Fill an RGB `output` with the mean color of each label, see
+    /// [`mean_colors`]. The return value is the count of superpixels.
+    ///
+    /// `image` must have a length of `width * height`.
+    pub fn mean_colors<Wp>(
+        &self,
+        output: &mut [u8],
+        image: &[Lab<Wp, f64>],
+    ) -> Result<usize, ScError>
+    where
+        Wp: WhitePoint<f64>,
+        Lab<Wp, f64>: IntoColorUnclamped<Rgb<encoding::Srgb, f64>>,
+    {
+        if image.len() != self.labels.len() {
+            return Err(ScError::General(
+                "Image buffer does not match label dimensions",
+            ));
+        }
+        mean_colors(output, count_colors(&self.labels), &self.labels, image)
+    }
+}
+
+/// Summary statistics of a superpixel region, calculated by [`region_stats`].
+#[derive(Debug, Clone, Copy)]
+pub struct RegionStats<Wp> {
+    /// Number of pixels in the region.
+    pub count: usize,
+    /// Mean `(x, y)` coordinate of the region's pixels.
+    pub centroid: (f64, f64),
+    /// Mean color of the region.
+    pub mean: Lab<Wp, f64>,
+    /// Mean squared `Lab` distance of the region's pixels from `mean`.
+    pub variance: f64,
+    /// Inclusive bounding box of the region as `(min_x, min_y, max_x, max_y)`.
+    pub bbox: (u32, u32, u32, u32),
+}
+
+/// Calculate the size, centroid, mean color, color variance, and bounding box
+/// of every region in a single pass over the image.
+///
+/// Gathering the statistics together only reads `labels` and `image` once,
+/// which is faster on large images than computing each one separately. An
+/// image without pixels has no regions.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::image::region_stats;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [Lab::<D65, f64>::new(20.0, 0.0, 0.0), Lab::new(60.0, 0.0, 0.0)];
+/// let stats = region_stats(2, 1, &[3, 3], &image)?;
+/// assert_eq!(stats[&3].count, 2);
+/// assert_eq!(stats[&3].mean.l, 40.0);
+///
+/// assert!(region_stats::<D65>(0, 5, &[], &[])?.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `image` must both have a length of `width * height`.
+pub fn region_stats<Wp>(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+) -> Result<HashMap<usize, RegionStats<Wp>>, ScError> {
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || image.len() != len {
+        return Err(ScError::General(
+            "Label or image buffer does not match image dimensions",
+        ));
+    }
+    if len == 0 {
+        return Ok(HashMap::default());
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+
+    // Sums of the coordinates, colors, and squared colors of each region
+    struct Accumulator<Wp> {
+        count: usize,
+        x: f64,
+        y: f64,
+        color: Lab<Wp, f64>,
+        squared: f64,
+        bbox: (u32, u32, u32, u32),
+    }
+
+    let mut accumulators = HashMap::<usize, Accumulator<Wp>>::default();
+    for (y, (label_row, row)) in (0..height).zip(
+        labels
+            .chunks_exact(width_usize)
+            .zip(image.chunks_exact(width_usize)),
+    ) {
+        for (x, (&label, &color)) in (0..width).zip(label_row.iter().zip(row)) {
+            let squared = color.l * color.l + color.a * color.a + color.b * color.b;
+            let _ = accumulators
+                .entry(label)
+                .and_modify(|acc| {
+                    acc.count += 1;
+                    acc.x += f64::from(x);
+                    acc.y += f64::from(y);
+                    acc.color += color;
+                    acc.squared += squared;
+                    acc.bbox = (
+                        acc.bbox.0.min(x),
+                        acc.bbox.1.min(y),
+                        acc.bbox.2.max(x),
+                        acc.bbox.3.max(y),
+                    );
+                })
+                .or_insert(Accumulator {
+                    count: 1,
+                    x: f64::from(x),
+                    y: f64::from(y),
+                    color,
+                    squared,
+                    bbox: (x, y, x, y),
+                });
+        }
+    }
+
+    let mut stats = HashMap::<usize, RegionStats<Wp>>::default();
+    stats.try_reserve(accumulators.len())?;
+    stats.extend(accumulators.into_iter().map(|(label, acc)| {
+        #[allow(clippy::cast_precision_loss)]
+        let count = acc.count as f64;
+        let mean = acc.color / count;
+        let mean_squared = mean.l * mean.l + mean.a * mean.a + mean.b * mean.b;
+        (
+            label,
+            RegionStats {
+                count: acc.count,
+                centroid: (acc.x / count, acc.y / count),
+                mean,
+                // E[|c|^2] - |E[c]|^2, clamped for rounding error
+                variance: (acc.squared / count - mean_squared).max(0.0),
+                bbox: acc.bbox,
+            },
+        )
+    }));
+
+    Ok(stats)
+}
+
+/// Cropped RGB image of a single region as `(tile_width, tile_height,
+/// rgb_bytes)`, returned by [`extract_region_tiles`].
+pub type RegionTile = (u32, u32, Vec<u8>);
+
+/// Inclusive bounding box of a region as `(min_x, min_y, max_x, max_y)`.
+type Bounds = (u32, u32, u32, u32);
+
+/// Crop each region out of an RGB image into its own tile, for building
+/// datasets of individual superpixels.
+///
+/// Each tile covers the tight bounding box of its region and is returned as
+/// `(tile_width, tile_height, rgb_bytes)`, keyed by label. Pixels of the tile
+/// which belong to other regions are set to `fill`.
+///
+/// ```
+/// use simple_clustering::image::extract_region_tiles;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let labels = [
+///     0, 0, 1,
+///     0, 1, 1,
+/// ];
+/// let image = [10; 3 * 6];
+/// let tiles = extract_region_tiles(&labels, &image, 3, 2, [0, 0, 255])?;
+///
+/// let (tile_width, tile_height, bytes) = &tiles[&0];
+/// assert_eq!((*tile_width, *tile_height), (2, 2));
+/// assert_eq!(bytes[..], [10, 10, 10, 10, 10, 10, 10, 10, 10, 0, 0, 255]);
+///
+/// // An image without pixels has no tiles
+/// assert!(extract_region_tiles(&[], &[], 0, 5, [0; 3])?.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` must have a length of `width * height` and `image` must have three
+/// components for each pixel.
+pub fn extract_region_tiles(
+    labels: &[usize],
+    image: &[u8],
+    width: u32,
+    height: u32,
+    fill: [u8; 3],
+) -> Result<HashMap<usize, RegionTile>, ScError> {
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || len.checked_mul(3) != Some(image.len()) {
+        return Err(ScError::General(
+            "Label or image buffer does not match image dimensions",
+        ));
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+
+    let bounds = region_bounds(width, height, labels)?;
+    let mut tiles = HashMap::<usize, RegionTile>::default();
+    tiles.try_reserve(bounds.len())?;
+    for (&label, &(min_x, min_y, max_x, max_y)) in &bounds {
+        let (tile_width, tile_height) = (max_x - min_x + 1, max_y - min_y + 1);
+        let tile_len = pixel_count(tile_width, tile_height)?
+            .checked_mul(3)
+            .ok_or(ScError::Overflow("region tile length"))?;
+        let mut bytes = Vec::new();
+        bytes.try_reserve_exact(tile_len)?;
+        bytes.extend(fill.iter().cycle().take(tile_len));
+        let _ = tiles.insert(label, (tile_width, tile_height, bytes));
+    }
+
+    for (idx, (&label, rgb)) in labels.iter().zip(image.chunks_exact(3)).enumerate() {
+        let (x, y) = index_to_xy(idx, width_usize)?;
+        let (min_x, min_y, ..) = *bounds.get(&label).ok_or("Missing region bounds")?;
+        let (tile_width, _, bytes) = tiles.get_mut(&label).ok_or("Missing region tile")?;
+        let tile_idx = index(*tile_width, x - min_x, y - min_y)?
+            .checked_mul(3)
+            .ok_or(ScError::Overflow("region tile index"))?;
+        bytes
+            .get_mut(tile_idx..tile_idx + 3)
+            .ok_or("Tile pixel out of bounds")?
+            .copy_from_slice(rgb);
+    }
+
+    Ok(tiles)
+}
+
+/// Find the inclusive bounding box of every region. An image without pixels
+/// has no regions.
+fn region_bounds(
+    width: u32,
+    height: u32,
+    labels: &[usize],
+) -> Result<HashMap<usize, Bounds>, ScError> {
+    let mut bounds = HashMap::<usize, Bounds>::default();
+    if width == 0 || height == 0 {
+        return Ok(bounds);
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+    for (y, label_row) in (0..height).zip(labels.chunks_exact(width_usize)) {
+        for (x, &label) in (0..width).zip(label_row) {
+            let _ = bounds
+                .entry(label)
+                .and_modify(|(min_x, min_y, max_x, max_y)| {
+                    *min_x = (*min_x).min(x);
+                    *min_y = (*min_y).min(y);
+                    *max_x = (*max_x).max(x);
+                    *max_y = (*max_y).max(y);
+                })
+                .or_insert((x, y, x, y));
+        }
+    }
+
+    Ok(bounds)
+}
+
+/// Calculate the mean `Lab` gradient magnitude over the pixels of every region.
+///
+/// The gradient of a pixel is the one used to perturb seeds,
+/// `sqrt(|I[x + 1, y] - I[x - 1, y]|^2 + |I[x, y + 1] - I[x, y - 1]|^2)`,
+/// with the pixel's own color standing in for neighbors outside of the image.
+/// Flat regions have an edginess close to `0`, while textured or detailed
+/// regions have larger values.
+///
+/// ```
+/// use palette::Lab;
+/// use simple_clustering::image::region_edginess;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // Flat left half and striped right half
+/// let image: Vec<_> = (0..16)
+///     .map(|i| {
+///         let l = if i % 4 < 2 { 50.0 } else { 50.0 + 10.0 * f64::from(i % 2) };
+///         Lab::<palette::white_point::D65, f64>::new(l, 0.0, 0.0)
+///     })
+///     .collect();
+/// let labels = [0, 0, 1, 1].repeat(4);
+/// let edginess = region_edginess(&labels, &image, 4, 4)?;
+/// assert!(edginess[&0] < edginess[&1]);
+///
+/// // An image without pixels has no regions
+/// assert!(region_edginess::<palette::white_point::D65>(&[], &[], 0, 5)?.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `image` must both have a length of `width * height`.
+pub fn region_edginess<Wp>(
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    width: u32,
+    height: u32,
+) -> Result<HashMap<usize, f64>, ScError> {
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || image.len() != len {
+        return Err(ScError::General(
+            "Label or image buffer does not match image dimensions",
+        ));
+    }
+    if len == 0 {
+        return Ok(HashMap::default());
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+    let view = ImageView::new(image, width);
+
+    // Sum of the gradients and the pixel count of each region
+    let mut sums = HashMap::<usize, (f64, usize)>::default();
+    for (y, (label_row, row)) in (0..height).zip(
+        labels
+            .chunks_exact(width_usize)
+            .zip(image.chunks_exact(width_usize)),
+    ) {
+        for (x, (&label, &color)) in (0..width).zip(label_row.iter().zip(row)) {
+            let gradient = gradient(view, i64::from(x), i64::from(y), color).sqrt();
+            let sum = sums.entry(label).or_default();
+            sum.0 += gradient;
+            sum.1 += 1;
+        }
+    }
+
+    let mut edginess = HashMap::default();
+    edginess.try_reserve(sums.len())?;
+    #[allow(clippy::cast_precision_loss)]
+    edginess.extend(
+        sums.into_iter()
+            .map(|(label, (sum, count))| (label, sum / count as f64)),
+    );
+
+    Ok(edginess)
+}
+
+/// Find the darkest and brightest `Lab` lightness of every region as
+/// `(min_l, max_l)`.
+///
+/// ```
+/// use palette::Lab;
+/// use simple_clustering::image::region_lightness_range;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [
+///     Lab::<palette::white_point::D65, f64>::new(20.0, 0.0, 0.0),
+///     Lab::new(60.0, 0.0, 0.0),
+///     Lab::new(45.0, 0.0, 0.0),
+/// ];
+/// let ranges = region_lightness_range(&[0, 0, 1], &image)?;
+/// assert_eq!(ranges[&0], (20.0, 60.0));
+/// assert_eq!(ranges[&1], (45.0, 45.0));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `image` must have the same length.
+pub fn region_lightness_range<Wp>(
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+) -> Result<HashMap<usize, (f64, f64)>, ScError> {
+    if labels.len() != image.len() {
+        return Err(ScError::General("Label buffer does not match image length"));
+    }
+
+    let mut ranges = HashMap::<usize, (f64, f64)>::default();
+    for (&label, color) in labels.iter().zip(image) {
+        let _ = ranges
+            .entry(label)
+            .and_modify(|(min, max)| {
+                *min = min.min(color.l);
+                *max = max.max(color.l);
+            })
+            .or_insert((color.l, color.l));
+    }
+
+    Ok(ranges)
+}
+
+/// Find the `p`th percentile of the `Lab` lightness of every region.
+///
+/// `p` is between `0.0` and `100.0`, where `0.0` and `100.0` are the bounds of
+/// [`region_lightness_range`] and `50.0` is the median. Percentiles between
+/// two pixels are linearly interpolated.
+///
+/// ```
+/// use palette::Lab;
+/// use simple_clustering::image::region_lightness_percentile;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image: Vec<Lab<palette::white_point::D65, f64>> =
+///     (0..=10).map(|l| Lab::new(f64::from(l) * 10.0, 0.0, 0.0)).collect();
+/// let labels = [0; 11];
+/// assert_eq!(region_lightness_percentile(&labels, &image, 90.0)?[&0], 90.0);
+/// assert_eq!(region_lightness_percentile(&labels, &image, 25.0)?[&0], 25.0);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `image` must have the same length.
+pub fn region_lightness_percentile<Wp>(
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    p: f64,
+) -> Result<HashMap<usize, f64>, ScError> {
+    if labels.len() != image.len() {
+        return Err(ScError::General("Label buffer does not match image length"));
+    }
+    if !(0.0..=100.0).contains(&p) {
+        return Err(ScError::General("Percentile must be between 0 and 100"));
+    }
+
+    let mut regions = HashMap::<usize, Vec<f64>>::default();
+    for (&label, color) in labels.iter().zip(image) {
+        let region = regions.entry(label).or_default();
+        if region.capacity() == region.len() {
+            region.try_reserve(region.len().max(4))?;
+        }
+        region.push(color.l);
+    }
+
+    let mut percentiles = HashMap::<usize, f64>::default();
+    percentiles.try_reserve(regions.len())?;
+    percentiles.extend(regions.into_iter().map(|(label, mut values)| {
+        values.sort_unstable_by(f64::total_cmp);
+        #[allow(clippy::cast_precision_loss)]
+        let rank = 0.01 * p * (values.len() - 1) as f64;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let lower = rank.floor() as usize;
+        let fraction = rank - rank.floor();
+        let value = match (values.get(lower), values.get(lower + 1)) {
+            (Some(&lower), Some(&upper)) => lower + fraction * (upper - lower),
+            (Some(&lower), None) => lower,
+            _ => 0.0,
+        };
+        (label, value)
+    }));
+
+    Ok(percentiles)
+}
+
+/// Merge regions smaller than `min_area` pixels into their most similar
+/// neighbor. The return value is the count of regions after merging.
+///
+/// Regions are visited from smallest to largest. Each small region is merged
+/// into the adjacent region whose mean color is closest by `Lab` distance, and
+/// the merged region's mean color is updated before the next merge. A region
+/// with no neighbors is left as-is.
+///
+/// Unlike the size threshold used while enforcing connectivity in SLIC, this
+/// step is color-aware and can be run after either algorithm.
+pub fn merge_small_regions<Wp>(
+    width: u32,
+    height: u32,
+    labels: &mut [usize],
+    image: &[Lab<Wp, f64>],
+    min_area: usize,
+) -> Result<usize, ScError> {
+    merge_small_regions_impl(width, height, labels, image, min_area, None)
+}
+
+/// Merge regions smaller than `min_area` pixels into their most similar
+/// neighbor like [`merge_small_regions`], recording each merge in `log` so it
+/// can be reverted with [`undo`]. The return value is the count of regions
+/// after merging.
+///
+/// `log` is cleared before merging, so it only holds the merges of this call.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::image::{merge_small_regions_with_log, undo, MergeLog};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [Lab::<D65, f64>::new(50.0, 0.0, 0.0); 4];
+/// let original = [0, 0, 0, 1];
+/// let mut labels = original;
+/// let mut log = MergeLog::new();
+/// assert_eq!(merge_small_regions_with_log(4, 1, &mut labels, &image, 2, &mut log)?, 1);
+/// assert_eq!(labels, [0, 0, 0, 0]);
+/// assert_eq!(log.records()[0].pixels, [3]);
+///
+/// undo(&mut labels, &log)?;
+/// assert_eq!(labels, original);
+/// # Ok(())
+/// # }
+/// ```
+pub fn merge_small_regions_with_log<Wp>(
+    width: u32,
+    height: u32,
+    labels: &mut [usize],
+    image: &[Lab<Wp, f64>],
+    min_area: usize,
+    log: &mut MergeLog,
+) -> Result<usize, ScError> {
+    merge_small_regions_impl(width, height, labels, image, min_area, Some(log))
+}
+
+/// [`merge_small_regions`] implementation, optionally recording the merges.
+fn merge_small_regions_impl<Wp>(
+    width: u32,
+    height: u32,
+    labels: &mut [usize],
+    image: &[Lab<Wp, f64>],
+    min_area: usize,
+    mut log: Option<&mut MergeLog>,
+) -> Result<usize, ScError> {
+    if let Some(log) = log.as_deref_mut() {
+        log.clear();
+    }
+    if labels.len() != image.len() {
+        return Err(ScError::General("Label buffer does not match image length"));
+    }
+
+    let mut adjacency = region_adjacency(width, height, labels)?;
+
+    // Accumulated color and pixel count of each region
+    let mut regions = HashMap::<usize, (Lab<Wp, f64>, usize)>::default();
+    regions.try_reserve(adjacency.len())?;
+    for (&label, &color) in labels.iter().zip(image) {
+        let _ = regions
+            .entry(label)
+            .and_modify(|e| {
+                e.0 += color;
+                e.1 += 1;
+            })
+            .or_insert((color, 1));
+    }
+
+    let mut order = Vec::new();
+    order.try_reserve_exact(regions.len())?;
+    order.extend(
+        regions
+            .iter()
+            .filter(|(_, &(_, count))| count < min_area)
+            .map(|(&label, &(_, count))| (count, label)),
+    );
+    order.sort_unstable();
+
+    // Labels which have been merged away, mapped to the label they merged into
+    let mut merged = HashMap::<usize, usize>::default();
+
+    for &(_, label) in &order {
+        let (color, count) = match regions.get(&label) {
+            Some(&region) if region.1 < min_area => region,
+            _ => continue,
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let mean = color / count as f64;
+
+        #[allow(clippy::cast_precision_loss)]
+        let target = adjacency
+            .get(&label)
+            .into_iter()
+            .flatten()
+            .filter_map(|n| regions.get(n).map(|&(c, n_count)| (n, c / n_count as f64)))
+            .map(|(&n, n_mean)| (distance_lab(mean, n_mean), n))
+            .min_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)))
+            .map(|(_, n)| n);
+
+        if let Some(target) = target {
+            merge_region(label, target, &mut regions, &mut adjacency, &mut merged);
+            if let Some(log) = log.as_deref_mut() {
+                log.push(label, target)?;
+            }
+        }
+    }
+
+    if let Some(log) = log {
+        log.record_pixels(labels)?;
+    }
+    apply_merges(labels, &merged);
+
+    Ok(regions.len())
+}
+
+/// Merge adjacent regions whose mean colors are closer than `threshold`,
+/// closest pair first, until no such pair remains. The return value is the
+/// count of regions after merging.
+///
+/// `threshold` is compared to the squared `Lab` distance of the mean colors,
+/// so `25.0` merges regions whose means are within a distance of `5.0`. The
+/// mean color of a merged region is updated before the next merge, so chains
+/// of gradually changing regions stop merging once the merged mean drifts too
+/// far from its neighbors.
+///
+/// Afterwards the labels are numbered contiguously from `0` in the order they
+/// first appear in `labels`.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::image::merge_by_color_threshold;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image: [Lab<D65, f64>; 3] = [
+///     Lab::new(50.0, 0.0, 0.0),
+///     Lab::new(51.0, 0.0, 0.0),
+///     Lab::new(90.0, 0.0, 0.0),
+/// ];
+/// let mut labels = [3, 7, 9];
+/// let count = merge_by_color_threshold(3, 1, &mut labels, &image, 4.0)?;
+/// assert_eq!(count, 2);
+/// assert_eq!(labels, [0, 0, 1]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn merge_by_color_threshold<Wp>(
+    width: u32,
+    height: u32,
+    labels: &mut [usize],
+    image: &[Lab<Wp, f64>],
+    threshold: f64,
+) -> Result<usize, ScError> {
+    merge_by_color_threshold_impl(width, height, labels, image, threshold, None)
+}
+
+/// Merge adjacent regions whose mean colors are closer than `threshold` like
+/// [`merge_by_color_threshold`], recording each merge and the renumbering of
+/// the labels in `log` so they can be reverted with [`undo`]. The return value
+/// is the count of regions after merging.
+///
+/// `log` is cleared before merging, so it only holds the merges of this call.
+pub fn merge_by_color_threshold_with_log<Wp>(
+    width: u32,
+    height: u32,
+    labels: &mut [usize],
+    image: &[Lab<Wp, f64>],
+    threshold: f64,
+    log: &mut MergeLog,
+) -> Result<usize, ScError> {
+    merge_by_color_threshold_impl(width, height, labels, image, threshold, Some(log))
+}
+
+/// [`merge_by_color_threshold`] implementation, optionally recording the
+/// merges.
+fn merge_by_color_threshold_impl<Wp>(
+    width: u32,
+    height: u32,
+    labels: &mut [usize],
+    image: &[Lab<Wp, f64>],
+    threshold: f64,
+    mut log: Option<&mut MergeLog>,
+) -> Result<usize, ScError> {
+    if let Some(log) = log.as_deref_mut() {
+        log.clear();
+    }
+    if labels.len() != image.len() {
+        return Err(ScError::General("Label buffer does not match image length"));
+    }
+
+    let mut adjacency = region_adjacency(width, height, labels)?;
+
+    // Accumulated color and pixel count of each region
+    let mut regions = HashMap::<usize, (Lab<Wp, f64>, usize)>::default();
+    regions.try_reserve(adjacency.len())?;
+    for (&label, &color) in labels.iter().zip(image) {
+        let _ = regions
+            .entry(label)
+            .and_modify(|e| {
+                e.0 += color;
+                e.1 += 1;
+            })
+            .or_insert((color, 1));
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let distance = |regions: &HashMap<usize, (Lab<Wp, f64>, usize)>, a: usize, b: usize| {
+        let (a_color, a_count) = regions.get(&a)?;
+        let (b_color, b_count) = regions.get(&b)?;
+        Some(distance_lab(
+            *a_color / *a_count as f64,
+            *b_color / *b_count as f64,
+        ))
+    };
+
+    // Candidate pairs, closest first. Entries become stale when one of their
+    // regions changes, which is detected by recomputing the distance.
+    let mut candidates = std::collections::BinaryHeap::new();
+    for (&a, neighbors) in &adjacency {
+        for &b in neighbors.iter().filter(|&&b| a < b) {
+            if let Some(d) = distance(&regions, a, b).filter(|&d| d < threshold) {
+                candidates.push(MergeCandidate { distance: d, a, b });
+            }
+        }
+    }
+
+    // Labels which have been merged away, mapped to the label they merged into
+    let mut merged = HashMap::<usize, usize>::default();
+
+    while let Some(MergeCandidate { distance: d, a, b }) = candidates.pop() {
+        if distance(&regions, a, b).map(f64::to_bits) != Some(d.to_bits()) {
+            continue;
+        }
+
+        merge_region(b, a, &mut regions, &mut adjacency, &mut merged);
+        if let Some(log) = log.as_deref_mut() {
+            log.push(b, a)?;
+        }
+        for &n in adjacency.get(&a).into_iter().flatten() {
+            if let Some(d) = distance(&regions, a, n).filter(|&d| d < threshold) {
+                candidates.push(MergeCandidate {
+                    distance: d,
+                    a: a.min(n),
+                    b: a.max(n),
+                });
+            }
+        }
+    }
+
+    if let Some(log) = log.as_deref_mut() {
+        log.record_pixels(labels)?;
+    }
+    apply_merges(labels, &merged);
+
+    // Number the labels contiguously in order of appearance
+    let mut renumbered = HashMap::<usize, usize>::default();
+    renumbered.try_reserve(regions.len())?;
+    for label in labels.iter_mut() {
+        let next = renumbered.len();
+        *label = *renumbered.entry(*label).or_insert(next);
+    }
+    if let Some(log) = log {
+        log.renumbered.try_reserve_exact(renumbered.len())?;
+        log.renumbered.extend(renumbered);
+    }
+
+    Ok(regions.len())
+}
+
+/// Pair of adjacent regions ordered so the closest pair is popped first from a
+/// max-heap.
+#[derive(Debug, Clone, Copy)]
+struct MergeCandidate {
+    distance: f64,
+    a: usize,
+    b: usize,
+}
+
+impl PartialEq for MergeCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for MergeCandidate {}
+
+impl PartialOrd for MergeCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeCandidate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .distance
+            .total_cmp(&self.distance)
+            .then_with(|| (other.a, other.b).cmp(&(self.a, self.b)))
+    }
+}
+
+/// Move the pixels and borders of region `label` into region `target`,
+/// recording the merge in `merged`.
+fn merge_region<Wp>(
+    label: usize,
+    target: usize,
+    regions: &mut HashMap<usize, (Lab<Wp, f64>, usize)>,
+    adjacency: &mut HashMap<usize, HashSet<usize>>,
+    merged: &mut HashMap<usize, usize>,
+) {
+    let neighbors = adjacency.remove(&label).unwrap_or_default();
+    if let Some((color, count)) = regions.remove(&label) {
+        if let Some(region) = regions.get_mut(&target) {
+            region.0 += color;
+            region.1 += count;
+        }
+    }
+    for &n in &neighbors {
+        if let Some(n_neighbors) = adjacency.get_mut(&n) {
+            let _ = n_neighbors.remove(&label);
+            if n != target {
+                let _ = n_neighbors.insert(target);
+            }
+        }
+    }
+    if let Some(target_neighbors) = adjacency.get_mut(&target) {
+        target_neighbors.extend(neighbors.into_iter().filter(|&n| n != target));
+    }
+    let _ = merged.insert(label, target);
+}
+
+/// Replace merged labels with the label of the region they ended up in.
+fn apply_merges(labels: &mut [usize], merged: &HashMap<usize, usize>) {
+    if !merged.is_empty() {
+        for label in labels.iter_mut() {
+            while let Some(&target) = merged.get(label) {
+                *label = target;
+            }
+        }
+    }
+}
+
+/// A merge of one region into another, recorded in a [`MergeLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeRecord {
+    /// Label of the region which was merged away.
+    pub merged_label: usize,
+    /// Label of the region it was merged into, before the labels were
+    /// renumbered.
+    pub into_label: usize,
+    /// Buffer indices of the pixels which had `merged_label` before merging,
+    /// in increasing order.
+    ///
+    /// Pixels of regions which were merged into `merged_label` earlier are
+    /// listed in their own records, so each pixel appears at most once in a
+    /// log.
+    pub pixels: Vec<usize>,
+}
+
+/// History of the merges performed by [`merge_small_regions_with_log`] and
+/// [`merge_by_color_threshold_with_log`], which can be reverted with [`undo`].
+///
+/// The log stores the indices of the pixels which changed label, which is at
+/// most one index per pixel, rather than a copy of the labels.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeLog {
+    /// Merges in the order they were performed.
+    records: Vec<MergeRecord>,
+    /// Labels before and after renumbering, as `(old, new)`.
+    renumbered: Vec<(usize, usize)>,
+}
+
+impl MergeLog {
+    /// Create an empty [`MergeLog`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The merges in the order they were performed.
+    #[must_use]
+    pub fn records(&self) -> &[MergeRecord] {
+        &self.records
+    }
+
+    /// The number of merges in the log.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns `true` if the log contains no merges.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Remove every merge from the log, keeping its allocations.
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.renumbered.clear();
+    }
+
+    /// Record a merge of `merged_label` into `into_label`, without pixels.
+    fn push(&mut self, merged_label: usize, into_label: usize) -> Result<(), ScError> {
+        self.records.try_reserve(1)?;
+        self.records.push(MergeRecord {
+            merged_label,
+            into_label,
+            pixels: Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    /// Fill in the pixels of each merged region from the labels before the
+    /// merges were applied.
+    fn record_pixels(&mut self, labels: &[usize]) -> Result<(), ScError> {
+        let mut records = HashMap::<usize, usize>::default();
+        records.try_reserve(self.records.len())?;
+        records.extend(
+            self.records
+                .iter()
+                .enumerate()
+                .map(|(i, record)| (record.merged_label, i)),
+        );
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        for (idx, label) in labels.iter().enumerate() {
+            if let Some(record) = records.get(label).and_then(|&i| self.records.get_mut(i)) {
+                if record.pixels.capacity() == record.pixels.len() {
+                    record.pixels.try_reserve(record.pixels.len().max(4))?;
+                }
+                record.pixels.push(idx);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Restore the labels from before the merges recorded in `log`.
+///
+/// `labels` must be the labels produced by the merging call which filled
+/// `log`. Surviving labels are mapped back to their numbers before
+/// renumbering, and the pixels of every merged region get their original label
+/// back.
+pub fn undo(labels: &mut [usize], log: &MergeLog) -> Result<(), ScError> {
+    if !log.renumbered.is_empty() {
+        let mut original = HashMap::<usize, usize>::default();
+        original.try_reserve(log.renumbered.len())?;
+        original.extend(log.renumbered.iter().map(|&(old, new)| (new, old)));
+        for label in labels.iter_mut() {
+            if let Some(&old) = original.get(label) {
+                *label = old;
+            }
+        }
+    }
+
+    for record in &log.records {
+        for &idx in &record.pixels {
+            *labels
+                .get_mut(idx)
+                .ok_or("Merge log does not match the labels")? = record.merged_label;
+        }
+    }
+
+    Ok(())
+}
+
+/// Smooth the boundaries of superpixel regions by relabeling each pixel to the
+/// label with the largest weight in its neighborhood, like a bilateral filter
+/// over labels. The return value is the count of pixels which changed label.
+///
+/// Every pixel within `radius` pixels horizontally and vertically votes for its
+/// label. Its vote is weighted by a Gaussian of its spatial distance with a
+/// standard deviation of `radius`, and by a Gaussian of its `Lab` color
+/// distance to the center pixel with a standard deviation of `sigma_color`.
+/// Jagged boundaries are straightened while pixels keep to the regions they
+/// resemble in color. Ties keep the pixel's current label.
+///
+/// All votes are read from the labels before smoothing, so the result doesn't
+/// depend on the order the pixels are visited in. Unlike connectivity
+/// enforcement, this doesn't guarantee connected regions, and a region whose
+/// pixels are all outvoted disappears, which can slightly change the count of
+/// regions. A `radius` of `0` leaves the labels unchanged, and the window of a
+/// larger `radius` than the image is limited to the image.
+///
+/// `labels` and `image` must both have a length of `width * height`, and
+/// `sigma_color` must be positive and finite.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::image::smooth_labels;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [Lab::<D65, f64>::new(50.0, 0.0, 0.0); 9];
+/// // A lone pixel of label `1` is absorbed by the surrounding region
+/// let mut labels = [
+///     0, 0, 0,
+///     0, 1, 0,
+///     0, 0, 0,
+/// ];
+/// assert_eq!(smooth_labels(3, 3, &mut labels, &image, 10.0, 1)?, 1);
+/// assert_eq!(labels, [0; 9]);
+///
+/// // The window of a huge radius covers the whole image
+/// labels[4] = 1;
+/// assert_eq!(smooth_labels(3, 3, &mut labels, &image, 10.0, u32::MAX)?, 1);
+/// assert_eq!(labels, [0; 9]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn smooth_labels<Wp>(
+    width: u32,
+    height: u32,
+    labels: &mut [usize],
+    image: &[Lab<Wp, f64>],
+    sigma_color: f64,
+    radius: u32,
+) -> Result<usize, ScError> {
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || image.len() != len {
+        return Err(ScError::General(
+            "Label or image buffer does not match image dimensions",
+        ));
+    }
+    if !sigma_color.is_finite() || sigma_color <= 0.0 {
+        return Err(ScError::General("Color sigma must be positive and finite"));
+    }
+    if radius == 0 || len == 0 {
+        return Ok(0);
+    }
+
+    // The window never reaches past the image, so the kernel only needs to
+    // cover the image dimensions. The Gaussian keeps the requested `radius`.
+    let space_denom = 2.0 * f64::from(radius).powi(2);
+    let radius = radius.min(width.max(height));
+
+    // The spatial Gaussian is separable, so one kernel serves both axes
+    let mut kernel = Vec::new();
+    kernel.try_reserve_exact(
+        usize::try_from(radius)
+            .ok()
+            .and_then(|r| r.checked_mul(2))
+            .and_then(|d| d.checked_add(1))
+            .ok_or(ScError::Overflow("smoothing kernel length"))?,
+    )?;
+    kernel.extend((0..=2 * u64::from(radius)).map(|i| {
+        #[allow(clippy::cast_precision_loss)]
+        let d = i as f64 - f64::from(radius);
+        (-d * d / space_denom).exp()
+    }));
+    // Weight of a neighbor at `n` for a window around `center` starting at
+    // `start`, where `center - start` is at most `radius`
+    let kernel_weight = |n: u32, center: u32, start: u32| {
+        let i = u64::from(n - start) + u64::from(radius - (center - start));
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| kernel.get(i))
+            .copied()
+            .unwrap_or_default()
+    };
+    let color_denom = 2.0 * sigma_color * sigma_color;
+
+    let mut original = Vec::new();
+    original.try_reserve_exact(len)?;
+    original.extend_from_slice(labels);
+
+    // Accumulated weight of each label in the window
+    let mut votes = Vec::<(usize, f64)>::new();
+    let mut changed = 0;
+    for y in 0..height {
+        let y_start = y.saturating_sub(radius);
+        let y_end = y.saturating_add(radius).min(height - 1);
+        for x in 0..width {
+            let x_start = x.saturating_sub(radius);
+            let x_end = x.saturating_add(radius).min(width - 1);
+            let idx = index(width, x, y)?;
+            let (Some(&center), Some(&current)) = (image.get(idx), original.get(idx)) else {
+                continue;
+            };
+
+            votes.clear();
+            for n_y in y_start..=y_end {
+                let w_y = kernel_weight(n_y, y, y_start);
+                let row_start = index(width, x_start, n_y)?;
+                let row = (x_start..=x_end).zip(row_start..);
+                for (n_x, n_idx) in row {
+                    let (Some(&color), Some(&label)) = (image.get(n_idx), original.get(n_idx))
+                    else {
+                        continue;
+                    };
+                    let w_x = kernel_weight(n_x, x, x_start);
+                    let weight = w_x * w_y * (-distance_lab(center, color) / color_denom).exp();
+                    match votes.iter_mut().find(|(l, _)| *l == label) {
+                        Some(vote) => vote.1 += weight,
+                        None => {
+                            votes.try_reserve(1)?;
+                            votes.push((label, weight));
+                        }
+                    }
+                }
+            }
+
+            let current_weight = votes
+                .iter()
+                .find(|(l, _)| *l == current)
+                .map_or(0.0, |v| v.1);
+            let best = votes
+                .iter()
+                .filter(|(_, w)| *w > current_weight)
+                .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+            if let (Some(&(label, _)), Some(out)) = (best, labels.get_mut(idx)) {
+                *out = label;
+                changed += 1;
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Assign `new_label` to every pixel selected by `mask`, such as a region
+/// painted by the user in an editor.
+///
+/// Afterwards, `new_label` and every label painted over are checked for
+/// disconnected pieces. Each keeps its label on the piece found first in
+/// row-major order, and the other pieces receive new labels above the largest
+/// label in the image, so the edited labels are single 4-connected regions.
+/// Labels the edit didn't touch are left unchanged, even if they were already
+/// disconnected.
+///
+/// `mask` must be the same length as `labels`.
+///
+/// ```
+/// use simple_clustering::image::relabel_region;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let mut labels = [
+///     0, 0, 0,
+///     0, 0, 0,
+///     1, 1, 1,
+/// ];
+/// // Paint the middle column, which cuts label `0` in two
+/// let mask = [
+///     false, true, false,
+///     false, true, false,
+///     false, false, false,
+/// ];
+/// relabel_region(&mut labels, 3, &mask, 1)?;
+/// assert_eq!(labels, [0, 1, 2, 0, 1, 2, 1, 1, 1]);
+///
+/// // Label `2` was already disconnected, but the edit doesn't touch it
+/// let mut labels = [
+///     2, 1, 2,
+///     0, 0, 0,
+///     0, 0, 0,
+/// ];
+/// let mask = [
+///     false, false, false,
+///     false, false, false,
+///     true, false, false,
+/// ];
+/// relabel_region(&mut labels, 3, &mask, 3)?;
+/// assert_eq!(labels, [2, 1, 2, 0, 0, 0, 3, 0, 0]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn relabel_region(
+    labels: &mut [usize],
+    width: u32,
+    mask: &[bool],
+    new_label: usize,
+) -> Result<(), ScError> {
+    if mask.len() != labels.len() {
+        return Err(ScError::General("Mask does not match label buffer length"));
+    }
+
+    let mut edited = HashSet::<usize>::default();
+    let _ = edited.insert(new_label);
+    for (label, _) in labels
+        .iter_mut()
+        .zip(mask.iter())
+        .filter(|(_, &selected)| selected)
+    {
+        let _ = edited.insert(*label);
+        *label = new_label;
+    }
+
+    split_disconnected_labels(labels, width, |label| edited.contains(&label))
+}
+
+/// Split the pixels of `label` selected by `mask` into a new label. The return
+/// value is the new label, which is one above the largest label in the image.
+///
+/// Pixels selected by `mask` which don't belong to `label` are left unchanged.
+/// Like [`relabel_region`], pieces of either part which end up disconnected
+/// receive their own labels, while other labels are left unchanged.
+///
+/// `mask` must be the same length as `labels`.
+pub fn split_label(
+    labels: &mut [usize],
+    width: u32,
+    label: usize,
+    mask: &[bool],
+) -> Result<usize, ScError> {
+    if mask.len() != labels.len() {
+        return Err(ScError::General("Mask does not match label buffer length"));
+    }
+
+    let new_label = labels
+        .iter()
+        .max()
+        .map_or(Some(0), |max| max.checked_add(1))
+        .ok_or(ScError::Overflow("split label"))?;
+
+    labels
+        .iter_mut()
+        .zip(mask.iter())
+        .filter(|(l, &selected)| selected && **l == label)
+        .for_each(|(l, _)| *l = new_label);
+
+    split_disconnected_labels(labels, width, |l| l == label || l == new_label)?;
+
+    Ok(new_label)
+}
+
+/// Subdivide the region of `target_label` into `into` smaller superpixels with
+/// fresh labels, leaving the rest of the image unchanged. The return value is
+/// the sorted list of labels now covering the pixels of the old region.
+///
+/// SLIC clustering is run on the pixels of the region only, with seeds spread
+/// evenly over the region and a compactness of `10`. The new labels count up
+/// from one above the largest label in the image. Stray fragments smaller than
+/// a quarter of a piece are merged into a neighboring piece, and other pieces
+/// which end up disconnected receive their own labels like with
+/// [`split_label`]. The labels may therefore skip values, and slightly more
+/// than `into` can be returned. A region with fewer than `into` pixels is split
+/// into single pixels.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::image::split_region;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = vec![Lab::<D65, f64>::new(50.0, 0.0, 0.0); 8 * 4];
+/// // Two regions, the left one is split in two
+/// let mut labels: Vec<_> = (0..32).map(|i| usize::from(i % 8 >= 4)).collect();
+/// let pieces = split_region(&mut labels, 8, 4, &image, 0, 2)?;
+/// assert_eq!(pieces, [2, 3]);
+/// assert!(labels.iter().all(|&l| l != 0));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `image` must have a length of `width * height`.
+/// `into` must not be `0`.
+pub fn split_region<Wp>(
+    labels: &mut [usize],
+    width: u32,
+    height: u32,
+    image: &[Lab<Wp, f64>],
+    target_label: usize,
+    into: usize,
+) -> Result<Vec<usize>, ScError> {
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || image.len() != len {
+        return Err(ScError::General(
+            "Label or image buffer does not match image dimensions",
+        ));
+    }
+    if into == 0 {
+        return Err(ScError::ZeroSuperpixelCount);
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+
+    // Pixel index, color, and position of each pixel of the region
+    let region_len = labels.iter().filter(|&&l| l == target_label).count();
+    if region_len == 0 {
+        return Err(ScError::General("Target label is not in the image"));
+    }
+    let mut pixels = Vec::new();
+    pixels.try_reserve_exact(region_len)?;
+    for (idx, (_, &color)) in labels
+        .iter()
+        .zip(image)
+        .enumerate()
+        .filter(|(_, (&l, _))| l == target_label)
+    {
+        let (x, y) = index_to_xy(idx, width_usize)?;
+        pixels.push((idx, color, (f64::from(x), f64::from(y))));
+    }
+    let into = into.min(region_len);
+
+    // Spread the seeds by repeatedly picking the pixel farthest from the
+    // existing seeds, starting with the pixel nearest to the region centroid
+    #[allow(clippy::cast_precision_loss)]
+    let centroid = {
+        let sum = pixels
+            .iter()
+            .fold((0.0, 0.0), |sum, p| (sum.0 + p.2 .0, sum.1 + p.2 .1));
+        (sum.0 / region_len as f64, sum.1 / region_len as f64)
+    };
+    let mut nearest_seed = Vec::new();
+    nearest_seed.try_reserve_exact(region_len)?;
+    nearest_seed.extend(pixels.iter().map(|p| distance_xy(p.2, centroid)));
+    let mut pick = nearest_seed
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(i, _)| i);
+    nearest_seed.fill(f64::INFINITY);
+
+    let mut centers = Vec::new();
+    centers.try_reserve_exact(into)?;
+    while centers.len() < into {
+        let (_, color, position) = *pixels.get(pick).ok_or("Seed index out of bounds")?;
+        centers.push((color, position));
+        for (d, p) in nearest_seed.iter_mut().zip(&pixels) {
+            *d = d.min(distance_xy(p.2, position));
+        }
+        pick = nearest_seed
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map_or(0, |(i, _)| i);
+    }
+
+    // Cluster the region with the grid interval of `into` superpixels
+    #[allow(clippy::cast_precision_loss)]
+    let m_s_term = m_div_s(10.0, (region_len as f64 / into as f64).sqrt());
+    let mut assignments = Vec::new();
+    assignments.try_reserve_exact(region_len)?;
+    assignments.extend((0..region_len).map(|_| 0_usize));
+    let mut sums = Vec::new();
+    sums.try_reserve_exact(into)?;
+    for _ in 0..10 {
+        for (assignment, &(_, color, position)) in assignments.iter_mut().zip(&pixels) {
+            let mut best = f64::INFINITY;
+            for (i, &(center_color, center_position)) in centers.iter().enumerate() {
+                let distance = distance_s(
+                    m_s_term,
+                    distance_lab(color, center_color),
+                    distance_xy(position, center_position),
+                );
+                if distance < best {
+                    best = distance;
+                    *assignment = i;
+                }
+            }
+        }
+
+        sums.clear();
+        sums.extend((0..into).map(|_| (Lab::<Wp, f64>::new(0.0, 0.0, 0.0), (0.0, 0.0), 0.0)));
+        for (&assignment, &(_, color, position)) in assignments.iter().zip(&pixels) {
+            if let Some(sum) = sums.get_mut(assignment) {
+                sum.0 += color;
+                sum.1 = (sum.1 .0 + position.0, sum.1 .1 + position.1);
+                sum.2 += 1.0;
+            }
+        }
+        for (center, &(color, position, count)) in centers.iter_mut().zip(&sums) {
+            if count > 0.0 {
+                *center = (color / count, (position.0 / count, position.1 / count));
+            }
+        }
+    }
+
+    let first_label = labels
+        .iter()
+        .max()
+        .and_then(|max| max.checked_add(1))
+        .ok_or(ScError::Overflow("split label"))?;
+    for (&assignment, &(idx, _, _)) in assignments.iter().zip(&pixels) {
+        *labels.get_mut(idx).ok_or("Label index out of bounds")? = first_label
+            .checked_add(assignment)
+            .ok_or(ScError::Overflow("split label"))?;
+    }
+    split_disconnected_labels(labels, width, |label| {
+        label >= first_label && label - first_label < into
+    })?;
+
+    // Merge stray fragments smaller than a quarter of a piece into the
+    // neighboring piece they share the longest border with, smallest first
+    let mut piece_pixels = HashMap::<usize, Vec<usize>>::default();
+    for &(idx, _, _) in &pixels {
+        let label = *labels.get(idx).ok_or("Label index out of bounds")?;
+        piece_pixels.entry(label).or_default().push(idx);
+    }
+    let mut pieces: Vec<_> = piece_pixels.keys().copied().collect();
+    pieces.sort_unstable_by_key(|label| (piece_pixels.get(label).map_or(0, Vec::len), *label));
+
+    let threshold = region_len / into / 4;
+    let (width_i, height_i) = (i64::from(width), i64::from(height));
+    let mut borders = HashMap::<usize, usize>::default();
+    for label in pieces {
+        let Some(fragment) = piece_pixels.get(&label).filter(|p| p.len() <= threshold) else {
+            continue;
+        };
+
+        borders.clear();
+        for &idx in fragment {
+            let (x, y) = index_to_xy(idx, width_usize)?;
+            let (x, y) = (i64::from(x), i64::from(y));
+            for &(d_x, d_y) in Connectivity::Four.offsets() {
+                if let Some(&n) = get_in_bounds(width_i, height_i, x + d_x, y + d_y, labels) {
+                    if n != label && piece_pixels.contains_key(&n) {
+                        *borders.entry(n).or_default() += 1;
+                    }
+                }
+            }
+        }
+        let Some(target) = borders
+            .iter()
+            .max_by_key(|&(&n, &count)| (count, std::cmp::Reverse(n)))
+            .map(|(&n, _)| n)
+        else {
+            continue;
+        };
+
+        let fragment = piece_pixels.remove(&label).unwrap_or_default();
+        for &idx in &fragment {
+            *labels.get_mut(idx).ok_or("Label index out of bounds")? = target;
+        }
+        piece_pixels.entry(target).or_default().extend(fragment);
+    }
+
+    let mut pieces: Vec<_> = piece_pixels.into_keys().collect();
+    pieces.sort_unstable();
+
+    Ok(pieces)
+}
+
+/// Give every 4-connected piece after the first of each label selected by
+/// `edited` its own label, counting up from one above the largest label.
+fn split_disconnected_labels(
+    labels: &mut [usize],
+    width: u32,
+    edited: impl Fn(usize) -> bool,
+) -> Result<(), ScError> {
+    let height = labels
+        .len()
+        .checked_div(usize::try_from(width).or(Err("Could not convert width to usize"))?)
+        .unwrap_or_default();
+    let height = u32::try_from(height).or(Err(ScError::General("Image height is too large")))?;
+    let (components, component_labels) = connected_components(labels, width, height)?;
+
+    let mut next_label = labels.iter().max().copied().unwrap_or_default();
+    let mut seen = HashSet::<usize>::default();
+    let mut new_labels = Vec::new();
+    new_labels.try_reserve_exact(component_labels.len())?;
+    for &label in &component_labels {
+        if !edited(label) || seen.insert(label) {
+            new_labels.push(label);
+        } else {
+            next_label = next_label
+                .checked_add(1)
+                .ok_or(ScError::Overflow("split label"))?;
+            new_labels.push(next_label);
+        }
+    }
+
+    for (label, component) in labels.iter_mut().zip(components.iter()) {
+        *label = *new_labels
+            .get(*component)
+            .ok_or("Component index out of bounds")?;
+    }
+
+    Ok(())
+}
+
+/// Count the labels which are split into more than one 4-connected region.
+///
+/// Both algorithms enforce connectivity, so this should be `0` for their
+/// output. It can be used to check labels before relying on each label being a
+/// single region.
+///
+/// ```
+/// use simple_clustering::image::disconnected_region_count;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let labels = [
+///     0, 1, 0,
+///     0, 1, 0,
+/// ];
+/// assert_eq!(disconnected_region_count(&labels, 3, 2)?, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn disconnected_region_count(
+    labels: &[usize],
+    width: u32,
+    height: u32,
+) -> Result<usize, ScError> {
+    let (_, component_labels) = connected_components(labels, width, height)?;
+
+    let mut components_per_label = HashMap::<usize, usize>::default();
+    for &label in &component_labels {
+        *components_per_label.entry(label).or_default() += 1;
+    }
+
+    Ok(components_per_label
+        .values()
+        .filter(|&&count| count > 1)
+        .count())
+}
+
+/// Check whether any label is split into more than one 4-connected region.
+///
+/// See [`disconnected_region_count`].
+pub fn has_disconnected_regions(
+    labels: &[usize],
+    width: u32,
+    height: u32,
+) -> Result<bool, ScError> {
+    Ok(disconnected_region_count(labels, width, height)? > 0)
+}
+
+/// Mark the pixels whose partition membership differs between two label
+/// slices, ignoring how the labels are numbered.
+///
+/// Each label of `lhs` is paired with at most one label of `rhs`, taking the
+/// pairs with the most pixels in common first. A pixel is marked `true` unless
+/// its labels form one of these pairs, so identical partitions have no marked
+/// pixels even if their labels are numbered differently. If a region is split
+/// in two, the smaller part is marked.
+///
+/// The number of changed pixels is the count of `true` values.
+///
+/// ```
+/// use simple_clustering::image::label_diff;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let lhs = [0, 0, 0, 1, 1, 1];
+/// let rhs = [7, 7, 5, 5, 5, 5];
+/// let diff = label_diff(&lhs, &rhs)?;
+/// assert_eq!(diff, [false, false, true, false, false, false]);
+///
+/// // Renumbering the labels doesn't change the partition
+/// assert!(label_diff(&lhs, &[4, 4, 4, 2, 2, 2])?.iter().all(|&d| !d));
+/// # Ok(())
+/// # }
+/// ```
+pub fn label_diff(lhs: &[usize], rhs: &[usize]) -> Result<Vec<bool>, ScError> {
+    if lhs.len() != rhs.len() {
+        return Err(ScError::General("Label buffers differ in length"));
+    }
+
+    let mut overlaps = HashMap::<(usize, usize), usize>::default();
+    for pair in lhs.iter().copied().zip(rhs.iter().copied()) {
+        *overlaps.entry(pair).or_default() += 1;
+    }
+
+    // Pair the labels greedily by overlap, breaking ties by label so that the
+    // result doesn't depend on the hash map order
+    let mut pairs = Vec::new();
+    pairs.try_reserve_exact(overlaps.len())?;
+    pairs.extend(overlaps);
+    pairs.sort_unstable_by_key(|&(pair, count)| (std::cmp::Reverse(count), pair));
+
+    let mut paired_lhs = HashMap::<usize, usize>::default();
+    let mut paired_rhs = HashSet::<usize>::default();
+    for ((l, r), _) in pairs {
+        if !paired_lhs.contains_key(&l) && !paired_rhs.contains(&r) {
+            let _ = paired_lhs.insert(l, r);
+            let _ = paired_rhs.insert(r);
+        }
+    }
+
+    let mut diff = Vec::new();
+    diff.try_reserve_exact(lhs.len())?;
+    diff.extend(
+        lhs.iter()
+            .zip(rhs)
+            .map(|(l, &r)| paired_lhs.get(l) != Some(&r)),
+    );
+
+    Ok(diff)
+}
+
+/// Find the 4-connected components of the labels. The return value is the
+/// component index of every pixel and the label of every component, with the
+/// components numbered in row-major order of their first pixel.
+fn connected_components(
+    labels: &[usize],
+    width: u32,
+    height: u32,
+) -> Result<(Vec<usize>, Vec<usize>), ScError> {
+    if labels.len() != pixel_count(width, height)? {
+        return Err(ScError::General(
+            "Label buffer does not match image dimensions",
+        ));
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+    let width_i = i64::from(width);
+    let height_i = i64::from(height);
+
+    let mut components = Vec::new();
+    components.try_reserve_exact(labels.len())?;
+    components.extend((0..labels.len()).map(|_| usize::MAX));
+    let mut component_labels = Vec::new();
+    let mut queue = Vec::new();
+
+    for start in 0..labels.len() {
+        if components.get(start) != Some(&usize::MAX) {
+            continue;
+        }
+        let label = *labels.get(start).ok_or("Label index out of bounds")?;
+        let component = component_labels.len();
+        component_labels.try_reserve(1)?;
+        component_labels.push(label);
+
+        queue.clear();
+        queue.push(start);
+        *components
+            .get_mut(start)
+            .ok_or("Label index out of bounds")? = component;
+        while let Some(idx) = queue.pop() {
+            let (x, y) = index_to_xy(idx, width_usize)?;
+            for &(d_x, d_y) in Connectivity::Four.offsets() {
+                let n_x = i64::from(x) + d_x;
+                let n_y = i64::from(y) + d_y;
+                if get_in_bounds(width_i, height_i, n_x, n_y, labels) != Some(&label)
+                    || get_in_bounds(width_i, height_i, n_x, n_y, &components) != Some(&usize::MAX)
+                {
+                    continue;
+                }
+                // In bounds, so the index fits
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let n_idx = (n_y * width_i + n_x) as usize;
+                *components
+                    .get_mut(n_idx)
+                    .ok_or("Label index out of bounds")? = component;
+                queue.try_reserve(1)?;
+                queue.push(n_idx);
+            }
+        }
+    }
+
+    Ok((components, component_labels))
+}
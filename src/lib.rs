@@ -12,7 +12,10 @@
 //!
 //! Note that the convenience methods [`slic_from_bytes`] and
 //! [`snic_from_bytes`] also exist to allow for calculation of superpixel labels
-//! without having to convert to `Lab`.
+//! without having to convert to `Lab`. For 16-bit images, [`slic_from_u16`] and
+//! [`snic_from_u16`] accept `&[u16]` component buffers. RGBA buffers with
+//! straight or premultiplied alpha can be used with [`slic_from_rgba_bytes`]
+//! and [`snic_from_rgba_bytes`].
 //!
 //! ### SNIC
 //!
@@ -86,9 +89,10 @@
     missing_docs,
     non_ascii_idents,
     noop_method_call,
-    unsafe_code,
     unused_results
 )]
+#![cfg_attr(not(feature = "capi"), forbid(unsafe_code))]
+#![cfg_attr(feature = "capi", deny(unsafe_code))]
 #![warn(
     clippy::cast_lossless,
     clippy::cast_possible_truncation,
@@ -97,27 +101,365 @@
     clippy::cast_sign_loss
 )]
 
-use num_traits::{Float, One, Unsigned, Zero};
+use num_traits::{Float, Unsigned};
 use palette::Lab;
-use std::ops::{Add, Div, Rem};
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "rayon")]
+mod batch;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod edges;
 pub mod error;
 pub mod image;
+pub mod options;
 pub mod seed;
 mod slic;
 mod snic;
+#[cfg(feature = "testing")]
+pub mod testing;
 
-pub use slic::{slic, slic_from_bytes};
-pub use snic::{snic, snic_from_bytes};
+#[cfg(feature = "rayon")]
+pub use batch::{segment_batch, BatchInput};
+pub use slic::{
+    auto_m, slic, slic_cow, slic_features, slic_fixed_centers, slic_from_bytes,
+    slic_from_bytes_in_space, slic_from_bytes_with_buffer, slic_from_centers, slic_from_rgba_bytes,
+    slic_from_u16, slic_packed, slic_roi, slic_with_distances, slic_with_interval,
+    slic_with_options, slic_with_timings, slic_with_weights,
+};
+pub use snic::{
+    snic, snic_cow, snic_features, snic_from_bytes, snic_from_bytes_in_space,
+    snic_from_bytes_with_buffer, snic_from_rgba_bytes, snic_from_u16, snic_packed, snic_roi,
+    snic_with_counts, snic_with_interval, snic_with_options, snic_with_order, snic_with_timings,
+};
+
+use crate::error::ScError;
+use crate::options::AlphaMode;
+use num_traits::ToPrimitive;
+use palette::{
+    cast::ArrayCast,
+    encoding,
+    rgb::Rgb,
+    stimulus::FromStimulus,
+    white_point::{WhitePoint, D65},
+    FromColor, Hsv, IntoColor, Srgb,
+};
+
+/// Rounding mode used to convert the grid interval, `S`, to an integer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// Round towards zero. This is the mode used by SLIC and SNIC.
+    #[default]
+    Floor,
+    /// Round to the nearest integer, with halfway cases rounded away from zero.
+    Round,
+    /// Round towards positive infinity.
+    Ceil,
+}
 
 /// Calculate the superpixel side length, `S`.
 ///
 /// `S * S` is the approximate size of each superpixel in pixels. The formula is
 /// `S = (N / K).sqrt()`, where `N` is the number of pixels and `K` is the
-/// number of desired superpixels.
+/// number of desired superpixels. SLIC and SNIC use [`Rounding::Floor`].
+///
+/// Seeds are placed on a grid of roughly `(width / S) * (height / S)` points,
+/// capped at `superpixels`. Because `S` is rounded, the realized superpixel
+/// count can differ from `superpixels`, especially for small images or large
+/// `superpixels`.
+///
+/// The result may be `0` if `superpixels` is larger than the number of pixels.
+pub fn calculate_grid_interval(
+    width: u32,
+    height: u32,
+    superpixels: u32,
+    rounding: Rounding,
+) -> Result<u32, ScError> {
+    if superpixels == 0 {
+        return Err(ScError::ZeroSuperpixelCount);
+    }
+
+    let s = ((f64::from(width) * f64::from(height)) / f64::from(superpixels)).sqrt();
+    match rounding {
+        Rounding::Floor => s.floor(),
+        Rounding::Round => s.round(),
+        Rounding::Ceil => s.ceil(),
+    }
+    .to_u32()
+    .ok_or(ScError::InvalidGridInterval)
+}
+
+/// Validate the superpixel count and image dimensions, then calculate the
+/// superpixel side length, `S`.
+fn validate_grid_interval(k: u32, width: u32, height: u32) -> Result<u32, ScError> {
+    if k == 0 {
+        return Err(ScError::ZeroSuperpixelCount);
+    }
+
+    if width == 0 || height == 0 {
+        return Err(ScError::InvalidImageDimension);
+    }
+
+    match u64::from(k).cmp(&(u64::from(width) * u64::from(height))) {
+        std::cmp::Ordering::Less => {}
+        std::cmp::Ordering::Equal | std::cmp::Ordering::Greater => {
+            return Err(ScError::InvalidSuperpixelCount);
+        }
+    }
+
+    // Calculate S
+    let s = calculate_grid_interval(width, height, k, Rounding::Floor)?;
+
+    if s == 0 {
+        return Err(ScError::ZeroGridInterval);
+    }
+
+    Ok(s)
+}
+
+/// Size of the superpixels, either as a target count `k` or a side length `S`.
+#[derive(Debug, Clone, Copy)]
+enum GridSize {
+    /// Derive `S` from the desired number of superpixels.
+    Superpixels(u32),
+    /// Use the supplied `S` and place as many seeds as fit on its grid.
+    Interval(u32),
+}
+
+impl GridSize {
+    /// Validate the size and image dimensions, then calculate the superpixel
+    /// side length `S` and the superpixel count `k` as `(s, k)`.
+    fn resolve(self, width: u32, height: u32) -> Result<(u32, u32), ScError> {
+        match self {
+            Self::Superpixels(k) => Ok((validate_grid_interval(k, width, height)?, k)),
+            Self::Interval(s) => {
+                if width == 0 || height == 0 {
+                    return Err(ScError::InvalidImageDimension);
+                }
+                if s == 0 {
+                    return Err(ScError::ZeroGridInterval);
+                }
+                if u64::from(s) * u64::from(s) > u64::from(width) * u64::from(height) {
+                    return Err(ScError::InvalidGridInterval);
+                }
+                let (x_seeds, y_seeds) = seed::grid_shape(width, height, s, u32::MAX);
+
+                Ok((s, x_seeds.saturating_mul(y_seeds)))
+            }
+        }
+    }
+}
+
+/// Calculate the number of pixels in an image, `width * height`, as a `usize`.
+#[inline]
+fn pixel_count(width: u32, height: u32) -> Result<usize, ScError> {
+    u64::from(width)
+        .checked_mul(u64::from(height))
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or(ScError::Overflow("image pixel count"))
+}
+
+/// Check that a buffer of `len` color components with `channels` components
+/// per pixel holds exactly the pixels of a `width` x `height` image.
+///
+/// Returns [`ScError::InvalidByteBufferLength`] if the buffer doesn't hold a
+/// whole number of pixels, and `mismatch` if it holds the wrong number of them.
+fn check_component_buffer(
+    len: usize,
+    channels: usize,
+    width: u32,
+    height: u32,
+    mismatch: ScError,
+) -> Result<(), ScError> {
+    if !len.is_multiple_of(channels) {
+        return Err(ScError::InvalidByteBufferLength { len, channels });
+    }
+    if len / channels != pixel_count(width, height)? {
+        return Err(mismatch);
+    }
+
+    Ok(())
+}
+
+/// Calculate the buffer index of the pixel at `(x, y)` in an image `width`
+/// pixels wide, `y * width + x`.
+///
+/// Returns [`ScError::IndexOverflow`] instead of wrapping or saturating if the
+/// index doesn't fit in `usize`.
+#[inline]
+fn index(width: u32, x: u32, y: u32) -> Result<usize, ScError> {
+    usize::try_from(y)
+        .ok()
+        .zip(usize::try_from(width).ok())
+        .and_then(|(y, width)| y.checked_mul(width))
+        .zip(usize::try_from(x).ok())
+        .and_then(|(row, x)| row.checked_add(x))
+        .ok_or(ScError::IndexOverflow { x, y })
+}
+
+/// Convert a buffer index into the `(x, y)` coordinates of its pixel in an
+/// image `width` pixels wide, the inverse of [`index`].
+///
+/// Returns [`ScError::InvalidImageDimension`] if `width` is `0`, and
+/// [`ScError::IndexOverflow`] with the coordinates saturated to the `u32` range
+/// if either of them doesn't fit in `u32`.
+#[inline]
+fn index_to_xy(idx: usize, width: usize) -> Result<(u32, u32), ScError> {
+    let (Some(x), Some(y)) = (idx.checked_rem(width), idx.checked_div(width)) else {
+        return Err(ScError::InvalidImageDimension);
+    };
+    match (u32::try_from(x), u32::try_from(y)) {
+        (Ok(x), Ok(y)) => Ok((x, y)),
+        _ => {
+            let saturate = |v: usize| u32::try_from(v).unwrap_or(u32::MAX);
+            Err(ScError::IndexOverflow {
+                x: saturate(x),
+                y: saturate(y),
+            })
+        }
+    }
+}
+
+/// Convert signed pixel coordinates, such as a pixel plus a neighbor offset,
+/// into `u32` coordinates.
+///
+/// Returns [`ScError::IndexOverflow`] with the coordinates saturated to the
+/// `u32` range if either of them is outside of it.
 #[inline]
-fn calculate_grid_interval(width: u32, height: u32, superpixels: u32) -> f64 {
-    ((f64::from(width) * f64::from(height)) / f64::from(superpixels)).sqrt()
+fn checked_coords(x: i64, y: i64) -> Result<(u32, u32), ScError> {
+    match (u32::try_from(x), u32::try_from(y)) {
+        (Ok(x), Ok(y)) => Ok((x, y)),
+        _ => {
+            let saturate = |v: i64| u32::try_from(v.max(0)).unwrap_or(u32::MAX);
+            Err(ScError::IndexOverflow {
+                x: saturate(x),
+                y: saturate(y),
+            })
+        }
+    }
+}
+
+/// Convert a buffer of RGB components into a newly allocated `Lab` buffer.
+///
+/// The component type is normalized to the `0.0..=1.0` range of `f64` before
+/// conversion.
+fn srgb_components_to_lab<T>(image: &[T]) -> Result<Vec<Lab<D65, f64>>, ScError>
+where
+    T: Copy,
+    Srgb<T>: ArrayCast<Array = [T; 3]>,
+    f64: FromStimulus<T>,
+{
+    let mut input_lab = Vec::new();
+    srgb_components_into_lab(image, &mut input_lab)?;
+
+    Ok(input_lab)
+}
+
+/// Convert a buffer of RGB components into `Lab`, replacing the contents of
+/// `output`. `output` only reallocates if its capacity is too small.
+fn srgb_components_into_lab<T>(image: &[T], output: &mut Vec<Lab<D65, f64>>) -> Result<(), ScError>
+where
+    T: Copy,
+    Srgb<T>: ArrayCast<Array = [T; 3]>,
+    f64: FromStimulus<T>,
+{
+    let input_buffer = palette::cast::from_component_slice::<Srgb<T>>(image);
+    output.clear();
+    output.try_reserve_exact(input_buffer.len())?;
+    output.extend(
+        input_buffer
+            .iter()
+            .map(|&c| Lab::from_color(c.into_format::<f64>())),
+    );
+
+    Ok(())
+}
+
+/// Convert a buffer of RGBA component bytes into a newly allocated `Lab`
+/// buffer, dropping the alpha component.
+///
+/// Premultiplied colors are divided by alpha first. Fully transparent
+/// premultiplied pixels have no recoverable color, so they're converted as
+/// black. Straight colors are converted as-is whatever their alpha.
+fn rgba_bytes_to_lab(image: &[u8], alpha_mode: AlphaMode) -> Result<Vec<Lab<D65, f64>>, ScError> {
+    let input_buffer = palette::cast::from_component_slice::<palette::Srgba<u8>>(image);
+    let mut input_lab = Vec::new();
+    input_lab.try_reserve_exact(input_buffer.len())?;
+    input_lab.extend(input_buffer.iter().map(|&c| {
+        let c = c.into_format::<f64, f64>();
+        let color = match alpha_mode {
+            AlphaMode::Straight => c.color,
+            AlphaMode::Premultiplied if c.alpha == 0.0 => Srgb::new(0.0, 0.0, 0.0),
+            AlphaMode::Premultiplied => Srgb::new(
+                (c.red / c.alpha).min(1.0),
+                (c.green / c.alpha).min(1.0),
+                (c.blue / c.alpha).min(1.0),
+            ),
+        };
+        Lab::from_color(color)
+    }));
+
+    Ok(input_lab)
+}
+
+/// A pixel feature of three `f64` components that can be segmented with
+/// [`slic_features`] and [`snic_features`].
+///
+/// This is implemented for `Lab<Wp, f64>` and for plain `[f64; 3]` arrays,
+/// which allows segmenting features in a color space computed outside of
+/// `palette` without converting them. The features are borrowed in place
+/// without copying.
+pub trait AsFeature3: Copy {
+    /// White point of the `Lab` colors that the features are viewed as.
+    type WhitePoint;
+
+    /// View a slice of features as `Lab` colors with the same components.
+    fn as_lab_slice(features: &[Self]) -> &[Lab<Self::WhitePoint, f64>];
+
+    /// Create a feature from its three components.
+    fn from_array(array: [f64; 3]) -> Self;
+
+    /// Calculate the distance between two features. This is the squared
+    /// Euclidean distance for both provided implementations.
+    fn distance(self, other: Self) -> f64;
+}
+
+impl<Wp> AsFeature3 for Lab<Wp, f64> {
+    type WhitePoint = Wp;
+
+    #[inline]
+    fn as_lab_slice(features: &[Self]) -> &[Lab<Wp, f64>] {
+        features
+    }
+
+    #[inline]
+    fn from_array(array: [f64; 3]) -> Self {
+        array.into()
+    }
+
+    #[inline]
+    fn distance(self, other: Self) -> f64 {
+        distance_lab(self, other)
+    }
+}
+
+impl AsFeature3 for [f64; 3] {
+    type WhitePoint = D65;
+
+    #[inline]
+    fn as_lab_slice(features: &[Self]) -> &[Lab<D65, f64>] {
+        palette::cast::from_array_slice(features)
+    }
+
+    #[inline]
+    fn from_array(array: [f64; 3]) -> Self {
+        array
+    }
+
+    #[inline]
+    fn distance(self, other: Self) -> f64 {
+        distance_lab(Lab::<D65, f64>::from(self), Lab::from(other))
+    }
 }
 
 /// Calculate the distance between two `Lab` colors.
@@ -129,6 +471,48 @@ where
     (rhs.l - lhs.l).powi(2) + (rhs.a - lhs.a).powi(2) + (rhs.b - lhs.b).powi(2)
 }
 
+/// Calculate the distance between two `Lab` colors in the chroma-hue plane of
+/// `LCh`, ignoring lightness.
+///
+/// The hue difference is the shortest angle between the hues, measured as an
+/// arc at the mean chroma of both colors so that it's on the same scale as the
+/// chroma difference.
+#[inline]
+fn distance_lch<Wp>(lhs: Lab<Wp, f64>, rhs: Lab<Wp, f64>) -> f64 {
+    let lhs_chroma = lhs.a.hypot(lhs.b);
+    let rhs_chroma = rhs.a.hypot(rhs.b);
+    let mut hue_diff = (lhs.b.atan2(lhs.a) - rhs.b.atan2(rhs.a)).abs();
+    if hue_diff > std::f64::consts::PI {
+        hue_diff = std::f64::consts::TAU - hue_diff;
+    }
+
+    (lhs_chroma - rhs_chroma).powi(2) + (0.5 * (lhs_chroma + rhs_chroma) * hue_diff).powi(2)
+}
+
+/// Calculate the distance between two `Lab` colors in the `Hsv` cone.
+///
+/// Hue and saturation form polar coordinates scaled by value, which handles
+/// hue wraparound. The coordinates are scaled by `100` to match the range of
+/// `Lab` lightness.
+#[inline]
+fn distance_hsv<Wp>(lhs: Lab<Wp, f64>, rhs: Lab<Wp, f64>) -> f64
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let cone = |color: Lab<Wp, f64>| {
+        let rgb: Rgb<encoding::Srgb, f64> = color.into_color();
+        let hsv = Hsv::from_color(rgb);
+        let (sin, cos) = hsv.hue.into_radians().sin_cos();
+        let radius = hsv.saturation * hsv.value;
+        (radius * cos, radius * sin, hsv.value)
+    };
+    let (lhs_x, lhs_y, lhs_z) = cone(lhs);
+    let (rhs_x, rhs_y, rhs_z) = cone(rhs);
+
+    1.0e4 * ((lhs_x - rhs_x).powi(2) + (lhs_y - rhs_y).powi(2) + (lhs_z - rhs_z).powi(2))
+}
+
 /// Calculate the distance between two two-dimensional points.
 #[inline]
 fn distance_xy<T>(lhs: (T, T), rhs: (T, T)) -> T
@@ -138,7 +522,48 @@ where
     (rhs.0 - lhs.0).powi(2) + (rhs.1 - lhs.1).powi(2)
 }
 
-/// Calculate the `s` distance.
+/// Wrap an x-coordinate around the image width if `wrap` is set.
+///
+/// `width` must not be `0` if `wrap` is set.
+#[inline]
+fn wrap_x(width: i64, x: i64, wrap: bool) -> i64 {
+    if wrap {
+        x.rem_euclid(width)
+    } else {
+        x
+    }
+}
+
+/// Shift `x` by the image width so that it lies within half of the width from
+/// `reference`, taking the shortest way around a wrapped image.
+#[inline]
+fn unwrap_x(x: f64, reference: f64, width: f64) -> f64 {
+    if x - reference > 0.5 * width {
+        x - width
+    } else if reference - x > 0.5 * width {
+        x + width
+    } else {
+        x
+    }
+}
+
+/// Wrap an x-coordinate into `0.0..width`.
+#[inline]
+fn wrap_coordinate(x: f64, width: f64) -> f64 {
+    let x = x.rem_euclid(width);
+    // `rem_euclid` can round up to `width` for tiny negative values
+    if x < width {
+        x
+    } else {
+        0.0
+    }
+}
+
+/// Calculate the `s` distance from the squared color distance `d_lab` and the
+/// squared spatial distance `d_xy`.
+///
+/// The result is squared, which is enough for comparing distances inside the
+/// algorithms. Use [`distance_s_sqrt`] for a magnitude on the scale of ΔE.
 #[inline]
 fn distance_s<T>(m_div_s: T, d_lab: T, d_xy: T) -> T
 where
@@ -147,6 +572,34 @@ where
     d_lab + m_div_s * d_xy
 }
 
+/// Calculate the true, non-squared superpixel distance,
+/// `sqrt(d_lab) + (m / s) * sqrt(d_xy)`.
+///
+/// `d_lab` is a squared color distance, such as the result of
+/// [`ColorDistance::distance`](options::ColorDistance::distance), and `d_xy` is
+/// the squared spatial distance in pixels. The color term is then a Euclidean
+/// ΔE in `Lab` and the spatial term is in units of `m / s` per pixel, which is
+/// easier to interpret for thresholds than the squared distances used inside
+/// the algorithms.
+///
+/// The combined squared distances returned by
+/// [`slic_with_distances`] can't be split back into their terms. Their square
+/// root is the length of the vector `(sqrt(d_lab), (m / s) * sqrt(d_xy))`,
+/// which is on the same scale but never larger than this sum.
+///
+/// ```
+/// use simple_clustering::distance_s_sqrt;
+///
+/// // A ΔE of 3 at 4 pixels from the center with m = 10 and s = 20
+/// let distance = distance_s_sqrt(10.0, 20.0, 9.0, 16.0);
+/// assert!((distance - 5.0).abs() < 1e-12);
+/// ```
+#[must_use]
+#[inline]
+pub fn distance_s_sqrt(m: f64, s: f64, d_lab: f64, d_xy: f64) -> f64 {
+    d_lab.sqrt() + (m / s) * d_xy.sqrt()
+}
+
 /// Calculate the superpixel scaling factor.
 ///
 /// `m_div_s` is `(m / s).powi(2)`.
@@ -155,23 +608,6 @@ fn m_div_s(m: f64, s: f64) -> f64 {
     (m / s).powi(2)
 }
 
-/// Calculates the quotient of `lhs` and `rhs`, rounding the result towards
-/// positive infinity.
-// FIXME: Remove when stable
-#[inline]
-fn div_ceil<T>(lhs: T, rhs: T) -> T
-where
-    T: PartialOrd + Copy + Div + Rem + Add + Unsigned + Zero + One,
-{
-    let d = lhs / rhs;
-    let r = lhs % rhs;
-    if r > T::zero() && rhs > T::zero() {
-        d + T::one()
-    } else {
-        d
-    }
-}
-
 /// Checks if the index is in bounds and returns a reference to the data at that
 /// point if it exists.
 #[inline]
@@ -188,6 +624,75 @@ fn get_in_bounds<T>(width: i64, _height: i64, x: i64, y: i64, image: &[T]) -> Op
     }
 }
 
+/// Check that the number of distinct `labels` is within `tolerance` of `k`.
+fn check_superpixel_count(k: u32, labels: &[usize], tolerance: Option<f64>) -> Result<(), ScError> {
+    let Some(tolerance) = tolerance else {
+        return Ok(());
+    };
+
+    let realized = image::count_colors(labels);
+    #[allow(clippy::cast_precision_loss)]
+    let deviation = (realized as f64 - f64::from(k)).abs();
+    if deviation > f64::from(k) * tolerance || tolerance.is_nan() {
+        return Err(ScError::SuperpixelCountMismatch {
+            requested: k,
+            realized,
+        });
+    }
+
+    Ok(())
+}
+
+/// Return an error if a count tolerance is set and `k` is more than
+/// [`seed::max_superpixel_count`], since the count found couldn't meet it.
+///
+/// The limit only applies to perturbed [`Seeding::Grid`](seed::Seeding::Grid)
+/// and [`Seeding::JitteredGrid`](seed::Seeding::JitteredGrid) seeds placed for
+/// a requested count, where perturbing moves neighboring seeds together.
+fn check_superpixel_limit(
+    size: GridSize,
+    width: u32,
+    height: u32,
+    options: &options::Options,
+) -> Result<(), ScError> {
+    let GridSize::Superpixels(k) = size else {
+        return Ok(());
+    };
+    let perturbed_grid = options.perturb
+        && matches!(
+            options.seeding,
+            seed::Seeding::Grid | seed::Seeding::JitteredGrid
+        );
+    if options.count_tolerance.is_none() || !perturbed_grid {
+        return Ok(());
+    }
+
+    let achievable = seed::max_superpixel_count(width, height)?;
+    if k > achievable {
+        return Err(ScError::UnachievableSuperpixelCount {
+            requested: k,
+            achievable,
+        });
+    }
+
+    Ok(())
+}
+
+/// Convert labels into a narrower integer type, returning an error if a label
+/// doesn't fit.
+fn pack_labels<T>(labels: &[usize]) -> Result<Vec<T>, ScError>
+where
+    T: TryFrom<usize> + Unsigned,
+{
+    let mut packed = Vec::new();
+    packed.try_reserve_exact(labels.len())?;
+    for &label in labels {
+        packed.push(T::try_from(label).or(Err(ScError::Overflow("packed label")))?);
+    }
+
+    Ok(packed)
+}
+
 /// Checks if the index is in bounds and returns a mutable referance to the data
 /// at that point if it exists.
 #[inline]
@@ -210,6 +715,220 @@ fn get_mut_in_bounds<T>(
     }
 }
 
+/// Blur a copy of `image` with a Gaussian kernel with a standard deviation of
+/// `sigma` pixels, or return `None` if `sigma` is `None` or `0`.
+///
+/// The kernel is separable, so the image is blurred with a horizontal and a
+/// vertical pass of a 1D kernel covering `3 * sigma` pixels on each side.
+/// Pixels beyond the edges repeat the nearest edge pixel.
+fn pre_smooth<Wp>(
+    image: ImageView<'_, Lab<Wp, f64>>,
+    sigma: Option<f64>,
+) -> Result<Option<Vec<Lab<Wp, f64>>>, ScError> {
+    let Some(sigma) = sigma else {
+        return Ok(None);
+    };
+    if !(sigma.is_finite() && sigma >= 0.0) {
+        return Err(ScError::General(
+            "Smoothing sigma must be non-negative and finite",
+        ));
+    }
+    if sigma == 0.0 {
+        return Ok(None);
+    }
+
+    let radius = (3.0 * sigma)
+        .ceil()
+        .to_usize()
+        .ok_or(ScError::Overflow("smoothing radius"))?;
+    let mut kernel = Vec::new();
+    kernel.try_reserve_exact(radius.saturating_mul(2).saturating_add(1))?;
+    #[allow(clippy::cast_precision_loss)]
+    kernel.extend((0..=2 * radius).map(|i| {
+        let offset = i as f64 - radius as f64;
+        (-offset * offset / (2.0 * sigma * sigma)).exp()
+    }));
+    let total: f64 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|weight| *weight /= total);
+
+    // Sum the kernel around `center` of a line of `len` pixels
+    let convolve = |center: usize, len: usize, pixel: &dyn Fn(usize) -> Lab<Wp, f64>| {
+        kernel
+            .iter()
+            .enumerate()
+            .fold(Lab::new(0.0, 0.0, 0.0), |acc, (i, &weight)| {
+                let idx = (center + i).saturating_sub(radius).min(len - 1);
+                acc + pixel(idx) * weight
+            })
+    };
+
+    let width = usize::try_from(image.width).or(Err("Could not convert width to usize"))?;
+    let mut horizontal = Vec::new();
+    horizontal.try_reserve_exact(image.len())?;
+    for row in image.rows() {
+        horizontal.extend((0..width).map(|x| convolve(x, width, &|idx| row[idx])));
+    }
+
+    let height = horizontal.len() / width.max(1);
+    let mut smoothed = Vec::new();
+    smoothed.try_reserve_exact(horizontal.len())?;
+    for y in 0..height {
+        smoothed
+            .extend((0..width).map(|x| convolve(y, height, &|idx| horizontal[idx * width + x])));
+    }
+
+    Ok(Some(smoothed))
+}
+
+/// Read-only view of a `width` pixels wide image inside of a buffer whose rows
+/// are `stride` elements apart, starting at `offset`.
+///
+/// Pixel coordinates are relative to the view. A view of a whole buffer covers
+/// all of its elements, like [`get_in_bounds`] does.
+#[derive(Debug, Clone, Copy)]
+struct ImageView<'a, T> {
+    data: &'a [T],
+    width: u32,
+    stride: usize,
+    offset: usize,
+    len: usize,
+}
+
+impl<'a, T> ImageView<'a, T> {
+    /// View a whole buffer with rows of `width` elements.
+    fn new(data: &'a [T], width: u32) -> Self {
+        Self {
+            data,
+            width,
+            stride: usize::try_from(width).unwrap_or_default(),
+            offset: 0,
+            len: data.len(),
+        }
+    }
+
+    /// View the `(x, y, width, height)` rectangle `roi` of a buffer with rows
+    /// of `stride` elements.
+    fn with_roi(data: &'a [T], stride: u32, roi: (u32, u32, u32, u32)) -> Result<Self, ScError> {
+        let (x, y, width, height) = roi;
+        if width == 0 || height == 0 || stride == 0 {
+            return Err(ScError::InvalidImageDimension);
+        }
+        if x.checked_add(width)
+            .filter(|&right| right <= stride)
+            .is_none()
+        {
+            return Err(ScError::General("Region of interest exceeds the stride"));
+        }
+
+        let stride = usize::try_from(stride).or(Err(ScError::Overflow("stride")))?;
+        let row = |y: u32| usize::try_from(y).ok()?.checked_mul(stride);
+        let offset = row(y)
+            .and_then(|start| start.checked_add(usize::try_from(x).ok()?))
+            .ok_or(ScError::Overflow("region of interest offset"))?;
+        // The end of the last row of the region must be inside of the buffer
+        let end = row(y.saturating_add(height - 1))
+            .and_then(|start| start.checked_add(usize::try_from(x).ok()?))
+            .and_then(|start| start.checked_add(usize::try_from(width).ok()?))
+            .ok_or(ScError::Overflow("region of interest end"))?;
+        if end > data.len() {
+            return Err(ScError::General("Region of interest exceeds the buffer"));
+        }
+
+        Ok(Self {
+            data,
+            width,
+            stride,
+            offset,
+            len: pixel_count(width, height)?,
+        })
+    }
+
+    /// Number of pixels in the view.
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Get the pixel at `(x, y)` if it's inside of the view.
+    #[inline]
+    fn get(&self, x: i64, y: i64) -> Option<&'a T> {
+        if !(0..i64::from(self.width)).contains(&x) {
+            return None;
+        }
+        let x = usize::try_from(x).ok()?;
+        let y = usize::try_from(y).ok()?;
+        let width = usize::try_from(self.width).ok()?;
+        if y.checked_mul(width)?.checked_add(x)? >= self.len {
+            return None;
+        }
+        self.data.get(
+            self.offset
+                .checked_add(y.checked_mul(self.stride)?.checked_add(x)?)?,
+        )
+    }
+
+    /// Iterate over the complete rows of the view.
+    fn rows(&self) -> impl Iterator<Item = &'a [T]> + '_ {
+        let width = usize::try_from(self.width).unwrap_or_default();
+        (0..self.len.checked_div(width).unwrap_or_default()).filter_map(move |y| {
+            let start = self.offset.checked_add(y.checked_mul(self.stride)?)?;
+            self.data.get(start..start.checked_add(width)?)
+        })
+    }
+}
+
+// Compile-time check that the public outputs can be sent and shared across
+// threads, such as when segmenting batches of images.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<error::ScError>();
+    assert_send_sync::<Superpixel<Lab<palette::white_point::D65, f64>>>();
+    assert_send_sync::<Vec<usize>>();
+    assert_send_sync::<options::Options>();
+    assert_send_sync::<image::LabelIndex>();
+};
+
+/// Time spent in each stage of a run of SLIC or SNIC, filled in by
+/// [`slic_with_timings`] and [`snic_with_timings`].
+///
+/// The stages don't include validating the arguments or checking
+/// [`Options::count_tolerance`](options::Options::count_tolerance), so their
+/// sum is slightly less than the total time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Blurring the image with [`Options::pre_smooth`](options::Options::pre_smooth).
+    pub smoothing: Duration,
+    /// Placing and perturbing the seeds, including assigning them to the
+    /// regions of [`Options::edges`](options::Options::edges).
+    pub seeding: Duration,
+    /// Assigning pixels to clusters, which is every iteration of SLIC or the
+    /// region growing of SNIC.
+    pub clustering: Duration,
+    /// Enforcing that each superpixel is a single connected region.
+    pub connectivity: Duration,
+}
+
+/// Stopwatch for filling in [`Timings`] which doesn't read the clock when
+/// it's disabled.
+#[derive(Debug)]
+struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    /// Start a stopwatch if `enabled` is set.
+    fn new(enabled: bool) -> Self {
+        Self(enabled.then(Instant::now))
+    }
+
+    /// Time since the stopwatch was started or last split, restarting it.
+    fn split(&mut self) -> Duration {
+        self.0.as_mut().map_or(Duration::ZERO, |start| {
+            let now = Instant::now();
+            let elapsed = now.duration_since(*start);
+            *start = now;
+            elapsed
+        })
+    }
+}
+
 /// Struct containing a superpixel's color, X-coordinate, and Y-coordinate in
 /// an image.
 #[derive(Debug, Clone, Copy)]
@@ -222,6 +941,34 @@ pub struct Superpixel<T> {
     pub y: u32,
 }
 
+impl<T> Superpixel<T> {
+    /// Create a superpixel with the color `data` at `(x, y)`.
+    ///
+    /// ```
+    /// use simple_clustering::Superpixel;
+    ///
+    /// let superpixel = Superpixel::new([0.5, 0.25, 0.0], 3, 7);
+    /// assert_eq!(superpixel.position(), (3, 7));
+    /// assert_eq!(superpixel.color(), &[0.5, 0.25, 0.0]);
+    /// ```
+    #[inline]
+    pub const fn new(data: T, x: u32, y: u32) -> Self {
+        Self { data, x, y }
+    }
+
+    /// Superpixel `(x, y)` coordinates.
+    #[inline]
+    pub const fn position(&self) -> (u32, u32) {
+        (self.x, self.y)
+    }
+
+    /// Superpixel color.
+    #[inline]
+    pub const fn color(&self) -> &T {
+        &self.data
+    }
+}
+
 impl<T: Default> Default for Superpixel<T> {
     #[inline]
     fn default() -> Self {
@@ -232,3 +979,65 @@ impl<T: Default> Default for Superpixel<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn index_at_u32_limits() {
+        let max = u64::from(u32::MAX);
+        let as_u64 = |idx: Result<usize, ScError>| idx.ok().and_then(|i| u64::try_from(i).ok());
+
+        assert_eq!(
+            as_u64(index(u32::MAX, u32::MAX, u32::MAX)),
+            Some(max * max + max)
+        );
+        assert_eq!(
+            as_u64(index(u32::MAX, u32::MAX - 1, u32::MAX)),
+            Some(max * max + max - 1)
+        );
+        assert_eq!(
+            as_u64(index(u32::MAX, u32::MAX, u32::MAX - 1)),
+            Some(max * (max - 1) + max)
+        );
+        assert_eq!(as_u64(index(u32::MAX, 0, 0)), Some(0));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn index_overflow() {
+        assert!(matches!(
+            index(u32::MAX, u32::MAX, u32::MAX),
+            Err(ScError::IndexOverflow {
+                x: u32::MAX,
+                y: u32::MAX
+            })
+        ));
+        // The last index that fits in a 32-bit `usize`
+        assert_eq!(index(u32::MAX, 0, 1).ok(), Some(usize::MAX));
+        assert!(matches!(
+            index(u32::MAX, 1, 1),
+            Err(ScError::IndexOverflow { x: 1, y: 1 })
+        ));
+        assert!(matches!(
+            index(u32::MAX, 0, 2),
+            Err(ScError::IndexOverflow { x: 0, y: 2 })
+        ));
+    }
+
+    #[test]
+    fn checked_coords_limits() {
+        let max = i64::from(u32::MAX);
+        assert_eq!(checked_coords(max, max).ok(), Some((u32::MAX, u32::MAX)));
+        assert!(matches!(
+            checked_coords(-1, 3),
+            Err(ScError::IndexOverflow { x: 0, y: 3 })
+        ));
+        assert!(matches!(
+            checked_coords(2, max + 1),
+            Err(ScError::IndexOverflow { x: 2, y: u32::MAX })
+        ));
+    }
+}
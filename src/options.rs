@@ -0,0 +1,542 @@
+//! Options for configuring the superpixel algorithms.
+use crate::seed::Seeding;
+use crate::{distance_hsv, distance_lab, distance_lch};
+
+use palette::convert::IntoColorUnclamped;
+use palette::{
+    encoding, rgb::Rgb, white_point::WhitePoint, white_point::D65, FromColor, IntoColor, Lab,
+    Oklab, Srgb,
+};
+
+/// Additional configuration for [`slic_with_options`](crate::slic_with_options)
+/// and [`snic_with_options`](crate::snic_with_options).
+///
+/// The default options reproduce the results of [`slic`](crate::slic) and
+/// [`snic`](crate::snic).
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Strategy used to place the initial superpixel seeds.
+    pub seeding: Seeding,
+    /// Measure used to compare pixel colors to superpixel colors.
+    pub color_distance: ColorDistance,
+    /// Treat the left and right edges of the image as connected, such as for
+    /// equirectangular panoramas.
+    ///
+    /// Neighbor lookups wrap around the image width and spatial distances take
+    /// the shortest way around the seam, so regions crossing the seam aren't
+    /// split. Defaults to `false`.
+    pub wrap_x: bool,
+    /// Move grid seeds to the lowest gradient position in their 3x3
+    /// neighborhood before clustering. Defaults to `true`.
+    ///
+    /// Disabling the perturbation skips a gradient search for every seed. The
+    /// search only reads a few pixels around each seed, so it's negligible
+    /// next to a single SLIC iteration over the whole image and disabling it
+    /// rarely saves noticeable time. Without it, seeds may start on an edge or
+    /// a noisy pixel, which can produce a few ragged or split superpixels near
+    /// strong edges. Has no effect on [`Seeding::Points`].
+    pub perturb: bool,
+    /// Move SLIC clusters which weren't assigned any pixels during an iteration
+    /// onto the worst fitting pixels, where the distance to their cluster is
+    /// largest. Defaults to `false`.
+    ///
+    /// The reseeded clusters can claim pixels in the next iteration, which
+    /// reduces the number of lost superpixels on images with large uniform
+    /// regions. This changes the output of SLIC and has no effect on SNIC.
+    ///
+    /// ```
+    /// use palette::{white_point::D65, Lab};
+    /// use simple_clustering::{image::count_colors, options::Options, seed::Seeding};
+    /// use simple_clustering::slic_with_options;
+    ///
+    /// # fn main() -> Result<(), simple_clustering::error::ScError> {
+    /// let image = vec![Lab::<D65, f64>::new(50.0, 10.0, -5.0); 20 * 20];
+    ///
+    /// // Seeds on the same pixel leave all but one of their clusters empty
+    /// let mut options = Options {
+    ///     seeding: Seeding::Points(vec![(10, 10); 4]),
+    ///     ..Options::default()
+    /// };
+    /// let labels = slic_with_options(4, 10, 20, 20, None, &options, &image)?;
+    /// assert_eq!(count_colors(&labels), 1);
+    ///
+    /// options.reseed_empty = true;
+    /// let labels = slic_with_options(4, 10, 20, 20, None, &options, &image)?;
+    /// assert_eq!(count_colors(&labels), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub reseed_empty: bool,
+    /// Maximum relative deviation of the number of superpixels found from the
+    /// requested `k`, such as `0.1` for 10%. Defaults to `None`, which accepts
+    /// any count.
+    ///
+    /// If the count after connectivity is enforced is outside of
+    /// `k * (1 - tolerance)..=k * (1 + tolerance)`, the algorithm returns
+    /// [`ScError::SuperpixelCountMismatch`](crate::error::ScError::SuperpixelCountMismatch)
+    /// instead of the labels. With perturbed [`Seeding::Grid`] or
+    /// [`Seeding::JitteredGrid`] seeds, setting a tolerance also rejects a `k`
+    /// above [`max_superpixel_count`](crate::seed::max_superpixel_count) up
+    /// front with
+    /// [`ScError::UnachievableSuperpixelCount`](crate::error::ScError::UnachievableSuperpixelCount),
+    /// since far fewer superpixels would be found.
+    pub count_tolerance: Option<f64>,
+    /// Pixel neighborhood used when enforcing that each superpixel is a single
+    /// connected region. Defaults to [`Connectivity::Four`].
+    ///
+    /// With [`Connectivity::Eight`], pixels touching only at a corner belong
+    /// to the same region, so fewer small fragments are merged into their
+    /// neighbors. SNIC also grows its regions through this neighborhood, which
+    /// queues about twice as many candidate pixels and takes roughly three
+    /// times as long.
+    ///
+    /// A region which is only connected along a diagonal is merged into its
+    /// neighbors with [`Connectivity::Four`] and kept with
+    /// [`Connectivity::Eight`].
+    ///
+    /// ```
+    /// use palette::{white_point::D65, Lab};
+    /// use simple_clustering::options::{Connectivity, Options};
+    /// use simple_clustering::{seed::Seeding, slic_with_options, snic_with_options};
+    ///
+    /// # fn main() -> Result<(), simple_clustering::error::ScError> {
+    /// // A red diagonal line on a white 8x8 image
+    /// let image: Vec<Lab<D65, f64>> = (0..64)
+    ///     .map(|i| match i % 8 == i / 8 {
+    ///         true => Lab::new(50.0, 80.0, 60.0),
+    ///         false => Lab::new(100.0, 0.0, 0.0),
+    ///     })
+    ///     .collect();
+    /// let diagonal_is_one_label = |labels: &[usize]| {
+    ///     (1..8).all(|i| labels[i * 8 + i] == labels[0])
+    /// };
+    ///
+    /// for (connectivity, kept) in [(Connectivity::Four, false), (Connectivity::Eight, true)] {
+    ///     let options = Options {
+    ///         seeding: Seeding::Points(vec![(3, 3), (1, 6), (6, 1)]),
+    ///         connectivity,
+    ///         ..Options::default()
+    ///     };
+    ///     let slic_labels = slic_with_options(3, 1, 8, 8, Some(10), &options, &image)?;
+    ///     assert_eq!(diagonal_is_one_label(&slic_labels), kept);
+    ///     let snic_labels = snic_with_options(3, 1, 8, 8, &options, &image)?;
+    ///     assert_eq!(diagonal_is_one_label(&snic_labels), kept);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub connectivity: Connectivity,
+    /// Seed for the pseudorandom number generator of randomized seeding
+    /// strategies such as [`Seeding::JitteredGrid`] and
+    /// [`Seeding::KMeansPlusPlus`]. Defaults to `0`.
+    ///
+    /// The other seeding strategies are deterministic, so their output is the
+    /// same for every `rng_seed`.
+    pub rng_seed: u64,
+    /// Side length of the square window searched around each SLIC cluster, in
+    /// multiples of the grid interval `S`. Defaults to `2.0` for the standard
+    /// `2S x 2S` window.
+    ///
+    /// Pixels can only join clusters whose window covers them. A larger window
+    /// lets superpixels grow beyond `2S` on unevenly detailed images instead
+    /// of pixels being stuck with a distant cluster, but the time per iteration
+    /// grows with the area of the window. Must be positive and finite. Has no
+    /// effect on SNIC.
+    pub search_window: f64,
+    /// Standard deviation in pixels of a Gaussian blur applied to a copy of the
+    /// image before seeding and clustering. Defaults to `None`, where the image
+    /// is used as-is like with `Some(0.0)`.
+    ///
+    /// A light blur, such as `1.0`, reduces the influence of noise and film
+    /// grain so superpixel boundaries are smoother. Larger values blur away
+    /// weaker edges. Must be non-negative and finite.
+    pub pre_smooth: Option<f64>,
+    /// Largest distance at which SNIC assigns a pixel to a neighboring
+    /// cluster. Defaults to `None`, where every pixel is labeled.
+    ///
+    /// Pixels farther than this from every cluster they border aren't queued,
+    /// so they keep the label `0` and the regions around them stop growing.
+    /// This is faster for coarse previews but leaves holes that need a fill
+    /// pass, such as assigning each `0` pixel the label of a neighbor. Must
+    /// not be `NaN`. Has no effect on SLIC.
+    pub max_distance: Option<f64>,
+    /// Edge map with one value per pixel in row-major order, where `true`
+    /// marks an edge that superpixels shouldn't grow across. Defaults to
+    /// `None`.
+    ///
+    /// The non-edge pixels are split into regions connected through
+    /// [`Options::connectivity`], and each superpixel only takes non-edge
+    /// pixels from the region of its seed. Edge pixels join a superpixel on
+    /// either side. SNIC doesn't grow across a line of edge pixels and SLIC
+    /// doesn't assign pixels across one, so boundaries follow the edges. A
+    /// diagonal line is only a barrier with [`Connectivity::Four`].
+    ///
+    /// A superpixel is added for each region that no seed lies in, so regions
+    /// enclosed by edges keep their own label and the count can exceed `k`.
+    /// Connectivity enforcement still applies afterwards. SLIC merges
+    /// fragments up to a quarter of a superpixel in size into a neighbor, which
+    /// includes small regions enclosed by edges, while SNIC only merges single
+    /// stray pixels.
+    pub edges: Option<Vec<bool>>,
+    /// Spacing of the pixels that SLIC assigns to clusters while iterating.
+    /// Defaults to `1`, which assigns every pixel.
+    ///
+    /// With a stride of `n`, only every `n`th pixel of every `n`th row is
+    /// assigned and used to update the cluster centers, which takes about
+    /// `1 / n²` of the time per iteration. After the last iteration, each
+    /// skipped pixel joins the closest of the clusters of the sampled pixels
+    /// around it, which visits every pixel once. This is an approximation for
+    /// previews of very large images: the centers are estimated from fewer
+    /// pixels, and details narrower than the stride can be lost because no
+    /// sampled pixel lies on them. Must not be `0`. Has no effect on SNIC.
+    pub stride: u32,
+    /// Fraction of the way that each SLIC center is pulled back toward its
+    /// seed position after every update, between `0.0` and `1.0`. Defaults to
+    /// `0.0`, which is standard SLIC.
+    ///
+    /// At `1.0` the centers never move and only their colors are updated, so
+    /// the superpixels stay aligned to the seed grid for mosaic effects. Values
+    /// in between keep the superpixels more regular than a high `m` while
+    /// still letting colors pull them toward edges. Has no effect on SNIC.
+    pub grid_lock: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            seeding: Seeding::default(),
+            color_distance: ColorDistance::default(),
+            wrap_x: false,
+            perturb: true,
+            reseed_empty: false,
+            count_tolerance: None,
+            connectivity: Connectivity::default(),
+            rng_seed: 0,
+            search_window: 2.0,
+            pre_smooth: None,
+            max_distance: None,
+            edges: None,
+            stride: 1,
+            grid_lock: 0.0,
+        }
+    }
+}
+
+impl Options {
+    /// Create an [`Options`] with the default settings.
+    #[must_use]
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Superpixel algorithm to run, for functions which dispatch on the algorithm
+/// such as [`segment_and_recolor_bytes`](crate::image::segment_and_recolor_bytes).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Simple Linear Iterative Clustering, run for the default `10` iterations.
+    Slic,
+    /// Simple Non-Iterative Clustering.
+    #[default]
+    Snic,
+}
+
+/// Pixel neighborhood which determines whether two pixels are adjacent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Pixels sharing an edge are adjacent.
+    #[default]
+    Four,
+    /// Pixels sharing an edge or a corner are adjacent.
+    Eight,
+}
+
+impl Connectivity {
+    /// Offsets to the adjacent pixels, going clockwise from West.
+    pub(crate) fn offsets(self) -> &'static [(i64, i64)] {
+        match self {
+            Self::Four => &[(-1, 0), (0, -1), (1, 0), (0, 1)],
+            Self::Eight => &[
+                (-1, 0),
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+            ],
+        }
+    }
+}
+
+/// How the color components of an RGBA buffer relate to its alpha component.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// The color components are independent of alpha.
+    #[default]
+    Straight,
+    /// The color components have been multiplied by alpha. They're divided by
+    /// alpha before conversion to `Lab`, otherwise translucent colors would be
+    /// skewed toward black.
+    Premultiplied,
+}
+
+/// Color space that sRGB inputs are converted to before segmentation.
+///
+/// The converted colors are stored in `Lab<D65, f64>` buffers either way, so
+/// they can be segmented by the same functions, see [`WorkingImage`]. OKLab
+/// components are scaled by `100` to match the range of CIELAB lightness, which
+/// keeps the effect of the compactness parameter `m` comparable between the
+/// spaces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorkingSpace {
+    /// CIELAB with a D65 white point, as used by [`slic`](crate::slic) and
+    /// [`snic`](crate::snic).
+    #[default]
+    CieLab,
+    /// OKLab, which is more perceptually uniform than CIELAB in hue and
+    /// chroma. Superpixels often have more even sizes across saturated colors.
+    OkLab,
+}
+
+impl WorkingSpace {
+    /// Scale of the OKLab components in the working buffer.
+    const OKLAB_SCALE: f64 = 100.0;
+
+    /// Convert an sRGB color into the working space.
+    pub(crate) fn convert_srgb(self, color: Srgb<f64>) -> Lab<D65, f64> {
+        match self {
+            Self::CieLab => Lab::from_color(color),
+            Self::OkLab => {
+                let Oklab { l, a, b } = Oklab::from_color(color);
+                Lab::new(l, a, b) * Self::OKLAB_SCALE
+            }
+        }
+    }
+
+    /// Convert a color in the working space to sRGB without clamping.
+    pub(crate) fn to_srgb(self, color: Lab<D65, f64>) -> Srgb<f64> {
+        match self {
+            Self::CieLab => color.into_color_unclamped(),
+            Self::OkLab => {
+                let Lab { l, a, b, .. } = color / Self::OKLAB_SCALE;
+                Oklab::new(l, a, b).into_color_unclamped()
+            }
+        }
+    }
+
+    /// Convert a buffer of RGB component bytes into the working space.
+    ///
+    /// The result can be segmented through [`WorkingImage::colors`] with
+    /// functions taking `Lab` buffers, such as [`slic`](crate::slic), and
+    /// recolored with [`mean_colors_in_space`](crate::image::mean_colors_in_space).
+    ///
+    /// ```
+    /// use simple_clustering::options::WorkingSpace;
+    ///
+    /// # fn main() -> Result<(), simple_clustering::error::ScError> {
+    /// let image = [255, 255, 255, 0, 0, 0];
+    /// let oklab = WorkingSpace::OkLab.convert_bytes(&image)?;
+    /// assert_eq!(oklab.space(), WorkingSpace::OkLab);
+    /// assert!((oklab.colors()[0].l - 100.0).abs() < 1e-3);
+    /// assert!(oklab.colors()[1].l.abs() < 1e-9);
+    ///
+    /// assert!(WorkingSpace::OkLab.convert_bytes(&[0; 4]).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The length of `image` must be a multiple of `3`, otherwise
+    /// [`ScError::InvalidByteBufferLength`](crate::error::ScError::InvalidByteBufferLength)
+    /// is returned.
+    pub fn convert_bytes(self, image: &[u8]) -> Result<WorkingImage, crate::error::ScError> {
+        if !image.len().is_multiple_of(3) {
+            return Err(crate::error::ScError::InvalidByteBufferLength {
+                len: image.len(),
+                channels: 3,
+            });
+        }
+        let input_buffer = palette::cast::from_component_slice::<Srgb<u8>>(image);
+        let mut colors = Vec::new();
+        colors.try_reserve_exact(input_buffer.len())?;
+        colors.extend(
+            input_buffer
+                .iter()
+                .map(|&c| self.convert_srgb(c.into_format())),
+        );
+
+        Ok(WorkingImage {
+            space: self,
+            colors,
+        })
+    }
+}
+
+/// Colors converted into a [`WorkingSpace`] by [`WorkingSpace::convert_bytes`].
+///
+/// The colors are stored as `Lab<D65, f64>` so they can be segmented by the
+/// same functions as CIELAB images, but for [`WorkingSpace::OkLab`] they are
+/// scaled OKLab values rather than CIELAB. Functions which read the colors as
+/// CIELAB, such as [`mean_colors`](crate::image::mean_colors),
+/// [`dominant_colors`](crate::image::dominant_colors), or the `Hsv` and `Lch`
+/// [`ColorDistance`] measures, give wrong colors for them. Keeping the colors
+/// in this type ties them to their space, and
+/// [`mean_colors_in_space`](crate::image::mean_colors_in_space) only accepts
+/// this type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkingImage {
+    /// Space the colors were converted to.
+    space: WorkingSpace,
+    /// Converted colors.
+    colors: Vec<Lab<D65, f64>>,
+}
+
+impl WorkingImage {
+    /// The space the colors were converted to.
+    #[must_use]
+    pub fn space(&self) -> WorkingSpace {
+        self.space
+    }
+
+    /// The converted colors, for segmenting with the Euclidean
+    /// [`ColorDistance::Lab`] measure.
+    ///
+    /// These are only CIELAB colors for [`WorkingSpace::CieLab`].
+    #[must_use]
+    pub fn colors(&self) -> &[Lab<D65, f64>] {
+        &self.colors
+    }
+
+    /// The number of pixels in the image.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Returns `true` if the image has no pixels.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+}
+
+/// How colors outside of the sRGB gamut are converted for RGB output, such as
+/// the mean colors of [`mean_colors_with_gamut`](crate::image::mean_colors_with_gamut).
+///
+/// The average of `Lab` colors can fall outside of the sRGB gamut even if each
+/// color is inside of it, most often for regions mixing saturated colors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GamutMapping {
+    /// Clamp each RGB component to the valid range after conversion. This can
+    /// shift the hue and lightness of saturated colors.
+    #[default]
+    Clip,
+    /// Reduce the chroma of the color while keeping its lightness and hue
+    /// until it fits in the sRGB gamut.
+    ReduceChroma,
+}
+
+/// Measure used for the color term of the superpixel distance.
+///
+/// All measures return squared distances on a scale similar to `Lab`, so the
+/// compactness parameter `m` has a comparable effect for each of them.
+///
+/// ```
+/// use palette::{white_point::D65, FromColor, Lab, Lch};
+/// use simple_clustering::options::{ColorDistance, Options};
+/// use simple_clustering::{slic_with_options, snic_with_options};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // Two hue gradients around 20° and 210° meeting at x = 5, with lightness
+/// // alternating in a checkerboard
+/// let (width, height) = (16, 8);
+/// let image: Vec<Lab<D65, f64>> = (0..width * height)
+///     .map(|i| {
+///         let (x, y) = (i % width, i / width);
+///         let hue = if x < 5 { 10.0 } else { 190.0 } + f64::from(x) * 3.0;
+///         let lightness = if (x + y) % 2 == 0 { 30.0 } else { 70.0 };
+///         Lab::from_color(Lch::new(lightness, 50.0, hue))
+///     })
+///     .collect();
+/// let options = Options {
+///     color_distance: ColorDistance::Lch,
+///     ..Options::default()
+/// };
+///
+/// // Similar hues share a label regardless of their lightness
+/// let follows_hue = |labels: &[usize]| {
+///     (0..width * height)
+///         .zip(labels)
+///         .all(|(i, &label)| (label == labels[0]) == (i % width < 5))
+/// };
+/// assert!(follows_hue(&slic_with_options(2, 1, width, height, None, &options, &image)?));
+/// assert!(follows_hue(&snic_with_options(2, 1, width, height, &options, &image)?));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorDistance {
+    /// Euclidean distance in CIELAB.
+    #[default]
+    Lab,
+    /// Distance in the chroma-hue plane of CIELCh, ignoring lightness. Regions
+    /// of similar hue are grouped regardless of their brightness.
+    Lch,
+    /// Distance in the HSV cone, where hue is weighted by saturation and value.
+    Hsv,
+}
+
+impl ColorDistance {
+    /// Calculate the squared distance between two colors with this measure.
+    ///
+    /// Hue differences take the shortest way around the hue circle.
+    ///
+    /// ```
+    /// use palette::{FromColor, Lab, Lch};
+    /// use simple_clustering::options::ColorDistance;
+    ///
+    /// let lab = |hue: f64| Lab::from_color(Lch::new(60.0, 50.0, hue));
+    /// let distance = ColorDistance::Lch;
+    ///
+    /// // 350° and 10° are 20° apart across the 0° boundary
+    /// let across = distance.distance(lab(350.0), lab(10.0));
+    /// let within = distance.distance(lab(30.0), lab(50.0));
+    /// assert!((across - within).abs() < 1e-6);
+    /// assert!(across < distance.distance(lab(350.0), lab(300.0)));
+    ///
+    /// // Lightness is ignored
+    /// let darker = Lab::from_color(Lch::new(20.0, 50.0, 30.0));
+    /// assert!(distance.distance(lab(30.0), darker) < 1e-6);
+    /// ```
+    pub fn distance<Wp>(self, lhs: Lab<Wp, f64>, rhs: Lab<Wp, f64>) -> f64
+    where
+        Wp: WhitePoint<f64>,
+        Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+    {
+        match self {
+            Self::Lab => distance_lab(lhs, rhs),
+            Self::Lch => distance_lch(lhs, rhs),
+            Self::Hsv => distance_hsv(lhs, rhs),
+        }
+    }
+}
+
+/// Quality measure that [`auto_m`](crate::auto_m) optimizes when choosing the
+/// compactness `m`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Objective {
+    /// Maximize the [`boundary_recall`](crate::image::boundary_recall) of an
+    /// edge map with one `bool` per pixel, where edges within `tolerance`
+    /// pixels of a superpixel contour count as recalled.
+    BoundaryRecall {
+        /// Edge map in row-major order, `true` for edge pixels.
+        edges: Vec<bool>,
+        /// Distance in pixels along each axis at which an edge is recalled.
+        tolerance: u32,
+    },
+    /// Minimize the mean squared `Lab` distance of each pixel from the mean
+    /// color of its superpixel.
+    ColorVariance,
+}
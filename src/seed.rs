@@ -1,26 +1,569 @@
 //! Functions for initializing superpixel seeds.
 use crate::error::{ScError, SeedErrorKind};
-use crate::{distance_lab, div_ceil, get_in_bounds, Superpixel};
+use crate::image::HashMap;
+use crate::options::Options;
+use crate::{
+    distance_lab, index_to_xy, pixel_count, validate_grid_interval, ImageView, Superpixel,
+};
 
 use num_traits::{Float, FromPrimitive, ToPrimitive};
 use palette::Lab;
 
-/// Initialize the superpixel seed centers.
+/// Strategy used to place the initial superpixel seeds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Seeding {
+    /// Seeds are spread over a regular grid with an interval of `S` and then
+    /// moved to the lowest gradient position in their 3x3 neighborhood.
+    ///
+    /// The grid holds at most `k` seeds and can hold fewer, see
+    /// [`grid_dimensions`]. Segmenting returns
+    /// [`ScError::UnachievableSuperpixelCount`] if `k` is more than twice the
+    /// number of seeds on the grid.
+    #[default]
+    Grid,
+    /// Seeds are placed at the supplied `(x, y)` pixel coordinates.
+    ///
+    /// The points are used as-is and are not perturbed, so seeds written out
+    /// from [`grid_seeds`] reproduce the same segmentation when read back in.
+    /// The grid interval `S` is still derived from `k`.
+    Points(Vec<(u32, u32)>),
+    /// Seeds are spread over a grid whose columns and rows are chosen so the
+    /// seed count is as close to `k` as possible from above, with separate
+    /// spacing along each axis. They are then perturbed like [`Seeding::Grid`].
+    ///
+    /// [`Seeding::Grid`] uses the same interval `S` along both axes and can
+    /// place noticeably fewer than `k` seeds, while this realizes close to
+    /// `k` seeds for any aspect ratio. See [`init_exact_seeds`].
+    ExactGrid,
+    /// Seeds are spread over the same grid as [`Seeding::Grid`], then each is
+    /// moved by a random offset of up to `S / 4` pixels along each axis before
+    /// being perturbed.
+    ///
+    /// Jittering breaks up the regular grid pattern of the superpixels. The
+    /// offsets are drawn from a generator seeded with
+    /// [`Options::rng_seed`], so the same seed reproduces the same output.
+    JitteredGrid,
+    /// Seeds are chosen by k-means++ over the colors of a sample of the
+    /// pixels, ignoring their positions, and placed at the pixels they were
+    /// picked from.
+    ///
+    /// Each seed is picked with a probability proportional to its squared
+    /// color distance from the seeds picked before it, so rare colors receive
+    /// their own seeds. The seeds are not spread over a regular grid and are
+    /// not perturbed. This is slower than grid seeding for large `k`, and
+    /// since SLIC only searches `2S x 2S` around each center, SNIC is better
+    /// suited to the uneven seed density. Fewer than `k` seeds are placed if
+    /// the sample has fewer distinct colors. The picks are drawn from a
+    /// generator seeded with [`Options::rng_seed`].
+    KMeansPlusPlus,
+}
+
+/// Calculate the seeds that SLIC and SNIC start from with [`Seeding::Grid`],
+/// including the perturbation step.
 ///
-/// `width`, `height`, `s`, and `k` must not be `0`.
-pub fn init_seeds<T: Copy>(
+/// `k` must not be `0`.
+/// `width` and `height` must not be `0`.
+pub fn grid_seeds<Wp>(
+    k: u32,
+    width: u32,
+    height: u32,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<Superpixel<Lab<Wp, f64>>>, ScError> {
+    let s = validate_grid_interval(k, width, height)?;
+    let mut seeds = Vec::new();
+    place_seeds(
+        width,
+        height,
+        s,
+        k,
+        &Options::default(),
+        ImageView::new(image, width),
+        &mut seeds,
+    )?;
+
+    Ok(seeds)
+}
+
+/// Calculate the seeds that [`slic_with_options`](crate::slic_with_options)
+/// and [`snic_with_options`](crate::snic_with_options) start from, following
+/// the seeding and perturbation settings of `options`.
+///
+/// With the default [`Options`] this is the same as [`grid_seeds`]. The seeds
+/// don't depend on the compactness `m`, so they can be inspected or reused
+/// independently of the clustering step.
+///
+/// The grid seeds lie inside of the image for any interval, including an `S`
+/// of `1` where the grid is as dense as the pixels.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::{options::Options, seed::seeds_with_options};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = vec![Lab::<D65, f64>::new(50.0, 10.0, -5.0); 10 * 7];
+/// let mut options = Options::default();
+/// options.perturb = false;
+///
+/// // `S` is 1 for 60 seeds on a 10x7 image
+/// let seeds = seeds_with_options(60, 10, 7, &options, &image)?;
+/// assert_eq!(seeds.len(), 9 * 6);
+/// assert!(seeds.iter().all(|seed| seed.x < 10 && seed.y < 7));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `k` must not be `0`.
+/// `width` and `height` must not be `0`.
+pub fn seeds_with_options<Wp>(
+    k: u32,
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<Superpixel<Lab<Wp, f64>>>, ScError> {
+    let s = validate_grid_interval(k, width, height)?;
+    let mut seeds = Vec::new();
+    place_seeds(
+        width,
+        height,
+        s,
+        k,
+        options,
+        ImageView::new(image, width),
+        &mut seeds,
+    )?;
+
+    Ok(seeds)
+}
+
+/// Calculate the center of each label of an existing segmentation, which is
+/// the mean color and the centroid rounded to the nearest pixel.
+///
+/// The centers are sorted by label. The centroid of a region that isn't convex
+/// can lie outside of the region.
+///
+/// The centers can be passed to [`slic_from_centers`](crate::slic_from_centers)
+/// to refine a previous or hand-edited segmentation with a few more SLIC
+/// iterations. Their coordinates can also be passed back in as
+/// [`Seeding::Points`] to seed a new segmentation near the same regions. Only
+/// the positions are used then, and each seed starts from the color of the
+/// pixel under it rather than the mean color, so the clustering starts over
+/// instead of resuming from the previous centers.
+///
+/// ```
+/// use palette::Lab;
+/// use simple_clustering::seed::{centers_from_labels, Seeding};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [
+///     Lab::new(10.0, 0.0, 0.0), Lab::new(20.0, 0.0, 0.0), Lab::new(80.0, 0.0, 0.0),
+///     Lab::new(10.0, 0.0, 0.0), Lab::new(20.0, 0.0, 0.0), Lab::new(80.0, 0.0, 0.0),
+///     Lab::new(10.0, 0.0, 0.0), Lab::new(20.0, 0.0, 0.0), Lab::new(80.0, 0.0, 0.0),
+/// ];
+/// let labels = [
+///     4, 4, 9,
+///     4, 4, 9,
+///     4, 4, 9,
+/// ];
+/// let centers = centers_from_labels::<palette::white_point::D65>(&labels, &image, 3, 3)?;
+/// assert_eq!((centers[0].x, centers[0].y, centers[0].data.l), (1, 1, 15.0));
+/// assert_eq!((centers[1].x, centers[1].y, centers[1].data.l), (2, 1, 80.0));
+///
+/// let seeding = Seeding::Points(centers.iter().map(|c| c.position()).collect());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `labels` and `image` must have `width * height` elements.
+pub fn centers_from_labels<Wp>(
+    labels: &[usize],
+    image: &[Lab<Wp, f64>],
+    width: u32,
+    height: u32,
+) -> Result<Vec<Superpixel<Lab<Wp, f64>>>, ScError> {
+    let len = pixel_count(width, height)?;
+    if labels.len() != len || image.len() != len {
+        return Err(ScError::General(
+            "Label buffer does not match image dimensions",
+        ));
+    }
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+
+    // Color, x, and y sums, and the pixel count of each label
+    let mut sums = HashMap::<usize, (Lab<Wp, f64>, f64, f64, f64)>::default();
+    for (idx, (&label, &color)) in labels.iter().zip(image).enumerate() {
+        let (x, y) = index_to_xy(idx, width_usize)?;
+        let (x, y) = (f64::from(x), f64::from(y));
+        let _ = sums
+            .entry(label)
+            .and_modify(|sum| {
+                sum.0 += color;
+                sum.1 += x;
+                sum.2 += y;
+                sum.3 += 1.0;
+            })
+            .or_insert((color, x, y, 1.0));
+    }
+
+    let mut sums: Vec<_> = sums.into_iter().collect();
+    sums.sort_unstable_by_key(|&(label, _)| label);
+
+    let mut centers = Vec::new();
+    centers.try_reserve_exact(sums.len())?;
+    for (_, (color, x, y, count)) in sums {
+        let (x, y) = ((x / count).round(), (y / count).round());
+        let (Some(x), Some(y)) = (x.to_u32(), y.to_u32()) else {
+            return Err(ScError::IndexOverflow {
+                x: x.to_u32().unwrap_or(u32::MAX),
+                y: y.to_u32().unwrap_or(u32::MAX),
+            });
+        };
+        centers.push(Superpixel {
+            data: color / count,
+            x,
+            y,
+        });
+    }
+
+    Ok(centers)
+}
+
+/// Initialize the superpixel seeds according to the seeding strategy of
+/// `options`.
+///
+/// Grid seeds are only moved to a lower gradient position if `options.perturb`
+/// is set.
+pub(crate) fn place_seeds<Wp>(
     width: u32,
     height: u32,
     s: u32,
     k: u32,
+    options: &Options,
+    image: ImageView<'_, Lab<Wp, f64>>,
+    seeds: &mut Vec<Superpixel<Lab<Wp, f64>>>,
+) -> Result<(), ScError> {
+    match &options.seeding {
+        Seeding::Grid => {
+            check_grid_capacity(k, init_seeds_view(width, height, s, k, image, seeds)?)?;
+        }
+        Seeding::ExactGrid => {
+            let _ = init_exact_seeds_view(width, height, k, image, seeds)?;
+        }
+        Seeding::JitteredGrid => {
+            check_grid_capacity(k, init_seeds_view(width, height, s, k, image, seeds)?)?;
+            let mut rng = SplitMix64::new(options.rng_seed);
+            for seed in seeds.iter_mut() {
+                jitter(seed, width, height, s / 4, &mut rng, image)?;
+            }
+        }
+        Seeding::Points(points) => {
+            return init_point_seeds_view(width, height, points, image, seeds);
+        }
+        Seeding::KMeansPlusPlus => {
+            return init_kmeans_pp_seeds(width, k, options.rng_seed, image, seeds);
+        }
+    }
+
+    if options.perturb {
+        for seed in seeds.iter_mut() {
+            perturb_view(seed, image)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Return an error if `k` is more than twice the number of seeds on the seed
+/// grid of `(x_seeds, y_seeds)`, since clustering would silently produce far
+/// fewer superpixels than requested.
+fn check_grid_capacity(k: u32, (x_seeds, y_seeds): (u32, u32)) -> Result<(), ScError> {
+    let achievable = x_seeds.saturating_mul(y_seeds);
+    if u64::from(k) > 2 * u64::from(achievable) {
+        return Err(ScError::UnachievableSuperpixelCount {
+            requested: k,
+            achievable,
+        });
+    }
+
+    Ok(())
+}
+
+/// Small deterministic pseudorandom number generator for randomized seeding.
+///
+/// <https://prng.di.unimi.it/splitmix64.c>
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Return a number in `0.0..1.0`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        // The top 53 bits fit the mantissa of an f64 exactly
+        #[allow(clippy::cast_precision_loss)]
+        let next = (self.next_u64() >> 11) as f64;
+        next * (f64::EPSILON / 2.0)
+    }
+
+    /// Return a number in `0..n`, `n` must not be `0`. The modulo bias is
+    /// negligible for the small ranges used in seeding.
+    pub(crate) fn next_below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+/// Move a seed by a random offset of up to `max_offset` pixels along each
+/// axis, keeping it inside of the image.
+fn jitter<T: Copy>(
+    seed: &mut Superpixel<T>,
+    width: u32,
+    height: u32,
+    max_offset: u32,
+    rng: &mut SplitMix64,
+    image: ImageView<'_, T>,
+) -> Result<(), ScError> {
+    let range = u64::from(max_offset) * 2 + 1;
+    let mut offset = |coordinate: u32, dimension: u32| {
+        // Offset in `-max_offset..=max_offset`, stopping at the image edges
+        let moved =
+            (u64::from(coordinate) + rng.next_below(range)).saturating_sub(u64::from(max_offset));
+        u32::try_from(moved).map_or(dimension - 1, |c| c.min(dimension - 1))
+    };
+    seed.x = offset(seed.x, width);
+    seed.y = offset(seed.y, height);
+
+    seed.data = *image
+        .get(i64::from(seed.x), i64::from(seed.y))
+        .ok_or(ScError::SeedError(SeedErrorKind::InvalidImageIndex))?;
+
+    Ok(())
+}
+
+/// Initialize the superpixel seed centers from a list of `(x, y)` points.
+///
+/// Every point must lie inside the image and `points` must not be empty.
+pub fn init_point_seeds<T: Copy>(
+    width: u32,
+    height: u32,
+    points: &[(u32, u32)],
     image: &[T],
     seeds: &mut Vec<Superpixel<T>>,
+) -> Result<(), ScError> {
+    init_point_seeds_view(width, height, points, ImageView::new(image, width), seeds)
+}
+
+/// [`init_point_seeds`] for a view of the image.
+fn init_point_seeds_view<T: Copy>(
+    width: u32,
+    height: u32,
+    points: &[(u32, u32)],
+    image: ImageView<'_, T>,
+    seeds: &mut Vec<Superpixel<T>>,
+) -> Result<(), ScError> {
+    seeds.clear();
+    if points.is_empty() {
+        return Err(ScError::SeedError(SeedErrorKind::EmptySeeds));
+    }
+
+    if points.len() > seeds.capacity() {
+        seeds.try_reserve_exact(points.len() - seeds.capacity())?;
+    }
+
+    for &(x, y) in points {
+        if x >= width || y >= height {
+            return Err(ScError::SeedError(SeedErrorKind::InvalidImageIndex));
+        }
+        let data = *image
+            .get(i64::from(x), i64::from(y))
+            .ok_or(ScError::SeedError(SeedErrorKind::InvalidImageIndex))?;
+        seeds.push(Superpixel { data, x, y });
+    }
+
+    Ok(())
+}
+
+/// Minimum number of pixels sampled for [`Seeding::KMeansPlusPlus`].
+const KMEANS_PP_MIN_SAMPLES: usize = 10_000;
+
+/// Pick up to `k` seeds with k-means++ over the colors of an evenly spaced
+/// sample of the pixels, see [`Seeding::KMeansPlusPlus`].
+fn init_kmeans_pp_seeds<Wp>(
+    width: u32,
+    k: u32,
+    rng_seed: u64,
+    image: ImageView<'_, Lab<Wp, f64>>,
+    seeds: &mut Vec<Superpixel<Lab<Wp, f64>>>,
 ) -> Result<(), ScError> {
     seeds.clear();
-    let s = s;
-    let half_s = div_ceil(s, 2);
-    let mut x_seeds = div_ceil(width, s);
-    let mut y_seeds = div_ceil(height, s);
+    let width = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+    let k = usize::try_from(k).or(Err("Could not convert k to usize"))?;
+
+    // Sample every `step`th pixel, with at least a few candidates per seed
+    let samples = KMEANS_PP_MIN_SAMPLES
+        .max(k.saturating_mul(4))
+        .min(image.len());
+    let step = image.len().checked_div(samples).unwrap_or(1).max(1);
+    let mut candidates = Vec::new();
+    candidates.try_reserve_exact(samples)?;
+    for idx in (0..image.len()).step_by(step).take(samples) {
+        let (x, y) = index_to_xy(idx, width)?;
+        let data = *image
+            .get(i64::from(x), i64::from(y))
+            .ok_or(ScError::SeedError(SeedErrorKind::InvalidImageIndex))?;
+        // Squared distance to the nearest picked seed
+        candidates.push((Superpixel { data, x, y }, f64::INFINITY));
+    }
+    if candidates.is_empty() {
+        return Err(ScError::SeedError(SeedErrorKind::EmptySeeds));
+    }
+
+    if k > seeds.capacity() {
+        seeds.try_reserve_exact(k - seeds.capacity())?;
+    }
+    let mut rng = SplitMix64::new(rng_seed);
+    let first =
+        usize::try_from(rng.next_below(candidates.len() as u64)).or(Err("Invalid first seed"))?;
+    let mut pick = candidates.get(first).map(|&(seed, _)| seed);
+    while let Some(seed) = pick.take() {
+        seeds.push(seed);
+        if seeds.len() == k {
+            break;
+        }
+
+        let mut total = 0.0;
+        for (candidate, nearest) in &mut candidates {
+            *nearest = nearest.min(distance_lab(candidate.data, seed.data));
+            total += *nearest;
+        }
+        if total.is_nan() || total <= 0.0 {
+            // Every remaining color has already been picked
+            break;
+        }
+
+        // Pick the next seed with a probability proportional to its distance
+        let mut target = rng.next_f64() * total;
+        pick = candidates
+            .iter()
+            .find(|&&(_, nearest)| {
+                target -= nearest;
+                nearest > 0.0 && target < 0.0
+            })
+            .or_else(|| candidates.iter().rfind(|&&(_, nearest)| nearest > 0.0))
+            .map(|&(candidate, _)| candidate);
+    }
+
+    Ok(())
+}
+
+/// Calculate the number of seed columns and rows, `(x_seeds, y_seeds)`, in the
+/// seed grid that SLIC and SNIC use with [`Seeding::Grid`].
+///
+/// Seeds which would land outside the image are skipped, so the realized seed
+/// count can be lower than `x_seeds * y_seeds`. Segmenting with a `k` of more
+/// than twice `x_seeds * y_seeds` returns
+/// [`ScError::UnachievableSuperpixelCount`].
+///
+/// ```
+/// use simple_clustering::{error::ScError, seed::grid_dimensions, snic};
+///
+/// # fn main() -> Result<(), ScError> {
+/// // Only 2 of the 5 requested seeds fit the grid of a 2x3 image
+/// assert_eq!(grid_dimensions(5, 2, 3)?, (1, 2));
+///
+/// // `S` is 2 for 15 seeds on a 10x7 image, even interval
+/// assert_eq!(grid_dimensions(15, 10, 7)?, (5, 3));
+/// // `S` is 3 for 7 seeds, odd interval
+/// assert_eq!(grid_dimensions(7, 10, 7)?, (3, 2));
+/// // `S` is 1 for 60 seeds, and the grid is trimmed down to `k`
+/// assert_eq!(grid_dimensions(60, 10, 7)?, (9, 6));
+/// // `S` is larger than the height for 1 seed
+/// assert_eq!(grid_dimensions(1, 10, 7)?, (1, 1));
+/// // A single row of seeds is still trimmed down to `k`
+/// assert_eq!(grid_dimensions(3, 100, 1)?, (3, 1));
+///
+/// let image = vec![palette::Lab::<palette::white_point::D65, f64>::default(); 6];
+/// assert!(matches!(
+///     snic(5, 10, 2, 3, &image),
+///     Err(ScError::UnachievableSuperpixelCount { requested: 5, achievable: 2 })
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `k` must not be `0`.
+/// `width` and `height` must not be `0`.
+pub fn grid_dimensions(k: u32, width: u32, height: u32) -> Result<(u32, u32), ScError> {
+    let s = validate_grid_interval(k, width, height)?;
+
+    Ok(grid_shape(width, height, s, k))
+}
+
+/// Calculate the largest superpixel count which can be requested for an image
+/// when [`Options::count_tolerance`] is set, which is half of the pixels.
+///
+/// Above a quarter of the pixels, the grid interval `S` is `1` and neighboring
+/// seeds are moved onto the same regions while perturbing, so the count found
+/// falls well short of `k`. Past half of the pixels the shortfall is large
+/// enough that segmenting with a count tolerance returns
+/// [`ScError::UnachievableSuperpixelCount`] before clustering, rather than
+/// clustering only to report [`ScError::SuperpixelCountMismatch`].
+///
+/// The limit only applies when `k` is requested with perturbed
+/// [`Seeding::Grid`] or [`Seeding::JitteredGrid`] seeds. Other seeding
+/// strategies and unperturbed seeds don't move seeds together, so their
+/// counts are only checked after clustering.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::{error::ScError, options::Options, seed::max_superpixel_count};
+/// use simple_clustering::snic_with_options;
+///
+/// # fn main() -> Result<(), ScError> {
+/// assert_eq!(max_superpixel_count(40, 30)?, 600);
+///
+/// let image = vec![Lab::<D65, f64>::default(); 40 * 30];
+/// let options = Options {
+///     count_tolerance: Some(0.5),
+///     ..Options::default()
+/// };
+/// assert!(matches!(
+///     snic_with_options(700, 10, 40, 30, &options, &image),
+///     Err(ScError::UnachievableSuperpixelCount { requested: 700, achievable: 600 })
+/// ));
+///
+/// // Without perturbing, the count is only checked after clustering
+/// let options = Options { perturb: false, ..options };
+/// assert!(!matches!(
+///     snic_with_options(700, 10, 40, 30, &options, &image),
+///     Err(ScError::UnachievableSuperpixelCount { .. })
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `width` and `height` must not be `0`.
+pub fn max_superpixel_count(width: u32, height: u32) -> Result<u32, ScError> {
+    if width == 0 || height == 0 {
+        return Err(ScError::InvalidImageDimension);
+    }
+    let half = u64::from(width) * u64::from(height) / 2;
+
+    Ok(u32::try_from(half).unwrap_or(u32::MAX))
+}
+
+/// Calculate the number of seed columns and rows for a grid interval of `s`.
+pub(crate) fn grid_shape(width: u32, height: u32, s: u32, k: u32) -> (u32, u32) {
+    let mut x_seeds = width.div_ceil(s);
+    let mut y_seeds = height.div_ceil(s);
 
     // The seeds per row and column might be too high due to the div_ceil
     if u64::from(s) * u64::from(x_seeds) > u64::from(width) {
@@ -30,13 +573,6 @@ pub fn init_seeds<T: Copy>(
         y_seeds -= 1;
     }
 
-    // If the seed count is larger than k, reduce until we're below (we may add
-    // seeds in the "enforce connectivity" step later for SLIC)
-    while u64::from(x_seeds) * u64::from(y_seeds) > u64::from(k) {
-        x_seeds -= 1;
-        y_seeds -= 1;
-    }
-
     // Edge case for very small image sizes where no clusters would be produced
     if x_seeds == 0 {
         x_seeds += 1;
@@ -45,12 +581,240 @@ pub fn init_seeds<T: Copy>(
         y_seeds += 1;
     }
 
+    // If the seed count is larger than k, reduce until we're below (we may add
+    // seeds in the "enforce connectivity" step later for SLIC)
+    while u64::from(x_seeds) * u64::from(y_seeds) > u64::from(k) {
+        x_seeds = x_seeds.saturating_sub(1).max(1);
+        y_seeds = y_seeds.saturating_sub(1).max(1);
+    }
+
+    (x_seeds, y_seeds)
+}
+
+/// Initialize the superpixel seed centers.
+///
+/// The return value is the number of seed columns and rows in the grid,
+/// `(x_seeds, y_seeds)`.
+///
+/// ```
+/// use simple_clustering::seed::init_seeds;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = [0u8; 10 * 7];
+/// let mut seeds = Vec::new();
+/// let positions = |seeds: &[simple_clustering::Superpixel<u8>]| {
+///     seeds.iter().map(|s| s.position()).collect::<Vec<_>>()
+/// };
+///
+/// // Every pixel is a seed when `s` is 1
+/// assert_eq!(init_seeds(10, 7, 1, 70, &image, &mut seeds)?, (10, 7));
+/// let pixels = (0..7).flat_map(|y| (0..10).map(move |x| (x, y)));
+/// assert!(positions(&seeds).into_iter().eq(pixels));
+///
+/// // Even interval
+/// assert_eq!(init_seeds(10, 7, 2, 1000, &image, &mut seeds)?, (5, 3));
+/// assert_eq!(
+///     positions(&seeds),
+///     [
+///         (1, 1), (3, 1), (5, 1), (7, 1), (9, 1),
+///         (1, 3), (3, 3), (5, 3), (7, 3), (9, 3),
+///         (1, 5), (3, 5), (5, 5), (7, 5), (9, 5),
+///     ]
+/// );
+///
+/// // Odd interval
+/// assert_eq!(init_seeds(10, 7, 3, 1000, &image, &mut seeds)?, (3, 2));
+/// assert_eq!(positions(&seeds), [(2, 2), (5, 2), (8, 2), (2, 5), (5, 5), (8, 5)]);
+///
+/// // An interval larger than both dimensions places a single seed
+/// assert_eq!(init_seeds(10, 7, 12, 1000, &image, &mut seeds)?, (1, 1));
+/// assert_eq!(positions(&seeds), [(5, 4)]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `width`, `height`, `s`, and `k` must not be `0`.
+pub fn init_seeds<T: Copy>(
+    width: u32,
+    height: u32,
+    s: u32,
+    k: u32,
+    image: &[T],
+    seeds: &mut Vec<Superpixel<T>>,
+) -> Result<(u32, u32), ScError> {
+    init_seeds_view(width, height, s, k, ImageView::new(image, width), seeds)
+}
+
+/// Initialize the superpixel seed centers of an image whose rows are still
+/// arriving, placing only the seeds in the rows received so far.
+///
+/// `rows` holds the first complete rows of the `width` x `height` image. The
+/// grid is the one [`init_seeds`] places for the full image, and the seeds are
+/// placed in the same row-major order, so they're always a prefix of the full
+/// image's seeds. Calling this again after more rows arrive places the same
+/// seeds followed by the new ones, and once every row has arrived the seeds
+/// match [`init_seeds`].
+///
+/// Seeds are placed at the center of each grid cell, so the first row of seeds
+/// is available once about `s / 2` rows have arrived. [`perturb`] reads the
+/// two rows below a seed, so seeds should only be perturbed once those rows
+/// have arrived too.
+///
+/// The return value is the number of seed columns and rows in the grid of the
+/// full image, `(x_seeds, y_seeds)`.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::seed::{init_seeds, init_seeds_partial};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let (width, height, s, k) = (40, 30, 10, 12);
+/// let image = vec![Lab::<D65, f64>::default(); 40 * 30];
+///
+/// // Seed the new rows of the grid as every `s` rows of the image arrive
+/// let mut seeds = Vec::new();
+/// let mut seeded = 0;
+/// for received in (s..=height).step_by(s as usize) {
+///     let rows = &image[..(received * width) as usize];
+///     init_seeds_partial(width, height, s, k, rows, &mut seeds)?;
+///     for seed in &seeds[seeded..] {
+///         assert!(seed.y < received);
+///     }
+///     seeded = seeds.len();
+/// }
+///
+/// let mut full = Vec::new();
+/// init_seeds(width, height, s, k, &image, &mut full)?;
+/// assert_eq!(seeds.len(), full.len());
+/// assert!(seeds.iter().zip(&full).all(|(a, b)| a.position() == b.position()));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `width`, `height`, `s`, and `k` must not be `0`. The length of `rows` must
+/// be a multiple of `width` and must not be larger than `width * height`.
+pub fn init_seeds_partial<T: Copy>(
+    width: u32,
+    height: u32,
+    s: u32,
+    k: u32,
+    rows: &[T],
+    seeds: &mut Vec<Superpixel<T>>,
+) -> Result<(u32, u32), ScError> {
+    if width == 0 || height == 0 {
+        return Err(ScError::InvalidImageDimension);
+    }
+    let row_len = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+    if !rows.len().is_multiple_of(row_len) || rows.len() > pixel_count(width, height)? {
+        return Err(ScError::General(
+            "Partial buffer must hold whole rows of the image",
+        ));
+    }
+
+    // Seeds in rows which haven't arrived yet are skipped since the view has
+    // no pixels there
+    init_seeds_view(width, height, s, k, ImageView::new(rows, width), seeds)
+}
+
+/// [`init_seeds`] for a view of the image.
+fn init_seeds_view<T: Copy>(
+    width: u32,
+    height: u32,
+    s: u32,
+    k: u32,
+    image: ImageView<'_, T>,
+    seeds: &mut Vec<Superpixel<T>>,
+) -> Result<(u32, u32), ScError> {
+    seeds.clear();
+    let (x_seeds, y_seeds) = grid_shape(width, height, s, k);
+    spread_seeds(width, height, (s, s), (x_seeds, y_seeds), image, seeds)?;
+
+    Ok((x_seeds, y_seeds))
+}
+
+/// Initialize the superpixel seed centers on a grid of close to `k` seeds.
+///
+/// The number of seed columns is `round(sqrt(k * width / height))` and the
+/// number of rows is `ceil(k / x_seeds)`, so `x_seeds * y_seeds` is the
+/// smallest grid of that shape with at least `k` seeds. Each axis is spaced
+/// evenly on its own. The counts are limited to the image dimensions, so very
+/// small images can receive fewer than `k` seeds.
+///
+/// The return value is the number of seed columns and rows in the grid,
+/// `(x_seeds, y_seeds)`.
+///
+/// `width`, `height`, and `k` must not be `0`.
+pub fn init_exact_seeds<T: Copy>(
+    width: u32,
+    height: u32,
+    k: u32,
+    image: &[T],
+    seeds: &mut Vec<Superpixel<T>>,
+) -> Result<(u32, u32), ScError> {
+    init_exact_seeds_view(width, height, k, ImageView::new(image, width), seeds)
+}
+
+/// [`init_exact_seeds`] for a view of the image.
+fn init_exact_seeds_view<T: Copy>(
+    width: u32,
+    height: u32,
+    k: u32,
+    image: ImageView<'_, T>,
+    seeds: &mut Vec<Superpixel<T>>,
+) -> Result<(u32, u32), ScError> {
+    seeds.clear();
+    if width == 0 || height == 0 {
+        return Err(ScError::InvalidImageDimension);
+    }
+    if k == 0 {
+        return Err(ScError::ZeroSuperpixelCount);
+    }
+
+    let x_seeds = (f64::from(k) * f64::from(width) / f64::from(height))
+        .sqrt()
+        .round()
+        .to_u32()
+        .ok_or("Could not convert seed column count")?
+        .clamp(1, width);
+    let y_seeds = k.div_ceil(x_seeds).clamp(1, height);
+
+    let interval = (width / x_seeds, height / y_seeds);
+    spread_seeds(width, height, interval, (x_seeds, y_seeds), image, seeds)?;
+
+    Ok((x_seeds, y_seeds))
+}
+
+/// Place `x_seeds * y_seeds` seeds spaced `interval` pixels apart along each
+/// axis, spreading the remainder of the dimensions evenly between them.
+fn spread_seeds<T: Copy>(
+    width: u32,
+    height: u32,
+    (s_x, s_y): (u32, u32),
+    (x_seeds, y_seeds): (u32, u32),
+    image: ImageView<'_, T>,
+    seeds: &mut Vec<Superpixel<T>>,
+) -> Result<(), ScError> {
+    // Offset of the first seed along an axis. The interval is limited to the
+    // dimension since the grid can be forced to have one seed along an axis
+    // shorter than `s`, and the offset is kept below the interval so the last
+    // seed lands inside of the image when `s` is `1`.
+    let half_s = |s: u32, dimension: u32| {
+        let s = s.min(dimension);
+        s.div_ceil(2).min(s.saturating_sub(1))
+    };
+    let half_s_x = half_s(s_x, width);
+    let half_s_y = half_s(s_y, height);
+
     // Error correction for spreading the seeds out more evenly along rows/cols
-    let x_correction = (f64::from(width) - f64::from(x_seeds) * f64::from(s)) / f64::from(x_seeds);
-    let y_correction = (f64::from(height) - f64::from(y_seeds) * f64::from(s)) / f64::from(y_seeds);
+    let x_correction =
+        (f64::from(width) - f64::from(x_seeds) * f64::from(s_x)) / f64::from(x_seeds);
+    let y_correction =
+        (f64::from(height) - f64::from(y_seeds) * f64::from(s_y)) / f64::from(y_seeds);
 
-    let total_seeds = usize::try_from(u64::from(x_seeds) * u64::from(y_seeds))
-        .or(Err(ScError::SeedError(SeedErrorKind::InvalidTotalSeeds)))?;
+    let total_seeds = x_seeds
+        .checked_mul(y_seeds)
+        .and_then(|total| usize::try_from(total).ok())
+        .ok_or(ScError::Overflow("seed count"))?;
 
     if total_seeds > seeds.capacity() {
         seeds.try_reserve_exact(total_seeds - seeds.capacity())?;
@@ -65,27 +829,17 @@ pub fn init_seeds<T: Copy>(
                 .to_u32()
                 .ok_or("Could not convert X correction")?;
             let x = xdx
-                .saturating_mul(s)
-                .saturating_add(half_s)
+                .saturating_mul(s_x)
+                .saturating_add(half_s_x)
                 .saturating_add(x_correct);
             let y = ydx
-                .saturating_mul(s)
-                .saturating_add(half_s)
+                .saturating_mul(s_y)
+                .saturating_add(half_s_y)
                 .saturating_add(y_correct);
-            let i = usize::try_from(
-                u64::from(y)
-                    .saturating_mul(u64::from(width))
-                    .saturating_add(u64::from(x)),
-            )
-            .or(Err(ScError::SeedError(SeedErrorKind::InvalidImageIndex)))?;
-            if x < width && y < height && i < image.len() {
-                seeds.push(Superpixel {
-                    data: *image
-                        .get(i)
-                        .ok_or(ScError::SeedError(SeedErrorKind::InvalidImageIndex))?,
-                    x,
-                    y,
-                });
+            if x < width && y < height {
+                if let Some(&data) = image.get(i64::from(x), i64::from(y)) {
+                    seeds.push(Superpixel { data, x, y });
+                }
             }
         }
     }
@@ -96,12 +850,32 @@ pub fn init_seeds<T: Copy>(
 /// Find the lowest gradient in a 3x3 neighborhood for a seed.
 ///
 /// This step minimizes the chance that a noisy pixel is chosen as a seed.
+/// Only the first `width * height` pixels of `image` are read, so rows past
+/// `height` are treated as outside of the image.
 pub fn perturb<Wp, T>(
     seed: &mut Superpixel<Lab<Wp, T>>,
     width: i64,
     height: i64,
     image: &[Lab<Wp, T>],
 ) -> Result<(), ScError>
+where
+    T: Float + FromPrimitive,
+    Lab<Wp, T>: Default,
+{
+    // A width or height outside of the `u32` range has no pixels to move to,
+    // and pixels in the rows past `height` are outside of the image
+    let width = u32::try_from(width).unwrap_or_default();
+    let height = u32::try_from(height).unwrap_or_default();
+    let len = usize::try_from(u64::from(width) * u64::from(height)).unwrap_or(usize::MAX);
+    let image = image.get(..len).unwrap_or(image);
+    perturb_view(seed, ImageView::new(image, width))
+}
+
+/// [`perturb`] for a view of the image.
+fn perturb_view<Wp, T>(
+    seed: &mut Superpixel<Lab<Wp, T>>,
+    image: ImageView<'_, Lab<Wp, T>>,
+) -> Result<(), ScError>
 where
     T: Float + FromPrimitive,
     Lab<Wp, T>: Default,
@@ -111,32 +885,14 @@ where
     let sp_x = i64::from(seed.x);
     let sp_y = i64::from(seed.y);
 
-    // Gradient equation is
-    // fn gradient() -> f64 {
-    //     (I[x + 1, y] - I[x - 1, y]).powi(2) +
-    //     (I[x, y + 1] - I[x, y - 1]).powi(2)
-    // }
     for ydx in -1..=1 {
         for xdx in -1..=1 {
-            let superpixel =
-                if let Some(color) = get_in_bounds(width, height, sp_x + xdx, sp_y + ydx, image) {
-                    (*color, sp_x + xdx, sp_y + ydx)
-                } else {
-                    continue;
-                };
-            let a_x = sp_x + xdx + 1;
-            let b_x = sp_x + xdx - 1;
-            let ab_y = sp_y + ydx;
-            let cd_x = sp_x + xdx;
-            let c_y = sp_y + ydx + 1;
-            let d_y = sp_y + ydx - 1;
-
-            let a = *get_in_bounds(width, height, a_x, ab_y, image).unwrap_or(&default);
-            let b = *get_in_bounds(width, height, b_x, ab_y, image).unwrap_or(&default);
-            let c = *get_in_bounds(width, height, cd_x, c_y, image).unwrap_or(&default);
-            let d = *get_in_bounds(width, height, cd_x, d_y, image).unwrap_or(&default);
-
-            let gradient = distance_lab(a, b) + distance_lab(c, d);
+            let superpixel = if let Some(color) = image.get(sp_x + xdx, sp_y + ydx) {
+                (*color, sp_x + xdx, sp_y + ydx)
+            } else {
+                continue;
+            };
+            let gradient = gradient(image, superpixel.1, superpixel.2, default);
             if gradient < min {
                 min = gradient;
                 seed.data = superpixel.0;
@@ -150,3 +906,69 @@ where
 
     Ok(())
 }
+
+/// Calculate the squared `Lab` gradient at `(x, y)`, using `outside` in place
+/// of neighbors beyond the edges of the image.
+pub(crate) fn gradient<Wp, T>(
+    image: ImageView<'_, Lab<Wp, T>>,
+    x: i64,
+    y: i64,
+    outside: Lab<Wp, T>,
+) -> T
+where
+    T: Float,
+{
+    // Gradient equation is
+    // fn gradient() -> f64 {
+    //     (I[x + 1, y] - I[x - 1, y]).powi(2) +
+    //     (I[x, y + 1] - I[x, y - 1]).powi(2)
+    // }
+    let get = |x, y| *image.get(x, y).unwrap_or(&outside);
+
+    distance_lab(get(x + 1, y), get(x - 1, y)) + distance_lab(get(x, y + 1), get(x, y - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_count_overflow() {
+        // A 65536x65536 grid holds one seed more than fits in a `u32`
+        let mut seeds = Vec::<Superpixel<u8>>::new();
+        assert!(matches!(
+            init_exact_seeds(u32::MAX, u32::MAX, u32::MAX, &[], &mut seeds),
+            Err(ScError::Overflow("seed count"))
+        ));
+        assert!(seeds.is_empty());
+    }
+
+    #[test]
+    fn grid_dimensions_limits() {
+        let max = u32::MAX;
+        assert_eq!(grid_dimensions(max, max, max).ok(), Some((65535, 65535)));
+        assert_eq!(grid_dimensions(1, max, max).ok(), Some((1, 1)));
+        assert_eq!(grid_dimensions(max - 1, max, 1).ok(), Some((max - 1, 1)));
+        assert_eq!(grid_dimensions(1, 1, max).ok(), Some((1, 1)));
+    }
+
+    #[test]
+    fn k_near_pixel_count() {
+        let image = [0u8; 10 * 7];
+        let mut seeds = Vec::new();
+        for k in [68, 69] {
+            let (x_seeds, y_seeds) = grid_dimensions(k, 10, 7).unwrap();
+            assert_eq!((x_seeds, y_seeds), (9, 6));
+            assert_eq!(
+                init_seeds(10, 7, 1, k, &image, &mut seeds).ok(),
+                Some((x_seeds, y_seeds))
+            );
+            assert_eq!(seeds.len(), 54);
+            assert!(seeds.iter().all(|s| s.x < 10 && s.y < 7));
+        }
+        assert!(matches!(
+            grid_dimensions(70, 10, 7),
+            Err(ScError::InvalidSuperpixelCount)
+        ));
+    }
+}
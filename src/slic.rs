@@ -1,12 +1,25 @@
-use crate::error::ScError;
-use crate::seed::{init_seeds, perturb};
+use std::borrow::Cow;
+
+use crate::edges::{self, edge_regions, EdgeRegions};
+use crate::error::{ScError, SeedErrorKind};
+use crate::image::{boundary_recall, region_stats};
+use crate::options::{AlphaMode, Connectivity, Objective, Options, WorkingSpace};
+use crate::seed::place_seeds;
 use crate::{
-    calculate_grid_interval, distance_lab, distance_s, distance_xy, get_in_bounds,
-    get_mut_in_bounds, m_div_s,
+    check_component_buffer, check_superpixel_count, check_superpixel_limit, checked_coords,
+    distance_lab, distance_s, distance_xy, get_in_bounds, get_mut_in_bounds, index, index_to_xy,
+    m_div_s, pack_labels, pixel_count, pre_smooth, rgba_bytes_to_lab, srgb_components_into_lab,
+    srgb_components_to_lab, unwrap_x, wrap_coordinate, wrap_x, AsFeature3, GridSize, ImageView,
+    Stopwatch, Superpixel, Timings,
 };
 
-use num_traits::ToPrimitive;
-use palette::{cast, FromColor, Lab, Srgb};
+use num_traits::{ToPrimitive, Unsigned};
+use palette::{
+    encoding,
+    rgb::Rgb,
+    white_point::{WhitePoint, D65},
+    IntoColor, Lab,
+};
 
 /// Information for tracking image pixels' nearest superpixel cluster and
 /// distance to that cluster during SLIC.
@@ -48,6 +61,9 @@ struct SlicUpdate<T> {
     pub y: f64,
     /// Total elements in the cluster.
     pub count: f64,
+    /// Whether any pixel was assigned to the cluster, even with a weight of
+    /// `0`.
+    pub assigned: bool,
 }
 
 impl<T: Default> SlicUpdate<T> {
@@ -67,17 +83,31 @@ impl<T: Default> Default for SlicUpdate<T> {
             x: Default::default(),
             y: Default::default(),
             count: Default::default(),
+            assigned: false,
         }
     }
 }
 
 /// Calculate SLIC by providing a buffer of RGB component bytes as `&[u8]`.
 ///
-/// `iter` will default to `10` if `None` is supplied.
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
 ///
 /// `k` must not be `0`.
 /// `m` is clamped to be between `1` and `20`.
 /// `width` and `height` must not be `0`.
+/// A length of `image` that isn't a multiple of `3` returns
+/// [`ScError::InvalidByteBufferLength`], and any other length than
+/// `width * height * 3` returns [`ScError::MismatchedSlicBuffer`].
+///
+/// ```
+/// use simple_clustering::{error::ScError, slic_from_bytes};
+///
+/// let result = slic_from_bytes(1, 10, 2, 2, None, &[0; 13]);
+/// assert!(matches!(result, Err(ScError::InvalidByteBufferLength { len: 13, channels: 3 })));
+/// let result = slic_from_bytes(1, 10, 2, 2, None, &[0; 15]);
+/// assert!(matches!(result, Err(ScError::MismatchedSlicBuffer)));
+/// ```
 pub fn slic_from_bytes(
     k: u32,
     m: u8,
@@ -86,31 +116,175 @@ pub fn slic_from_bytes(
     iter: Option<u8>,
     image: &[u8],
 ) -> Result<Vec<usize>, ScError> {
-    if usize::try_from(u64::from(width) * u64::from(height))
-        .or(Err("Invalid image dimensions in SLIC from bytes"))?
-        != image.len() / 3
-    {
-        return Err(ScError::MismatchedSlicBuffer);
-    }
-    let input_buffer = cast::from_component_slice::<Srgb<u8>>(image);
-    let mut input_lab: Vec<Lab<_, f64>> = Vec::new();
-    input_lab.try_reserve_exact(input_buffer.len())?;
-    input_lab.extend(
-        input_buffer
-            .iter()
-            .map(|&c| Lab::from_color(c.into_format())),
-    );
+    check_component_buffer(image.len(), 3, width, height, ScError::MismatchedSlicBuffer)?;
+    let input_lab = srgb_components_to_lab(image)?;
+
+    slic(k, m, width, height, iter, &input_lab)
+}
+
+/// Calculate SLIC by providing a buffer of RGB component bytes as `&[u8]`,
+/// clustering the colors in the working space `space`.
+///
+/// The labels of [`WorkingSpace::CieLab`] match [`slic_from_bytes`]. Use
+/// [`WorkingSpace::convert_bytes`] and
+/// [`mean_colors_in_space`](crate::image::mean_colors_in_space) to recolor the
+/// segments with mean colors averaged in the same space.
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_from_bytes_in_space(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    space: WorkingSpace,
+    image: &[u8],
+) -> Result<Vec<usize>, ScError> {
+    check_component_buffer(image.len(), 3, width, height, ScError::MismatchedSlicBuffer)?;
+    let input = space.convert_bytes(image)?;
+
+    slic(k, m, width, height, iter, input.colors())
+}
+
+/// Calculate SLIC by providing a buffer of RGBA component bytes as `&[u8]`.
+///
+/// The alpha component is not used for segmentation. With
+/// [`AlphaMode::Premultiplied`], the colors are divided by alpha before
+/// conversion to `Lab`, and fully transparent pixels are treated as black.
+/// With [`AlphaMode::Straight`], the colors of transparent pixels are kept.
+///
+/// ```
+/// use simple_clustering::{options::AlphaMode, slic_from_bytes, slic_from_rgba_bytes};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let rgb: Vec<u8> = (0..64u8).flat_map(|i| [i * 4, 255 - i * 4, 128]).collect();
+/// // The same colors, all fully transparent
+/// let rgba: Vec<u8> = rgb.chunks(3).flat_map(|c| [c[0], c[1], c[2], 0]).collect();
+/// assert_eq!(
+///     slic_from_rgba_bytes(4, 10, 8, 8, None, AlphaMode::Straight, &rgba)?,
+///     slic_from_bytes(4, 10, 8, 8, None, &rgb)?
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_from_rgba_bytes(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    alpha_mode: AlphaMode,
+    image: &[u8],
+) -> Result<Vec<usize>, ScError> {
+    check_component_buffer(image.len(), 4, width, height, ScError::MismatchedSlicBuffer)?;
+    let input_lab = rgba_bytes_to_lab(image, alpha_mode)?;
+
+    slic(k, m, width, height, iter, &input_lab)
+}
+
+/// Calculate SLIC by providing a buffer of RGB component bytes as `&[u8]`,
+/// converting to `Lab` in the caller-owned `buffer`.
+///
+/// `buffer` is cleared before use and only grows when it's too small, so it
+/// can be reused across calls to avoid reallocating for images of the same
+/// size.
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_from_bytes_with_buffer(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    image: &[u8],
+    buffer: &mut Vec<Lab<D65, f64>>,
+) -> Result<Vec<usize>, ScError> {
+    check_component_buffer(image.len(), 3, width, height, ScError::MismatchedSlicBuffer)?;
+    srgb_components_into_lab(image, buffer)?;
+
+    slic(k, m, width, height, iter, buffer)
+}
+
+/// Calculate SLIC by providing a buffer of 16-bit RGB components as `&[u16]`.
+///
+/// The components are normalized from the full `u16` range before conversion
+/// to `Lab`, preserving the precision of high bit depth images.
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_from_u16(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    image: &[u16],
+) -> Result<Vec<usize>, ScError> {
+    check_component_buffer(image.len(), 3, width, height, ScError::MismatchedSlicBuffer)?;
+    let input_lab = srgb_components_to_lab(image)?;
 
     slic(k, m, width, height, iter, &input_lab)
 }
 
 /// Calculate SLIC.
 ///
-/// `iter` will default to `10` if `None` is supplied.
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
 ///
 /// `k` must not be `0`.
 /// `m` is clamped to be between `1` and `20`.
 /// `width` and `height` must not be `0`.
+/// `image` must have `width * height` elements.
+///
+/// An image of a single color has no color distance between pixels, so only
+/// the spatial term separates the clusters and the result is a regular grid of
+/// one superpixel per seed.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::slic;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = vec![Lab::<D65, f64>::new(50.0, 10.0, -5.0); 60 * 40];
+/// let mut labels = slic(24, 10, 60, 40, None, &image)?;
+/// labels.sort_unstable();
+/// labels.dedup();
+/// assert_eq!(labels.len(), 24);
+///
+/// // Without iterating, each pixel still joins its nearest seed
+/// let mut labels = slic(24, 10, 60, 40, Some(0), &image)?;
+/// labels.sort_unstable();
+/// labels.dedup();
+/// assert_eq!(labels.len(), 24);
+///
+/// assert!(matches!(
+///     slic(24, 10, 60, 39, None, &image),
+///     Err(simple_clustering::error::ScError::MismatchedSlicBuffer)
+/// ));
+/// # Ok(())
+/// # }
+/// ```
 ///
 /// ## Reference
 ///
@@ -129,41 +303,846 @@ pub fn slic<Wp>(
     iter: Option<u8>,
     image: &[Lab<Wp, f64>],
 ) -> Result<Vec<usize>, ScError> {
-    // Validate input parameters
-    let m = m.clamp(1, 20);
-    let iter = iter.unwrap_or(10);
-    if k == 0 {
-        return Err(ScError::ZeroSuperpixelCount);
+    slic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        iter,
+        &Options::default(),
+        distance_lab,
+        None,
+        None,
+        None,
+        ImageView::new(image, width),
+    )
+    .map(|info| info.labels)
+}
+
+/// Calculate SLIC with a superpixel side length of `s` pixels instead of a
+/// superpixel count.
+///
+/// The seeds are spread over a grid with an interval of `s` and each search
+/// window is `2s x 2s`, so superpixels are roughly `s x s` pixels. As many
+/// seeds are placed as fit on the grid.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::slic_with_interval;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = vec![Lab::<D65, f64>::new(50.0, 10.0, -5.0); 64 * 32];
+/// let mut labels = slic_with_interval(16, 10, 64, 32, None, &image)?;
+/// labels.sort_unstable();
+/// labels.dedup();
+/// assert_eq!(labels.len(), 8);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `s` must not be `0` and `s * s` must not be larger than `width * height`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_with_interval<Wp>(
+    s: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError> {
+    slic_impl(
+        GridSize::Interval(s),
+        m,
+        width,
+        height,
+        iter,
+        &Options::default(),
+        distance_lab,
+        None,
+        None,
+        None,
+        ImageView::new(image, width),
+    )
+    .map(|info| info.labels)
+}
+
+/// Assign each pixel to one of a fixed set of SLIC cluster centers, without
+/// ever updating the centers.
+///
+/// This is the assignment step of SLIC run once against `centers`. Each center
+/// searches a `2S x 2S` window, where `S` is the grid interval for
+/// `centers.len()` superpixels, and pixels join the center with the lowest
+/// SLIC distance. Pixels outside of every window join the nearest center in
+/// the image.
+///
+/// The label of each pixel is the index of its center in `centers`, and
+/// connectivity isn't enforced, so labels keep referring to the same center
+/// across images. Running this on a set of images with the same `centers`
+/// gives every image the same spatial layout, which allows comparing them
+/// region by region. A label can be missing or split into several pieces if
+/// the image content pulls pixels away from its center.
+///
+/// The center colors are used as given. To cluster around fixed positions with
+/// the colors of each image instead, seed with
+/// [`Seeding::Points`](crate::seed::Seeding::Points) and pass `Some(0)` for
+/// the iterations of [`slic_with_options`].
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::{slic_fixed_centers, Superpixel};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let gray = Lab::<D65, f64>::new(50.0, 0.0, 0.0);
+/// let centers = [Superpixel::new(gray, 2, 2), Superpixel::new(gray, 7, 2)];
+/// let labels = slic_fixed_centers(&centers, 10, 10, 5, &[gray; 50])?;
+/// assert_eq!(labels[0], 0);
+/// assert_eq!(labels[9], 1);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `centers` must not be empty, must all lie within the image, and must be
+/// fewer than `width * height`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_fixed_centers<Wp>(
+    centers: &[Superpixel<Lab<Wp, f64>>],
+    m: u8,
+    width: u32,
+    height: u32,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError> {
+    if centers.is_empty() {
+        return Err(ScError::SeedError(SeedErrorKind::EmptySeeds));
+    }
+    let k = u32::try_from(centers.len()).or(Err(ScError::InvalidSuperpixelCount))?;
+    let (s, _) = GridSize::Superpixels(k).resolve(width, height)?;
+    if image.len() != pixel_count(width, height)? {
+        return Err(ScError::MismatchedSlicBuffer);
     }
+    if centers.iter().any(|c| c.x >= width || c.y >= height) {
+        return Err(ScError::SeedError(SeedErrorKind::InvalidImageIndex));
+    }
+
+    let m_s_term = m_div_s(f64::from(m.clamp(1, 20)), f64::from(s));
+    let distance = |color, x: u32, y: u32, center: &Superpixel<Lab<Wp, f64>>| {
+        distance_s(
+            m_s_term,
+            distance_lab(color, center.data),
+            distance_xy(
+                (f64::from(x), f64::from(y)),
+                (f64::from(center.x), f64::from(center.y)),
+            ),
+        )
+    };
+
+    let mut info = SlicInfo::<f64, usize>::new();
+    info.distances.try_reserve_exact(image.len())?;
+    info.labels.try_reserve_exact(image.len())?;
+    info.distances
+        .extend((0..image.len()).map(|_| f64::INFINITY));
+    info.labels.extend((0..image.len()).map(|_| 0));
 
-    if width == 0 || height == 0 {
-        return Err(ScError::InvalidImageDimension);
+    for (center_index, center) in centers.iter().enumerate() {
+        let x_start = center.x.saturating_sub(s);
+        let x_end = center.x.saturating_add(s).min(width);
+        for y in center.y.saturating_sub(s)..center.y.saturating_add(s).min(height) {
+            let row_start = index(width, x_start, y)?;
+            for (x, idx) in (x_start..x_end).zip(row_start..) {
+                let (Some(&color), Some(best)) = (image.get(idx), info.distances.get_mut(idx))
+                else {
+                    continue;
+                };
+                let d = distance(color, x, y, center);
+                if d < *best {
+                    *best = d;
+                    if let Some(label) = info.labels.get_mut(idx) {
+                        *label = center_index;
+                    }
+                }
+            }
+        }
     }
 
-    match u64::from(k).cmp(&(u64::from(width) * u64::from(height))) {
-        std::cmp::Ordering::Less => {}
-        std::cmp::Ordering::Equal | std::cmp::Ordering::Greater => {
-            return Err(ScError::InvalidSuperpixelCount);
+    // Fall back to searching every center for pixels outside of all windows
+    let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+    for (idx, (label, &best)) in info.labels.iter_mut().zip(&info.distances).enumerate() {
+        if best.is_finite() {
+            continue;
+        }
+        let (x, y) = index_to_xy(idx, width_usize)?;
+        let &color = image.get(idx).ok_or(ScError::IndexOverflow { x, y })?;
+        if let Some((nearest, _)) = centers
+            .iter()
+            .map(|center| distance(color, x, y, center))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+        {
+            *label = nearest;
         }
     }
 
-    // Calculate S
-    let s = calculate_grid_interval(width, height, k)
-        .to_u32()
-        .ok_or(ScError::InvalidGridInterval)?;
+    Ok(info.labels)
+}
+
+/// Calculate SLIC starting from existing cluster centers, such as those of a
+/// previous segmentation from
+/// [`centers_from_labels`](crate::seed::centers_from_labels).
+///
+/// Unlike [`Seeding::Points`](crate::seed::Seeding::Points), the centers keep
+/// their colors, so the clustering resumes where the previous one stopped and
+/// a few iterations are enough to refine it. [`Options::seeding`] and
+/// [`Options::perturb`] are ignored, and the grid interval `S` is derived from
+/// the number of centers. Use [`slic_fixed_centers`] to assign the pixels
+/// without moving the centers.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::{options::Options, seed::centers_from_labels, slic_from_centers};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let (dark, light) = (Lab::<D65, f64>::new(20.0, 0.0, 0.0), Lab::new(80.0, 0.0, 0.0));
+/// let image: Vec<_> = (0..8 * 4).map(|i| if i % 8 < 4 { dark } else { light }).collect();
+///
+/// // A rough split in the wrong place is moved onto the color edge
+/// let labels: Vec<_> = (0..8 * 4).map(|i| usize::from(i % 8 >= 3)).collect();
+/// let centers = centers_from_labels(&labels, &image, 8, 4)?;
+/// let labels = slic_from_centers(&centers, 10, 8, 4, Some(3), &Options::default(), &image)?;
+/// assert!(labels.iter().enumerate().all(|(i, &label)| label == usize::from(i % 8 >= 4)));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest center without updating the centers.
+///
+/// `centers` must not be empty, must all lie within the image, and must be
+/// fewer than `width * height`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_from_centers<Wp>(
+    centers: &[Superpixel<Lab<Wp, f64>>],
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    if centers.is_empty() {
+        return Err(ScError::SeedError(SeedErrorKind::EmptySeeds));
+    }
+    if centers.iter().any(|c| c.x >= width || c.y >= height) {
+        return Err(ScError::SeedError(SeedErrorKind::InvalidImageIndex));
+    }
+    let k = u32::try_from(centers.len()).or(Err(ScError::InvalidSuperpixelCount))?;
+
+    let measure = options.color_distance;
+    slic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        iter,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        Some(centers),
+        None,
+        ImageView::new(image, width),
+    )
+    .map(|info| info.labels)
+}
+
+/// Compactness values tried by [`auto_m`].
+const AUTO_M_CANDIDATES: [u8; 5] = [1, 2, 5, 10, 20];
+
+/// Choose the compactness `m` for [`slic`] that scores best on `objective`.
+///
+/// SLIC is run once for each of the candidates `1`, `2`, `5`, `10`, and `20`,
+/// so this is about five times slower than a single segmentation. Ties go to
+/// the larger `m`, which gives more regular superpixels for the same score.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::{auto_m, image::boundary_recall, options::Objective, slic};
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// // Dark left third and bright right two thirds
+/// let (width, height) = (30, 20);
+/// let image: Vec<_> = (0..width * height)
+///     .map(|i| Lab::<D65, f64>::new(if i % width < 10 { 20.0 } else { 80.0 }, 0.0, 0.0))
+///     .collect();
+/// let edges: Vec<_> = (0..width * height).map(|i| i % width == 10).collect();
+///
+/// let objective = Objective::BoundaryRecall { edges: edges.clone(), tolerance: 1 };
+/// let m = auto_m(6, width, height, &image, &objective)?;
+/// // Every candidate finds the edge, so the tie goes to the largest `m`
+/// assert_eq!(m, 20);
+/// let labels = slic(6, m, width, height, None, &image)?;
+/// assert_eq!(boundary_recall(width, height, &labels, &edges, 1)?, 1.0);
+///
+/// // An edge map without edges is rejected before segmenting
+/// let objective = Objective::BoundaryRecall { edges: vec![false; 600], tolerance: 1 };
+/// assert!(auto_m(6, width, height, &image, &objective).is_err());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `k` must not be `0`.
+/// `width` and `height` must not be `0`. An edge map of
+/// [`Objective::BoundaryRecall`] must have a length of `width * height` and at
+/// least one edge pixel.
+pub fn auto_m<Wp>(
+    k: u32,
+    width: u32,
+    height: u32,
+    image: &[Lab<Wp, f64>],
+    objective: &Objective,
+) -> Result<u8, ScError> {
+    // Reject an unusable edge map before running any of the segmentations
+    if let Objective::BoundaryRecall { edges, .. } = objective {
+        if edges.len() != pixel_count(width, height)? {
+            return Err(ScError::General(
+                "Edge buffer does not match image dimensions",
+            ));
+        }
+        if !edges.contains(&true) {
+            return Err(ScError::General("Edge map has no edge pixels"));
+        }
+    }
 
-    if s == 0 {
-        return Err(ScError::ZeroGridInterval);
+    let mut best = None;
+    for m in AUTO_M_CANDIDATES {
+        let labels = slic(k, m, width, height, None, image)?;
+        let score = match objective {
+            Objective::BoundaryRecall { edges, tolerance } => {
+                boundary_recall(width, height, &labels, edges, *tolerance)?
+            }
+            Objective::ColorVariance => {
+                let stats = region_stats(width, height, &labels, image)?;
+                #[allow(clippy::cast_precision_loss)]
+                let variance = stats
+                    .values()
+                    .map(|region| region.count as f64 * region.variance)
+                    .sum::<f64>()
+                    / image.len() as f64;
+                -variance
+            }
+        };
+        if best.filter(|&(_, best_score)| score < best_score).is_none() {
+            best = Some((m, score));
+        }
+    }
+
+    best.map(|(m, _)| m)
+        .ok_or(ScError::General("No compactness candidates"))
+}
+
+/// Calculate SLIC with additional [`Options`].
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_with_options<Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let measure = options.color_distance;
+    slic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        iter,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        None,
+        None,
+        ImageView::new(image, width),
+    )
+    .map(|info| info.labels)
+}
+
+/// Calculate SLIC with additional [`Options`] on an owned or borrowed image.
+///
+/// `image` can be a `Vec`, which is consumed, or a slice, which is borrowed.
+/// This is for generic code that stores its inputs either way. The result is
+/// the same as [`slic_with_options`].
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// use simple_clustering::{options::Options, slic_cow};
+///
+/// let image = vec![palette::Lab::<palette::white_point::D65, f64>::default(); 64];
+/// let borrowed = slic_cow(4, 10, 8, 8, None, &Options::new(), image.as_slice())?;
+/// let owned = slic_cow(4, 10, 8, 8, None, &Options::new(), image)?;
+/// assert_eq!(borrowed, owned);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_cow<'a, Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    image: impl Into<Cow<'a, [Lab<Wp, f64>]>>,
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    slic_with_options(k, m, width, height, iter, options, &image.into())
+}
+
+/// Calculate SLIC with additional [`Options`] on the `(x, y, width, height)`
+/// rectangle `roi` of a larger image whose rows are `stride` pixels long.
+///
+/// The region is segmented in place without copying it, so a viewport of a
+/// large image can be segmented directly. The labels and all coordinates of
+/// the result are relative to the region, and the returned labels have
+/// `width * height` elements. The result is the same as segmenting a cropped
+/// copy of the region.
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::{options::Options, slic_roi, slic_with_options};
+///
+/// let image: Vec<Lab<D65, f64>> = (0..48)
+///     .flat_map(|y| (0..64).map(move |x| Lab::new(f64::from(x), f64::from(y), 0.0)))
+///     .collect();
+/// let roi = (8, 4, 32, 24);
+/// let labels = slic_roi(16, 10, 64, roi, None, &Options::new(), &image)?;
+///
+/// let crop: Vec<_> = image
+///     .chunks_exact(64)
+///     .skip(4)
+///     .take(24)
+///     .flat_map(|row| &row[8..40])
+///     .copied()
+///     .collect();
+/// let expected = slic_with_options(16, 10, 32, 24, None, &Options::new(), &crop)?;
+/// assert_eq!(labels, expected);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// The region must not be empty and must lie inside of the image.
+pub fn slic_roi<Wp>(
+    k: u32,
+    m: u8,
+    stride: u32,
+    roi: (u32, u32, u32, u32),
+    iter: Option<u8>,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let (_, _, width, height) = roi;
+    let measure = options.color_distance;
+    slic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        iter,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        None,
+        None,
+        ImageView::with_roi(image, stride, roi)?,
+    )
+    .map(|info| info.labels)
+}
+
+/// Calculate SLIC with additional [`Options`], returning the labels as a
+/// narrower unsigned integer type such as `u16` or `u32`.
+///
+/// A `u16` label map takes a quarter of the memory of `usize` labels on 64-bit
+/// targets, which adds up when storing the labels of many large images. The
+/// labels are narrowed after segmentation, so the peak memory use while
+/// segmenting is unchanged. Returns [`ScError::Overflow`] if a label doesn't
+/// fit in `T`.
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// # let (width, height) = (4, 4);
+/// # let image = vec![palette::Lab::<palette::white_point::D65, f64>::default(); 16];
+/// use simple_clustering::{options::Options, slic_packed};
+///
+/// let labels: Vec<u16> = slic_packed(4, 10, width, height, None, &Options::new(), &image)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_packed<Wp, T>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<T>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+    T: TryFrom<usize> + Unsigned,
+{
+    let labels = slic_with_options(k, m, width, height, iter, options, image)?;
+    pack_labels(&labels)
+}
+
+/// Calculate SLIC with additional [`Options`], also returning the distance of
+/// each pixel to its nearest superpixel cluster as `(labels, distances)`.
+///
+/// The distances can be used as a per-pixel confidence map, where larger
+/// values indicate a poorer fit. Each distance is the squared measure used
+/// during clustering, `d_lab + (m / S)^2 * d_xy`, where `d_lab` is the squared
+/// color distance and `d_xy` is the squared spatial distance in pixels.
+///
+/// The distances are not reset between iterations, so each value is the
+/// smallest distance found for the pixel over all iterations. It can be the
+/// distance to a center's position and color in an earlier iteration, since
+/// the pixel only changes clusters when a closer center is found. The values
+/// are taken before connectivity is enforced, so pixels relabeled to merge
+/// stray regions keep the distance to their original cluster.
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_with_distances<Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<(Vec<usize>, Vec<f64>), ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let measure = options.color_distance;
+    slic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        iter,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        None,
+        None,
+        ImageView::new(image, width),
+    )
+    .map(|info| (info.labels, info.distances))
+}
+
+/// Calculate SLIC with additional [`Options`] on features that implement
+/// [`AsFeature3`], such as plain `[f64; 3]` arrays.
+///
+/// Features are compared with [`AsFeature3::distance`] instead of
+/// [`Options::color_distance`], so they can be in any color space where the
+/// Euclidean distance is meaningful.
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::{options::Options, slic, slic_features};
+///
+/// let features: Vec<[f64; 3]> = (0..64)
+///     .map(|i| [f64::from(i % 8) * 10.0, f64::from(i / 8) * 5.0, 0.0])
+///     .collect();
+/// let labels = slic_features(4, 10, 8, 8, None, &Options::new(), &features)?;
+///
+/// // The features are segmented like `Lab` colors with the same components
+/// let lab: Vec<Lab<D65, f64>> = features.iter().map(|&f| Lab::from(f)).collect();
+/// assert_eq!(labels, slic(4, 10, 8, 8, None, &lab)?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn slic_features<T: AsFeature3>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    image: &[T],
+) -> Result<Vec<usize>, ScError> {
+    slic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        iter,
+        options,
+        |lhs, rhs| T::from_array(lhs.into()).distance(T::from_array(rhs.into())),
+        None,
+        None,
+        None,
+        ImageView::new(T::as_lab_slice(image), width),
+    )
+    .map(|info| info.labels)
+}
+
+/// Calculate SLIC with additional [`Options`], weighting the influence of each
+/// pixel on the cluster centers by an importance map such as a saliency or
+/// focus map.
+///
+/// `weights` has one non-negative weight per pixel in row-major order. Each
+/// center is updated to the weighted mean color and position of its pixels,
+/// so superpixels conform more closely to high-weight regions. Uniform weights
+/// give the same result as [`slic_with_options`]. A cluster whose pixels all
+/// have a weight of `0` keeps its previous center, and isn't moved by
+/// [`Options::reseed_empty`] since it still has pixels.
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// # let (width, height) = (8, 8);
+/// # let image = vec![palette::Lab::<palette::white_point::D65, f64>::default(); 64];
+/// use simple_clustering::{options::Options, slic_with_options, slic_with_weights};
+///
+/// let weights = vec![1.0; 64];
+/// let labels = slic_with_weights(4, 10, width, height, None, &Options::new(), &weights, &image)?;
+/// assert_eq!(labels, slic_with_options(4, 10, width, height, None, &Options::new(), &image)?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+/// `weights` must have `width * height` elements and be finite.
+#[allow(clippy::too_many_arguments)]
+pub fn slic_with_weights<Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    weights: &[f64],
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let measure = options.color_distance;
+    slic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        iter,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        Some(weights),
+        None,
+        None,
+        ImageView::new(image, width),
+    )
+    .map(|info| info.labels)
+}
+
+/// Calculate SLIC with additional [`Options`], recording the time spent in
+/// each stage of the algorithm in `timings`.
+///
+/// The labels are the same as those of [`slic_with_options`]. The clock is
+/// only read by this function, so the other entry points have no timing
+/// overhead.
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// # let (width, height) = (8, 8);
+/// # let image = vec![palette::Lab::<palette::white_point::D65, f64>::default(); 64];
+/// use simple_clustering::{options::Options, slic_with_timings, Timings};
+///
+/// let mut timings = Timings::default();
+/// let _ = slic_with_timings(4, 10, width, height, None, &Options::new(), &image, &mut timings)?;
+/// let total = timings.smoothing + timings.seeding + timings.clustering + timings.connectivity;
+/// println!("Clustering took {:?} of {:?}", timings.clustering, total);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `iter` will default to `10` if `None` is supplied. `Some(0)` assigns each
+/// pixel to its nearest seed without updating the centers.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+#[allow(clippy::too_many_arguments)]
+pub fn slic_with_timings<Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+    timings: &mut Timings,
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let measure = options.color_distance;
+    slic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        iter,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        None,
+        Some(timings),
+        ImageView::new(image, width),
+    )
+    .map(|info| info.labels)
+}
+
+/// SLIC implementation using `color_distance` to compare colors.
+#[allow(clippy::too_many_arguments)]
+fn slic_impl<Wp, F>(
+    size: GridSize,
+    m: u8,
+    width: u32,
+    height: u32,
+    iter: Option<u8>,
+    options: &Options,
+    color_distance: F,
+    weights: Option<&[f64]>,
+    centers: Option<&[Superpixel<Lab<Wp, f64>>]>,
+    mut timings: Option<&mut Timings>,
+    image: ImageView<'_, Lab<Wp, f64>>,
+) -> Result<SlicInfo<f64, usize>, ScError>
+where
+    F: Fn(Lab<Wp, f64>, Lab<Wp, f64>) -> f64,
+{
+    // Validate input parameters
+    let m = m.clamp(1, 20);
+    let iter = iter.unwrap_or(10);
+    let (s, k) = size.resolve(width, height)?;
+    if centers.is_none() {
+        check_superpixel_limit(size, width, height, options)?;
+    }
+    if image.len() != pixel_count(width, height)? {
+        return Err(ScError::MismatchedSlicBuffer);
+    }
+    if !(options.search_window.is_finite() && options.search_window > 0.0) {
+        return Err(ScError::General(
+            "Search window must be positive and finite",
+        ));
+    }
+    if options.stride == 0 {
+        return Err(ScError::General("Stride must not be 0"));
+    }
+    if !(0.0..=1.0).contains(&options.grid_lock) {
+        return Err(ScError::General("Grid lock must be between 0 and 1"));
     }
 
     let m_s_term = m_div_s(f64::from(m), f64::from(s));
 
-    // Init seeds and shuffle them to a hopefully non-noisy pixel
-    let mut clusters = Vec::new();
-    init_seeds(width, height, s, k, image, &mut clusters)?;
+    if let Some(weights) = weights {
+        if weights.len() != pixel_count(width, height)? {
+            return Err(ScError::General(
+                "Weight buffer does not match image dimensions",
+            ));
+        }
+        if !weights.iter().all(|w| w.is_finite() && *w >= 0.0) {
+            return Err(ScError::General("Weights must be non-negative and finite"));
+        }
+    }
 
-    for seed in &mut clusters {
-        perturb(seed, i64::from(width), i64::from(height), image)?;
+    let mut stopwatch = Stopwatch::new(timings.is_some());
+
+    // Cluster a smoothed copy of the image if requested
+    let smoothed = pre_smooth(image, options.pre_smooth)?;
+    let image = smoothed
+        .as_deref()
+        .map_or(image, |smoothed| ImageView::new(smoothed, width));
+    if let Some(timings) = timings.as_deref_mut() {
+        timings.smoothing = stopwatch.split();
+    }
+
+    // Init seeds and shuffle them to a hopefully non-noisy pixel, unless the
+    // clustering resumes from supplied centers
+    let mut clusters = Vec::new();
+    if let Some(centers) = centers {
+        clusters.try_reserve_exact(centers.len())?;
+        clusters.extend_from_slice(centers);
+    } else {
+        place_seeds(width, height, s, k, options, image, &mut clusters)?;
+    }
+    let mut edge_regions = edge_regions(width, height, options, image, &mut clusters)?;
+    if let Some(timings) = timings.as_deref_mut() {
+        timings.seeding = stopwatch.split();
     }
 
     // Bookkeeping for tracking pixel clusters and updating cluster centers
@@ -178,29 +1157,78 @@ pub fn slic<Wp>(
     updates.try_reserve_exact(clusters.len())?;
     updates.extend((0..clusters.len()).map(|_| SlicUpdate::new()));
 
-    for _ in 0..iter {
-        // Search a pixel area of 2S x 2S size and match cluster centers to
-        // pixels with the lowest distance measure
+    // Indices of clusters without pixels, and scratch space for reseeding them
+    let mut empty = Vec::new();
+    empty.try_reserve_exact(clusters.len())?;
+    let mut worst = Vec::new();
+
+    // Distance from a cluster center to the edge of its search window
+    let reach = (0.5 * options.search_window * f64::from(s))
+        .round()
+        .to_u32()
+        .ok_or(ScError::Overflow("search window"))?
+        .max(1);
+
+    // Only pixels on a grid with this spacing are assigned while iterating
+    let stride = options.stride;
+    let stride_usize = usize::try_from(stride).or(Err(ScError::Overflow("stride")))?;
+
+    // Centers are pulled back toward where they were seeded by this fraction
+    let grid_lock = options.grid_lock;
+    let mut origins = Vec::new();
+    if grid_lock > 0.0 {
+        origins.try_reserve_exact(clusters.len())?;
+        origins.extend(clusters.iter().map(|c| (f64::from(c.x), f64::from(c.y))));
+    }
+    let align = |v: u32| v.div_ceil(stride).saturating_mul(stride);
+
+    // With 0 iterations, pixels are assigned once to their nearest seed
+    for iteration in 0..iter.max(1) {
+        // Search a pixel area of 2S x 2S size, or the configured window, and
+        // match cluster centers to pixels with the lowest distance measure
         for (center_index, center) in clusters.iter().enumerate() {
-            for y in center.y.saturating_sub(s)..center.y.saturating_add(s).min(height) {
-                let x_start = center.x.saturating_sub(s);
-                let x_end = center.x.saturating_add(s).min(width);
-                let row_start = u64::from(y).saturating_mul(u64::from(width));
+            for y in (align(center.y.saturating_sub(reach))
+                ..center.y.saturating_add(reach).min(height))
+                .step_by(stride_usize)
+            {
+                if options.wrap_x {
+                    assign_wrapped_row(
+                        width,
+                        y,
+                        reach,
+                        center_index,
+                        center,
+                        m_s_term,
+                        stride,
+                        &color_distance,
+                        edge_regions.as_ref(),
+                        image,
+                        &mut info,
+                    )?;
+                    continue;
+                }
+
+                let x_start = align(center.x.saturating_sub(reach));
+                let x_end = center.x.saturating_add(reach).min(width);
+                let row_start = index(width, x_start, y)?;
 
                 // (2023/01)WOULDBENICE: Try chunks_exact, attempted it here but clusters
                 // had worse results compared to current version indicating probable errors
                 // in implementation
-                for (x, idx) in (x_start..x_end).zip(
-                    row_start.saturating_add(u64::from(center.x.saturating_sub(s)))
-                        ..row_start.saturating_add(u64::from(x_end)),
-                ) {
-                    let idx = usize::try_from(idx)
-                        .or(Err("Index out of bounds for finding new neighbors"))?;
-                    if idx < image.len() && idx < info.distances.len() && idx < info.labels.len() {
-                        let color = image[idx];
+                for (x, idx) in (x_start..x_end)
+                    .step_by(stride_usize)
+                    .zip((row_start..).step_by(stride_usize))
+                {
+                    let Some(&color) = image.get(i64::from(x), i64::from(y)) else {
+                        continue;
+                    };
+                    if !edges::allows(edge_regions.as_ref(), idx, center_index) {
+                        continue;
+                    }
+                    if idx < info.distances.len() && idx < info.labels.len() {
                         let distance = distance_s(
                             m_s_term,
-                            distance_lab(color, center.data),
+                            color_distance(color, center.data),
                             distance_xy(
                                 (f64::from(x), f64::from(y)),
                                 (f64::from(center.x), f64::from(center.y)),
@@ -216,70 +1244,413 @@ pub fn slic<Wp>(
             }
         }
 
+        if iter == 0 {
+            break;
+        }
+
         // Compute new centers and update
         let width_usize = usize::try_from(width).or(Err("Could not convert width to usize"))?;
+        let width_f = f64::from(width);
         for (y, (row, info_labels)) in image
-            .chunks_exact(width_usize)
+            .rows()
             .zip(info.labels.chunks_exact(width_usize))
             .enumerate()
+            .step_by(stride_usize)
         {
+            let row_weights = weights.and_then(|weights| weights.chunks_exact(width_usize).nth(y));
             #[allow(clippy::cast_precision_loss)]
-            for (x, (&color, &info_label)) in row.iter().zip(info_labels).enumerate() {
+            for (x, (&color, &info_label)) in row
+                .iter()
+                .zip(info_labels)
+                .enumerate()
+                .step_by(stride_usize)
+            {
                 if let Some(update) = updates.get_mut(info_label) {
-                    update.data += color;
-                    update.x += x as f64;
-                    update.y += y as f64;
-                    update.count += 1.0;
+                    let weight = row_weights.and_then(|w| w.get(x)).copied().unwrap_or(1.0);
+                    update.data += color * weight;
+                    update.x += weight
+                        * match clusters.get(info_label) {
+                            // Accumulate x on the side of the seam nearest to the center
+                            Some(center) if options.wrap_x => {
+                                unwrap_x(x as f64, f64::from(center.x), width_f)
+                            }
+                            _ => x as f64,
+                        };
+                    update.y += weight * y as f64;
+                    update.count += weight;
+                    update.assigned = true;
                 }
             }
         }
 
-        for (update, center) in updates.iter_mut().zip(&mut clusters) {
+        empty.clear();
+        for (index, (update, center)) in updates.iter_mut().zip(&mut clusters).enumerate() {
             if update.count == 0.0 {
+                // A cluster whose pixels all have a weight of `0` isn't empty
+                // and keeps its center
+                if !update.assigned {
+                    empty.push(index);
+                }
+                *update = SlicUpdate::new();
                 continue;
             }
             center.data = update.data / update.count;
-            center.x = (update.x / update.count)
-                .to_u32()
-                .ok_or("Update X out of bounds")?;
-            center.y = (update.y / update.count)
-                .to_u32()
-                .ok_or("Update Y out of bounds")?;
+            let mut x = update.x / update.count;
+            let mut y = update.y / update.count;
+            if let Some(&(origin_x, origin_y)) = origins.get(index) {
+                let origin_x = if options.wrap_x {
+                    unwrap_x(origin_x, x, width_f)
+                } else {
+                    origin_x
+                };
+                x += grid_lock * (origin_x - x);
+                y += grid_lock * (origin_y - y);
+            }
+            let x = if options.wrap_x {
+                wrap_coordinate(x, width_f)
+            } else {
+                x
+            };
+            center.x = x.to_u32().ok_or("Update X out of bounds")?;
+            center.y = y.to_u32().ok_or("Update Y out of bounds")?;
             *update = SlicUpdate::new();
         }
+
+        // Reseeding after the last iteration would have no effect
+        if options.reseed_empty && iteration.saturating_add(1) < iter {
+            reseed_empty(
+                width_usize,
+                s,
+                stride_usize,
+                &empty,
+                image,
+                &info.distances,
+                &mut clusters,
+                &mut worst,
+            )?;
+            // Reseeded clusters take pixels from the region they moved to and
+            // are locked to their new position
+            for &index in &empty {
+                let Some(center) = clusters.get(index) else {
+                    continue;
+                };
+                if let Some(regions) = edge_regions.as_mut() {
+                    regions.reassign_cluster(index, center.x, center.y);
+                }
+                if let Some(origin) = origins.get_mut(index) {
+                    *origin = (f64::from(center.x), f64::from(center.y));
+                }
+            }
+        }
     }
 
-    enforce_connectivity(width, height, s, &mut info.labels)?;
+    if stride > 1 {
+        fill_skipped(
+            width,
+            height,
+            stride,
+            options.wrap_x,
+            m_s_term,
+            &color_distance,
+            edge_regions.as_ref(),
+            image,
+            &clusters,
+            &mut info,
+        )?;
+    }
+    if let Some(timings) = timings.as_deref_mut() {
+        timings.clustering = stopwatch.split();
+    }
 
-    Ok(info.labels)
+    enforce_connectivity(
+        width,
+        height,
+        s,
+        options.wrap_x,
+        options.connectivity,
+        image,
+        &color_distance,
+        &mut info.labels,
+    )?;
+    if let Some(timings) = timings {
+        timings.connectivity = stopwatch.split();
+    }
+    check_superpixel_count(k, &info.labels, options.count_tolerance)?;
+
+    Ok(info)
+}
+
+/// Move the centers of the `empty` clusters onto the pixels that fit their
+/// current clusters worst, one pixel for each cluster, so they can claim pixels
+/// in the next iteration. The new centers are kept at least `s` pixels apart
+/// so they don't compete for the same area. Only the pixels sampled with
+/// `stride` are considered.
+#[allow(clippy::too_many_arguments)]
+fn reseed_empty<Wp>(
+    width: usize,
+    s: u32,
+    stride: usize,
+    empty: &[usize],
+    image: ImageView<'_, Lab<Wp, f64>>,
+    distances: &[f64],
+    clusters: &mut [Superpixel<Lab<Wp, f64>>],
+    worst: &mut Vec<usize>,
+) -> Result<(), ScError> {
+    if empty.is_empty() || width == 0 {
+        return Ok(());
+    }
+
+    worst.clear();
+    worst.try_reserve_exact(distances.len())?;
+    worst.extend((0..distances.len()).filter(|idx| {
+        (idx % width).is_multiple_of(stride) && (idx / width).is_multiple_of(stride)
+    }));
+
+    // Pixels from largest to smallest distance, unreached pixels with an
+    // infinite distance come first. Ties are broken by index so the order is
+    // the same as fully sorting.
+    let worst_first =
+        |&a: &usize, &b: &usize| distances[b].total_cmp(&distances[a]).then(a.cmp(&b));
+
+    // Only the worst pixels are needed, so sort them in batches starting with
+    // one pixel per empty cluster. A batch is doubled whenever pixels were
+    // skipped for being too close to a new center.
+    let mut empty_iter = empty.iter().peekable();
+    let mut reseeded: Vec<(u32, u32)> = Vec::new();
+    reseeded.try_reserve_exact(empty.len())?;
+    let (mut start, mut batch) = (0, empty.len());
+    while start < worst.len() && empty_iter.peek().is_some() {
+        let end = start.saturating_add(batch).min(worst.len());
+        let rest = worst.get_mut(start..).ok_or("Reseed batch out of bounds")?;
+        let len = end - start;
+        if len < rest.len() {
+            let _ = rest.select_nth_unstable_by(len - 1, worst_first);
+        }
+        let candidates = rest.get_mut(..len).ok_or("Reseed batch out of bounds")?;
+        candidates.sort_unstable_by(worst_first);
+
+        for &idx in candidates.iter() {
+            let (x, y) = index_to_xy(idx, width)?;
+            if reseeded
+                .iter()
+                .any(|&(rx, ry)| rx.abs_diff(x) < s && ry.abs_diff(y) < s)
+            {
+                continue;
+            }
+
+            let Some(&cluster_index) = empty_iter.next() else {
+                break;
+            };
+            let cluster = clusters
+                .get_mut(cluster_index)
+                .ok_or("Cluster index out of bounds")?;
+            cluster.data = *image
+                .get(i64::from(x), i64::from(y))
+                .ok_or("Reseed index out of bounds")?;
+            cluster.x = x;
+            cluster.y = y;
+            reseeded.push((x, y));
+        }
+
+        start = end;
+        batch = batch.saturating_mul(2);
+    }
+
+    Ok(())
 }
 
-// Relabel disjoint labels to the largest, nearest neighbor cluster.
-fn enforce_connectivity(
+/// Assign the pixels skipped by sampling with `stride` to the closest of the
+/// clusters of the up to four sampled pixels surrounding them.
+#[allow(clippy::too_many_arguments)]
+fn fill_skipped<Wp, F>(
+    width: u32,
+    height: u32,
+    stride: u32,
+    wrap: bool,
+    m_s_term: f64,
+    color_distance: &F,
+    edge_regions: Option<&EdgeRegions>,
+    image: ImageView<'_, Lab<Wp, f64>>,
+    clusters: &[Superpixel<Lab<Wp, f64>>],
+    info: &mut SlicInfo<f64, usize>,
+) -> Result<(), ScError>
+where
+    F: Fn(Lab<Wp, f64>, Lab<Wp, f64>) -> f64,
+{
+    let width_f = f64::from(width);
+
+    for y in 0..height {
+        for x in 0..width {
+            if x.is_multiple_of(stride) && y.is_multiple_of(stride) {
+                continue;
+            }
+            let idx = index(width, x, y)?;
+            let &color = image
+                .get(i64::from(x), i64::from(y))
+                .ok_or("Skipped pixel out of bounds")?;
+
+            let (x_0, y_0) = (x - x % stride, y - y % stride);
+            let (x_1, y_1) = (x_0.saturating_add(stride), y_0.saturating_add(stride));
+            // The first column is also the next sampled column across the seam
+            let x_1 = if wrap && x_1 >= width { 0 } else { x_1 };
+
+            let mut nearest: Option<(f64, usize)> = None;
+            for (s_x, s_y) in [(x_0, y_0), (x_1, y_0), (x_0, y_1), (x_1, y_1)] {
+                if s_x >= width || s_y >= height {
+                    continue;
+                }
+                let s_idx = index(width, s_x, s_y)?;
+                // Sampled pixels outside of every search window have no cluster
+                if !info.distances.get(s_idx).is_some_and(|d| d.is_finite()) {
+                    continue;
+                }
+                let label = *info
+                    .labels
+                    .get(s_idx)
+                    .ok_or("Sampled label out of bounds")?;
+                let Some(center) = clusters.get(label) else {
+                    continue;
+                };
+                if !edges::allows(edge_regions, idx, label) {
+                    continue;
+                }
+                let x_f = if wrap {
+                    unwrap_x(f64::from(x), f64::from(center.x), width_f)
+                } else {
+                    f64::from(x)
+                };
+                let distance = distance_s(
+                    m_s_term,
+                    color_distance(color, center.data),
+                    distance_xy(
+                        (x_f, f64::from(y)),
+                        (f64::from(center.x), f64::from(center.y)),
+                    ),
+                );
+                if nearest.filter(|&(d, _)| d <= distance).is_none() {
+                    nearest = Some((distance, label));
+                }
+            }
+
+            if let Some((distance, label)) = nearest {
+                if let (Some(d), Some(l)) = (info.distances.get_mut(idx), info.labels.get_mut(idx))
+                {
+                    *d = distance;
+                    *l = label;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Assign the pixels of row `y` within `reach` of `center` when the image wraps
+/// around horizontally.
+#[allow(clippy::too_many_arguments)]
+fn assign_wrapped_row<Wp, F>(
+    width: u32,
+    y: u32,
+    reach: u32,
+    center_index: usize,
+    center: &Superpixel<Lab<Wp, f64>>,
+    m_s_term: f64,
+    stride: u32,
+    color_distance: &F,
+    edge_regions: Option<&EdgeRegions>,
+    image: ImageView<'_, Lab<Wp, f64>>,
+    info: &mut SlicInfo<f64, usize>,
+) -> Result<(), ScError>
+where
+    F: Fn(Lab<Wp, f64>, Lab<Wp, f64>) -> f64,
+{
+    let width_i = i64::from(width);
+
+    // Visit each column at most once if the search area spans the whole width
+    let (offset_start, offset_end) = if 2 * i64::from(reach) > width_i {
+        (-width_i / 2, width_i - width_i / 2)
+    } else {
+        (-i64::from(reach), i64::from(reach))
+    };
+
+    for x_offset in offset_start..offset_end {
+        let x = wrap_x(width_i, i64::from(center.x) + x_offset, true);
+        if x % i64::from(stride) != 0 {
+            continue;
+        }
+        let (x_u32, _) = checked_coords(x, i64::from(y))?;
+        let idx = index(width, x_u32, y)?;
+        let Some(&color) = image.get(x, i64::from(y)) else {
+            continue;
+        };
+        if !edges::allows(edge_regions, idx, center_index) {
+            continue;
+        }
+        if idx < info.distances.len() && idx < info.labels.len() {
+            // Offsets never exceed half of the width, so they're already the
+            // shortest horizontal distance across the seam
+            #[allow(clippy::cast_precision_loss)]
+            let distance = distance_s(
+                m_s_term,
+                color_distance(color, center.data),
+                distance_xy((x_offset as f64, f64::from(y)), (0.0, f64::from(center.y))),
+            );
+
+            if distance < info.distances[idx] {
+                info.distances[idx] = distance;
+                info.labels[idx] = center_index;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Relabel disjoint labels to the bordering cluster closest in mean color.
+#[allow(clippy::too_many_arguments)]
+fn enforce_connectivity<Wp, F>(
     width: u32,
     height: u32,
     s: u32,
+    wrap: bool,
+    connectivity: Connectivity,
+    image: ImageView<'_, Lab<Wp, f64>>,
+    color_distance: &F,
     labels: &mut [usize],
-) -> Result<(), ScError> {
+) -> Result<(), ScError>
+where
+    F: Fn(Lab<Wp, f64>, Lab<Wp, f64>) -> f64,
+{
     let width_i = i64::from(width);
     let height_i = i64::from(height);
-    let cluster_threshold =
-        usize::try_from(u64::from(s).pow(2) / 4).or(Err("Could not convert cluster threshold"))?;
+    let cluster_threshold = usize::try_from(u64::from(s).pow(2) / 4)
+        .or(Err(ScError::Overflow("connectivity cluster threshold")))?;
     let mut new_labels = Vec::new();
     new_labels.try_reserve_exact(labels.len())?;
     new_labels.extend((0..labels.len()).map(|_| usize::MAX));
     let new_labels = new_labels.as_mut_slice();
 
     // This will be reused for searching each superpixel cluster.
-    // For now, the size of the queue is 8 superpixels to start.
+    // For now, the size of the queue is 8 superpixels to start, but never more
+    // than the whole image.
     let mut label_queue = Vec::new();
     label_queue.try_reserve(
-        usize::try_from(u64::from(s).pow(2).saturating_mul(8))
-            .or(Err("Could not calculate label set size"))?,
+        u64::from(s)
+            .pow(2)
+            .checked_mul(8)
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or(ScError::Overflow("connectivity queue size"))?
+            .min(labels.len()),
     )?;
 
-    // Adjacent pixels, clockwise order West-North-East-South
-    let neighbors = [(-1, 0), (0, -1), (1, 0), (0, 1)];
+    // Adjacent pixels, clockwise order West-North-East-South. Clusters are
+    // searched with the requested connectivity.
+    let neighbors = Connectivity::Four.offsets();
+    let cluster_neighbors = connectivity.offsets();
+
+    // Color sum and pixel count of each new label, used to merge small clusters
+    // into the bordering cluster with the closest mean color
+    let mut label_sums: Vec<(Lab<Wp, f64>, f64)> = Vec::new();
 
     // Assign new labels to pixels by finding connected pixel clusters
     let mut neighbor_label = 0;
@@ -288,9 +1659,9 @@ fn enforce_connectivity(
     let width_usize = usize::try_from(width).or(Err(
         "Could not convert width to usize in enforce_connectivity",
     ))?;
-    for (y, label_row) in labels.chunks_exact(width_usize).enumerate() {
-        for (x, &old_label) in label_row.iter().enumerate() {
-            let idx_usize = y.saturating_mul(width_usize).saturating_add(x);
+    for (y, label_row) in (0..height).zip(labels.chunks_exact(width_usize)) {
+        for (x, &old_label) in (0..width).zip(label_row) {
+            let idx_usize = index(width, x, y)?;
 
             // If no assigned label, assign current_label
             if new_labels.get(idx_usize) == Some(&usize::MAX) {
@@ -299,13 +1670,12 @@ fn enforce_connectivity(
                     .ok_or("Label index out of bounds")? = new_label;
 
                 // Find neighbor label that borders current pixel if it exists.
-                // Ending on South seems to have best results. This label will
-                // be used to label the cluster if the current label is too
-                // small.
-                for &neighbor in &neighbors {
-                    // `x` and `y` went from u32->usize->i64
-                    let neighbor_x = (x as i64) + neighbor.0;
-                    let neighbor_y = (y as i64) + neighbor.1;
+                // Ending on South seems to have best results. This label is
+                // the fallback for a cluster that is too small but borders no
+                // labeled pixels.
+                for &neighbor in neighbors {
+                    let neighbor_x = wrap_x(width_i, i64::from(x) + neighbor.0, wrap);
+                    let neighbor_y = i64::from(y) + neighbor.1;
                     if let Some(l) =
                         get_in_bounds(width_i, height_i, neighbor_x, neighbor_y, new_labels)
                     {
@@ -319,16 +1689,16 @@ fn enforce_connectivity(
                 // same label. The members go into a queue so they can be
                 // reassigned a neighboring label if it's a disjoint cluster.
                 label_queue.clear();
-                label_queue.push(((x as i64), (y as i64)));
+                label_queue.push((i64::from(x), i64::from(y)));
                 let mut label_queue_idx = 0;
                 let mut label_count = 1_usize;
 
                 while label_queue_idx < label_count {
-                    for &neighbor in &neighbors {
+                    for &neighbor in cluster_neighbors {
                         let entry = label_queue
                             .get(label_queue_idx)
                             .ok_or("Could not get label")?;
-                        let new_vx = entry.0 + neighbor.0;
+                        let new_vx = wrap_x(width_i, entry.0 + neighbor.0, wrap);
                         let new_vy = entry.1 + neighbor.1;
 
                         if let (Some(old_visit_label), Some(new_visit_label)) = (
@@ -338,7 +1708,12 @@ fn enforce_connectivity(
                             // If new label is unassigned and matches old_label, assign it the current cluster
                             if *old_visit_label == old_label && *new_visit_label == usize::MAX {
                                 if label_queue.capacity() == label_queue.len() {
-                                    label_queue.try_reserve(1)?;
+                                    // Double the queue, but never beyond the
+                                    // number of pixels in the image
+                                    let remaining =
+                                        labels.len().saturating_sub(label_queue.len()).max(1);
+                                    label_queue
+                                        .try_reserve_exact(label_queue.len().clamp(1, remaining))?;
                                 }
                                 label_queue.push((new_vx, new_vy));
                                 *new_visit_label = new_label;
@@ -349,17 +1724,56 @@ fn enforce_connectivity(
                     label_queue_idx = label_queue_idx.saturating_add(1);
                 }
 
+                let mut sum = (Lab::new(0.0, 0.0, 0.0), 0.0);
+                for &(l_x, l_y) in &label_queue {
+                    sum.0 += *image.get(l_x, l_y).ok_or("Cluster pixel out of bounds")?;
+                    sum.1 += 1.0;
+                }
+
                 // If a label set is smaller than some threshold, relabel that
-                // set as the nearest neighboring label. Don't increment label
-                // if too small of a set. Currently set to a quarter of a
-                // superpixel size.
+                // set as the bordering label with the closest mean color.
+                // Don't increment label if too small of a set. Currently set to
+                // a quarter of a superpixel size.
                 if label_count <= cluster_threshold {
+                    let mean = sum.0 / sum.1;
+                    let mut nearest: Option<(f64, usize)> = None;
+                    for &(l_x, l_y) in &label_queue {
+                        for &neighbor in neighbors {
+                            let n_x = wrap_x(width_i, l_x + neighbor.0, wrap);
+                            let n_y = l_y + neighbor.1;
+                            let Some(&l) = get_in_bounds(width_i, height_i, n_x, n_y, new_labels)
+                            else {
+                                continue;
+                            };
+                            if l == new_label || l == usize::MAX {
+                                continue;
+                            }
+                            let (color, count) =
+                                *label_sums.get(l).ok_or("Label sum out of bounds")?;
+                            let distance = color_distance(mean, color / count);
+                            // Ties go to the lowest label to stay independent
+                            // of the search order
+                            if nearest.filter(|&n| n <= (distance, l)).is_none() {
+                                nearest = Some((distance, l));
+                            }
+                        }
+                    }
+                    if let Some((_, l)) = nearest {
+                        neighbor_label = l;
+                    }
+
                     for &(l_x, l_y) in &label_queue {
                         *get_mut_in_bounds(width_i, height_i, l_x, l_y, new_labels)
                             .ok_or("New label index out of bounds")? = neighbor_label;
                     }
+                    if let Some(target) = label_sums.get_mut(neighbor_label) {
+                        target.0 += sum.0;
+                        target.1 += sum.1;
+                    }
                     continue;
                 }
+                label_sums.try_reserve(1)?;
+                label_sums.push(sum);
                 new_label = new_label.saturating_add(1);
             }
         }
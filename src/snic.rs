@@ -1,14 +1,25 @@
+use std::borrow::Cow;
 use std::cmp::Reverse;
 
+use crate::edges::{self, edge_regions};
 use crate::error::ScError;
-use crate::seed::{init_seeds, perturb};
+use crate::options::{AlphaMode, Connectivity, Options, WorkingSpace};
+use crate::seed::place_seeds;
 use crate::{
-    calculate_grid_interval, distance_lab, distance_s, distance_xy, get_in_bounds,
-    get_mut_in_bounds, m_div_s,
+    check_component_buffer, check_superpixel_count, check_superpixel_limit, checked_coords,
+    distance_lab, distance_s, distance_xy, get_in_bounds, get_mut_in_bounds, index, m_div_s,
+    pack_labels, pixel_count, pre_smooth, rgba_bytes_to_lab, srgb_components_into_lab,
+    srgb_components_to_lab, unwrap_x, wrap_coordinate, wrap_x, AsFeature3, GridSize, ImageView,
+    Stopwatch, Timings,
 };
 
-use num_traits::ToPrimitive;
-use palette::{cast, FromColor, Lab, Srgb};
+use num_traits::{ToPrimitive, Unsigned};
+use palette::{
+    encoding,
+    rgb::Rgb,
+    white_point::{WhitePoint, D65},
+    IntoColor, Lab,
+};
 
 /// Struct used for accumulating and calculating superpixel clusters in SNIC.
 #[derive(Debug, Clone, Copy)]
@@ -49,6 +60,9 @@ impl<T: Default> Default for SnicUpdate<T> {
 /// `k` must not be `0`.
 /// `m` is clamped to be between `1` and `20`.
 /// `width` and `height` must not be `0`.
+/// A length of `image` that isn't a multiple of `3` returns
+/// [`ScError::InvalidByteBufferLength`], and any other length than
+/// `width * height * 3` returns [`ScError::MismatchedSnicBuffer`].
 pub fn snic_from_bytes(
     k: u32,
     m: u8,
@@ -56,20 +70,102 @@ pub fn snic_from_bytes(
     height: u32,
     image: &[u8],
 ) -> Result<Vec<usize>, ScError> {
-    if usize::try_from(u64::from(width) * u64::from(height))
-        .or(Err("Invalid image dimensions in SNIC from bytes"))?
-        != image.len() / 3
-    {
-        return Err(ScError::MismatchedSnicBuffer);
-    }
-    let input_buffer = cast::from_component_slice::<Srgb<u8>>(image);
-    let mut input_lab: Vec<Lab<_, f64>> = Vec::new();
-    input_lab.try_reserve_exact(input_buffer.len())?;
-    input_lab.extend(
-        input_buffer
-            .iter()
-            .map(|&c| Lab::from_color(c.into_format())),
-    );
+    check_component_buffer(image.len(), 3, width, height, ScError::MismatchedSnicBuffer)?;
+    let input_lab = srgb_components_to_lab(image)?;
+
+    snic(k, m, width, height, &input_lab)
+}
+
+/// Calculate SNIC by providing a buffer of RGB component bytes as `&[u8]`,
+/// clustering the colors in the working space `space`.
+///
+/// The labels of [`WorkingSpace::CieLab`] match [`snic_from_bytes`]. Use
+/// [`WorkingSpace::convert_bytes`] and
+/// [`mean_colors_in_space`](crate::image::mean_colors_in_space) to recolor the
+/// segments with mean colors averaged in the same space.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_from_bytes_in_space(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    space: WorkingSpace,
+    image: &[u8],
+) -> Result<Vec<usize>, ScError> {
+    check_component_buffer(image.len(), 3, width, height, ScError::MismatchedSnicBuffer)?;
+    let input = space.convert_bytes(image)?;
+
+    snic(k, m, width, height, input.colors())
+}
+
+/// Calculate SNIC by providing a buffer of RGBA component bytes as `&[u8]`.
+///
+/// The alpha component is not used for segmentation. With
+/// [`AlphaMode::Premultiplied`], the colors are divided by alpha before
+/// conversion to `Lab`, and fully transparent pixels are treated as black.
+/// With [`AlphaMode::Straight`], the colors of transparent pixels are kept.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_from_rgba_bytes(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    alpha_mode: AlphaMode,
+    image: &[u8],
+) -> Result<Vec<usize>, ScError> {
+    check_component_buffer(image.len(), 4, width, height, ScError::MismatchedSnicBuffer)?;
+    let input_lab = rgba_bytes_to_lab(image, alpha_mode)?;
+
+    snic(k, m, width, height, &input_lab)
+}
+
+/// Calculate SNIC by providing a buffer of RGB component bytes as `&[u8]`,
+/// converting to `Lab` in the caller-owned `buffer`.
+///
+/// `buffer` is cleared before use and only grows when it's too small, so it
+/// can be reused across calls to avoid reallocating for images of the same
+/// size.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_from_bytes_with_buffer(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    image: &[u8],
+    buffer: &mut Vec<Lab<D65, f64>>,
+) -> Result<Vec<usize>, ScError> {
+    check_component_buffer(image.len(), 3, width, height, ScError::MismatchedSnicBuffer)?;
+    srgb_components_into_lab(image, buffer)?;
+
+    snic(k, m, width, height, buffer)
+}
+
+/// Calculate SNIC by providing a buffer of 16-bit RGB components as `&[u16]`.
+///
+/// The components are normalized from the full `u16` range before conversion
+/// to `Lab`, preserving the precision of high bit depth images.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_from_u16(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    image: &[u16],
+) -> Result<Vec<usize>, ScError> {
+    check_component_buffer(image.len(), 3, width, height, ScError::MismatchedSnicBuffer)?;
+    let input_lab = srgb_components_to_lab(image)?;
 
     snic(k, m, width, height, &input_lab)
 }
@@ -79,6 +175,34 @@ pub fn snic_from_bytes(
 /// `k` must not be `0`.
 /// `m` is clamped to be between `1` and `20`.
 /// `width` and `height` must not be `0`.
+/// `image` must have `width * height` elements.
+///
+/// An image of a single color has no color distance between pixels, so only
+/// the spatial term separates the clusters and the result is a regular grid of
+/// one superpixel per seed.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::snic;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = vec![Lab::<D65, f64>::new(50.0, 10.0, -5.0); 60 * 40];
+/// let mut labels = snic(24, 10, 60, 40, &image)?;
+/// labels.sort_unstable();
+/// labels.dedup();
+/// assert_eq!(labels, (1..=24).collect::<Vec<_>>());
+///
+/// // The queue runs empty while the last pixel is still held back from it
+/// let image = [Lab::<D65, f64>::new(50.0, 10.0, -5.0); 2];
+/// assert_eq!(snic(1, 10, 2, 1, &image)?, [1, 1]);
+///
+/// assert!(matches!(
+///     snic(1, 10, 3, 1, &image),
+///     Err(simple_clustering::error::ScError::MismatchedSnicBuffer)
+/// ));
+/// # Ok(())
+/// # }
+/// ```
 ///
 /// ## Reference
 ///
@@ -92,42 +216,461 @@ pub fn snic<Wp>(
     height: u32,
     image: &[Lab<Wp, f64>],
 ) -> Result<Vec<usize>, ScError> {
+    snic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        &Options::default(),
+        distance_lab,
+        None,
+        None,
+        None,
+        ImageView::new(image, width),
+    )
+}
+
+/// Calculate SNIC with a superpixel side length of `s` pixels instead of a
+/// superpixel count.
+///
+/// The seeds are spread over a grid with an interval of `s`, so superpixels
+/// are roughly `s x s` pixels. As many seeds are placed as fit on the grid.
+///
+/// ```
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::snic_with_interval;
+///
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// let image = vec![Lab::<D65, f64>::new(50.0, 10.0, -5.0); 64 * 32];
+/// let mut labels = snic_with_interval(16, 10, 64, 32, &image)?;
+/// labels.sort_unstable();
+/// labels.dedup();
+/// assert_eq!(labels, (1..=8).collect::<Vec<_>>());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `s` must not be `0` and `s * s` must not be larger than `width * height`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_with_interval<Wp>(
+    s: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError> {
+    snic_impl(
+        GridSize::Interval(s),
+        m,
+        width,
+        height,
+        &Options::default(),
+        distance_lab,
+        None,
+        None,
+        None,
+        ImageView::new(image, width),
+    )
+}
+
+/// Calculate SNIC with additional [`Options`].
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_with_options<Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let measure = options.color_distance;
+    snic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        None,
+        None,
+        ImageView::new(image, width),
+    )
+}
+
+/// Calculate SNIC with additional [`Options`] on an owned or borrowed image.
+///
+/// `image` can be a `Vec`, which is consumed, or a slice, which is borrowed.
+/// This is for generic code that stores its inputs either way. The result is
+/// the same as [`snic_with_options`].
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// use simple_clustering::{options::Options, snic_cow};
+///
+/// let image = vec![palette::Lab::<palette::white_point::D65, f64>::default(); 64];
+/// let borrowed = snic_cow(4, 10, 8, 8, &Options::new(), image.as_slice())?;
+/// let owned = snic_cow(4, 10, 8, 8, &Options::new(), image)?;
+/// assert_eq!(borrowed, owned);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_cow<'a, Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: impl Into<Cow<'a, [Lab<Wp, f64>]>>,
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    snic_with_options(k, m, width, height, options, &image.into())
+}
+
+/// Calculate SNIC with additional [`Options`] on the `(x, y, width, height)`
+/// rectangle `roi` of a larger image whose rows are `stride` pixels long.
+///
+/// The region is segmented in place without copying it, so a viewport of a
+/// large image can be segmented directly. The labels and all coordinates of
+/// the result are relative to the region, and the returned labels have
+/// `width * height` elements. The result is the same as segmenting a cropped
+/// copy of the region.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// The region must not be empty and must lie inside of the image.
+pub fn snic_roi<Wp>(
+    k: u32,
+    m: u8,
+    stride: u32,
+    roi: (u32, u32, u32, u32),
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let (_, _, width, height) = roi;
+    let measure = options.color_distance;
+    snic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        None,
+        None,
+        ImageView::with_roi(image, stride, roi)?,
+    )
+}
+
+/// Calculate SNIC with additional [`Options`], returning the labels as a
+/// narrower unsigned integer type such as `u16` or `u32`.
+///
+/// The labels are narrowed after segmentation like with
+/// [`slic_packed`](crate::slic_packed), which describes the memory savings.
+/// Returns [`ScError::Overflow`] if a label doesn't fit in `T`.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_packed<Wp, T>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<Vec<T>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+    T: TryFrom<usize> + Unsigned,
+{
+    let labels = snic_with_options(k, m, width, height, options, image)?;
+    pack_labels(&labels)
+}
+
+/// Calculate SNIC with additional [`Options`], also returning the step at which
+/// each pixel was labeled as `(labels, order)`.
+///
+/// SNIC grows superpixels outward from their seeds, labeling one pixel per
+/// step of its priority queue. The step indices start at `0` for the first
+/// labeled pixel and show the growth front of the regions, which can be used to
+/// animate the process or to inspect unusual region shapes. The order is
+/// recorded before connectivity is enforced, so pixels relabeled to merge
+/// stray regions keep the step of their original assignment. Pixels that were
+/// never reached are given `u32::MAX`.
+///
+/// Returns [`ScError::Overflow`] if the image has more than `u32::MAX` pixels,
+/// since their steps wouldn't fit in `u32` without reaching `u32::MAX`.
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_with_order<Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<(Vec<usize>, Vec<u32>), ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let measure = options.color_distance;
+    let mut order = Vec::new();
+    let labels = snic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        Some(&mut order),
+        None,
+        None,
+        ImageView::new(image, width),
+    )?;
+
+    Ok((labels, order))
+}
+
+/// Calculate SNIC with additional [`Options`] on features that implement
+/// [`AsFeature3`], such as plain `[f64; 3]` arrays.
+///
+/// Features are compared with [`AsFeature3::distance`] instead of
+/// [`Options::color_distance`], so they can be in any color space where the
+/// Euclidean distance is meaningful.
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// use palette::{white_point::D65, Lab};
+/// use simple_clustering::{options::Options, snic, snic_features};
+///
+/// let features: Vec<[f64; 3]> = (0..64)
+///     .map(|i| [f64::from(i % 8) * 10.0, f64::from(i / 8) * 5.0, 0.0])
+///     .collect();
+/// let labels = snic_features(4, 10, 8, 8, &Options::new(), &features)?;
+///
+/// // The features are segmented like `Lab` colors with the same components
+/// let lab: Vec<Lab<D65, f64>> = features.iter().map(|&f| Lab::from(f)).collect();
+/// assert_eq!(labels, snic(4, 10, 8, 8, &lab)?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_features<T: AsFeature3>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: &[T],
+) -> Result<Vec<usize>, ScError> {
+    snic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        options,
+        |lhs, rhs| T::from_array(lhs.into()).distance(T::from_array(rhs.into())),
+        None,
+        None,
+        None,
+        ImageView::new(T::as_lab_slice(image), width),
+    )
+}
+
+/// Calculate SNIC with additional [`Options`], also returning the number of
+/// pixels with each label as `(labels, counts)`.
+///
+/// SNIC labels start at `1`, so `counts` is indexed by label and its first
+/// entry for label `0` is the number of pixels left unlabeled by
+/// [`Options::max_distance`], which is `0` by default. The counts are tracked
+/// while clustering and connectivity is enforced, so they match the returned
+/// labels without an extra pass over them.
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// # let (width, height) = (8, 8);
+/// # let image = vec![palette::Lab::<palette::white_point::D65, f64>::default(); 64];
+/// use simple_clustering::{options::Options, snic_with_counts};
+///
+/// let (labels, counts) = snic_with_counts(4, 10, width, height, &Options::new(), &image)?;
+/// assert_eq!(counts[0], 0);
+/// assert_eq!(counts.iter().sum::<usize>(), labels.len());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_with_counts<Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+) -> Result<(Vec<usize>, Vec<usize>), ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let measure = options.color_distance;
+    let mut counts = Vec::new();
+    let labels = snic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        Some(&mut counts),
+        None,
+        ImageView::new(image, width),
+    )?;
+
+    Ok((labels, counts))
+}
+
+/// Calculate SNIC with additional [`Options`], recording the time spent in
+/// each stage of the algorithm in `timings`.
+///
+/// The labels are the same as those of [`snic_with_options`]. The clock is
+/// only read by this function, so the other entry points have no timing
+/// overhead.
+///
+/// ```
+/// # fn main() -> Result<(), simple_clustering::error::ScError> {
+/// # let (width, height) = (8, 8);
+/// # let image = vec![palette::Lab::<palette::white_point::D65, f64>::default(); 64];
+/// use simple_clustering::{options::Options, snic_with_timings, Timings};
+///
+/// let mut timings = Timings::default();
+/// let _ = snic_with_timings(4, 10, width, height, &Options::new(), &image, &mut timings)?;
+/// let total = timings.smoothing + timings.seeding + timings.clustering + timings.connectivity;
+/// println!("Clustering took {:?} of {:?}", timings.clustering, total);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `k` must not be `0`.
+/// `m` is clamped to be between `1` and `20`.
+/// `width` and `height` must not be `0`.
+pub fn snic_with_timings<Wp>(
+    k: u32,
+    m: u8,
+    width: u32,
+    height: u32,
+    options: &Options,
+    image: &[Lab<Wp, f64>],
+    timings: &mut Timings,
+) -> Result<Vec<usize>, ScError>
+where
+    Wp: WhitePoint<f64>,
+    Lab<Wp, f64>: IntoColor<Rgb<encoding::Srgb, f64>>,
+{
+    let measure = options.color_distance;
+    snic_impl(
+        GridSize::Superpixels(k),
+        m,
+        width,
+        height,
+        options,
+        |lhs, rhs| measure.distance(lhs, rhs),
+        None,
+        None,
+        Some(timings),
+        ImageView::new(image, width),
+    )
+}
+
+/// SNIC implementation using `color_distance` to compare colors. The step at
+/// which each pixel is labeled is written to `order` and the pixel count of
+/// each label is written to `counts` if supplied.
+#[allow(clippy::too_many_arguments)]
+fn snic_impl<Wp, F>(
+    size: GridSize,
+    m: u8,
+    width: u32,
+    height: u32,
+    options: &Options,
+    color_distance: F,
+    mut order: Option<&mut Vec<u32>>,
+    mut counts: Option<&mut Vec<usize>>,
+    mut timings: Option<&mut Timings>,
+    image: ImageView<'_, Lab<Wp, f64>>,
+) -> Result<Vec<usize>, ScError>
+where
+    F: Fn(Lab<Wp, f64>, Lab<Wp, f64>) -> f64,
+{
     let width_i = i64::from(width);
     let height_i = i64::from(height);
     // Validate input parameters
     let m = m.clamp(1, 20);
-    if k == 0 {
-        return Err(ScError::ZeroSuperpixelCount);
+    let (s, k) = size.resolve(width, height)?;
+    check_superpixel_limit(size, width, height, options)?;
+    if image.len() != pixel_count(width, height)? {
+        return Err(ScError::MismatchedSnicBuffer);
     }
 
-    if width == 0 || height == 0 {
-        return Err(ScError::InvalidImageDimension);
+    let m_s_term = m_div_s(f64::from(m), f64::from(s));
+
+    let max_distance = options.max_distance.unwrap_or(f64::INFINITY);
+    if max_distance.is_nan() {
+        return Err(ScError::General("Max distance must not be NaN"));
     }
 
-    match u64::from(k).cmp(&(u64::from(width) * u64::from(height))) {
-        std::cmp::Ordering::Less => {}
-        std::cmp::Ordering::Equal | std::cmp::Ordering::Greater => {
-            return Err(ScError::InvalidSuperpixelCount);
-        }
+    // The steps go up to one less than the pixel count, which keeps them below
+    // the `u32::MAX` of unreached pixels
+    if order.is_some() && u32::try_from(image.len()).is_err() {
+        return Err(ScError::Overflow("labeling order"));
     }
 
-    // Calculate S
-    let s = calculate_grid_interval(width, height, k)
-        .to_u32()
-        .ok_or(ScError::InvalidGridInterval)?;
+    let mut stopwatch = Stopwatch::new(timings.is_some());
 
-    if s == 0 {
-        return Err(ScError::ZeroGridInterval);
+    // Cluster a smoothed copy of the image if requested
+    let smoothed = pre_smooth(image, options.pre_smooth)?;
+    let image = smoothed
+        .as_deref()
+        .map_or(image, |smoothed| ImageView::new(smoothed, width));
+    if let Some(timings) = timings.as_deref_mut() {
+        timings.smoothing = stopwatch.split();
     }
 
-    let m_s_term = m_div_s(f64::from(m), f64::from(s));
-
     // Init seeds and shuffle them to a hopefully non-noisy pixel
     let mut clusters = Vec::new();
-    init_seeds(width, height, s, k, image, &mut clusters)?;
-
-    for seed in &mut clusters {
-        perturb(seed, i64::from(width), i64::from(height), image)?;
+    place_seeds(width, height, s, k, options, image, &mut clusters)?;
+    let edge_regions = edge_regions(width, height, options, image, &mut clusters)?;
+    if let Some(timings) = timings.as_deref_mut() {
+        timings.seeding = stopwatch.split();
     }
 
     // Output labels
@@ -135,6 +678,13 @@ pub fn snic<Wp>(
     labels.try_reserve_exact(image.len())?;
     labels.extend((0..image.len()).map(|_| 0_usize));
 
+    if let Some(order) = order.as_deref_mut() {
+        order.clear();
+        order.try_reserve_exact(image.len())?;
+        order.extend((0..image.len()).map(|_| u32::MAX));
+    }
+    let mut step = 0_u32;
+
     // Leave the first entry vacant since label k starts at 1
     let mut updates: Vec<SnicUpdate<Lab<Wp, f64>>> = Vec::new();
     updates.try_reserve_exact(clusters.len().saturating_add(1))?;
@@ -147,7 +697,7 @@ pub fn snic<Wp>(
     // cluster. Push all current centers onto the queue with 0.0 distance. Label
     // values start at 1.
     let mut pq = std::collections::BinaryHeap::with_capacity(image.len() / 5);
-    for (k, &cluster) in clusters.iter().skip(1).enumerate() {
+    for (k, &cluster) in clusters.iter().enumerate() {
         element.distance = Reverse(NonNanFloat(0.0));
         element.k = k.saturating_add(1);
         element.x = cluster.x;
@@ -155,20 +705,18 @@ pub fn snic<Wp>(
         pq.push(element);
     }
 
-    // 4 way connectivity for neighboring pixels going clockwise from west
-    let neighbors = [(-1, 0), (0, -1), (1, 0), (0, 1)];
+    // Neighboring pixels going clockwise from west, 4 or 8 way
+    let neighbors = options.connectivity.offsets();
+    let wrap = options.wrap_x;
+    let width_f = f64::from(width);
 
     // Cache next element if its distance is less than the pq root to avoid
     // reheaping
     let mut swap_elem = None;
 
-    // Remember that we have to offset down by 1 for indexing k
-    while !pq.is_empty() {
-        let elem = if let Some(elem) = swap_elem.take().or_else(|| pq.pop()) {
-            elem
-        } else {
-            break;
-        };
+    // Remember that we have to offset down by 1 for indexing k. The swapped
+    // element must be processed even after the queue runs empty.
+    while let Some(elem) = swap_elem.take().or_else(|| pq.pop()) {
         if let Some(label) = get_mut_in_bounds(
             width_i,
             height_i,
@@ -179,6 +727,21 @@ pub fn snic<Wp>(
             if *label == 0 {
                 *label = elem.k;
 
+                if let Some(order) = order.as_deref_mut() {
+                    if let Some(o) = get_mut_in_bounds(
+                        width_i,
+                        height_i,
+                        i64::from(elem.x),
+                        i64::from(elem.y),
+                        order,
+                    ) {
+                        *o = step;
+                    }
+                    step = step
+                        .checked_add(1)
+                        .ok_or(ScError::Overflow("labeling order"))?;
+                }
+
                 // Update C[k_i]
                 let update = updates
                     .get_mut(elem.k)
@@ -187,58 +750,77 @@ pub fn snic<Wp>(
                 let cluster = clusters
                     .get_mut(elem.k - 1)
                     .ok_or("Cluster index out of bounds")?;
-                update.accum += *get_in_bounds(
-                    width_i,
-                    height_i,
-                    i64::from(elem.x),
-                    i64::from(elem.y),
-                    image,
-                )
-                .ok_or("Element color out of bounds")?;
-                update.x += f64::from(elem.x);
+                update.accum += *image
+                    .get(i64::from(elem.x), i64::from(elem.y))
+                    .ok_or("Element color out of bounds")?;
+                update.x += if wrap {
+                    unwrap_x(f64::from(elem.x), f64::from(cluster.x), width_f)
+                } else {
+                    f64::from(elem.x)
+                };
                 update.y += f64::from(elem.y);
                 update.count += 1.0;
 
                 cluster.data = update.accum * update.count.recip();
-                cluster.x = (update.x * update.count.recip())
-                    .to_u32()
-                    .ok_or("Invalid x update coordinate")?;
+                let x = update.x * update.count.recip();
+                let x = if wrap { wrap_coordinate(x, width_f) } else { x };
+                cluster.x = x.to_u32().ok_or("Invalid x update coordinate")?;
                 cluster.y = (update.y * update.count.recip())
                     .to_u32()
                     .ok_or("Invalid y update coordinate")?;
 
-                // Pushpop array to possibly skip a heap balancing operation
-                let mut arr_neighbors = [None; 4];
+                // Pushpop array to possibly skip a heap balancing operation,
+                // sized for the largest neighborhood
+                let mut arr_neighbors = [None; 8];
+                let arr_neighbors = &mut arr_neighbors[..neighbors.len()];
 
                 for (&neighbor, arr) in neighbors.iter().zip(arr_neighbors.iter_mut()) {
-                    let n_x = i64::from(elem.x) + neighbor.0;
+                    let n_x = wrap_x(width_i, i64::from(elem.x) + neighbor.0, wrap);
                     let n_y = i64::from(elem.y) + neighbor.1;
 
                     if let (Some(n_label), Some(color)) = (
                         get_in_bounds(width_i, height_i, n_x, n_y, &labels),
-                        get_in_bounds(width_i, height_i, n_x, n_y, image),
+                        image.get(n_x, n_y),
                     ) {
-                        if *n_label == 0 {
+                        let (n_x, n_y) = checked_coords(n_x, n_y)?;
+                        let n_idx = index(width, n_x, n_y)?;
+                        if *n_label == 0 && edges::allows(edge_regions.as_ref(), n_idx, elem.k - 1)
+                        {
+                            let x = if wrap {
+                                unwrap_x(f64::from(n_x), f64::from(cluster.x), width_f)
+                            } else {
+                                f64::from(n_x)
+                            };
                             let distance = distance_s(
                                 m_s_term,
-                                distance_lab(*color, cluster.data),
+                                color_distance(*color, cluster.data),
                                 distance_xy(
-                                    (
-                                        n_x.to_f64().ok_or("Could not convert x neighbor")?,
-                                        n_y.to_f64().ok_or("Could not convert y neighbor")?,
-                                    ),
+                                    (x, f64::from(n_y)),
                                     (f64::from(cluster.x), f64::from(cluster.y)),
                                 ),
                             );
 
                             if distance.is_nan() {
-                                return Err(ScError::NanDistance);
+                                // Point at the neighbor if its color is NaN.
+                                // Otherwise the cluster color is NaN, which
+                                // only happens if it was seeded on this pixel.
+                                let (x, y) =
+                                    if [color.l, color.a, color.b].iter().any(|c| c.is_nan()) {
+                                        (n_x, n_y)
+                                    } else {
+                                        (elem.x, elem.y)
+                                    };
+                                return Err(ScError::NanDistance { x, y });
+                            }
+                            // Leave pixels beyond the cap unlabeled
+                            if distance > max_distance {
+                                continue;
                             }
 
                             element.distance = Reverse(NonNanFloat(distance));
                             element.k = elem.k;
-                            element.x = u32::try_from(n_x).or(Err("Invalid neighbor x"))?;
-                            element.y = u32::try_from(n_y).or(Err("Invalid neighbor y"))?;
+                            element.x = n_x;
+                            element.y = n_y;
                             *arr = Some(element);
                         }
                     }
@@ -248,7 +830,7 @@ pub fn snic<Wp>(
                 // assign it to swap_elem. Because we're using cmp::Reverse, the
                 // smallest value will be the last element of the array.
                 arr_neighbors.sort_unstable();
-                if let Some(min) = arr_neighbors[3] {
+                if let Some((&Some(min), rest)) = arr_neighbors.split_last() {
                     if let Some(mut peek) = pq.peek_mut() {
                         // Swap element is less than root: don't push to heap.
                         // (.distance field is wrapped in cmp::Reverse)
@@ -260,36 +842,82 @@ pub fn snic<Wp>(
                             *peek = min;
                         }
                     } else {
-                        swap_elem = arr_neighbors[3];
+                        swap_elem = Some(min);
                     }
-                    pq.extend(arr_neighbors[..3].iter().flatten());
+                    pq.extend(rest.iter().flatten());
                 }
             }
         }
     }
 
-    enforce_connectivity(width_i, height_i, &mut labels);
+    // The updates hold the pixel count of each label. The unused first entry
+    // is replaced by the count of pixels left unlabeled by `max_distance`.
+    if let Some(counts) = counts.as_deref_mut() {
+        counts.clear();
+        counts.try_reserve_exact(updates.len())?;
+        for update in &updates {
+            counts.push(update.count.to_usize().ok_or("Invalid cluster count")?);
+        }
+        let labeled: usize = counts.iter().sum();
+        if let Some(unlabeled) = counts.first_mut() {
+            *unlabeled = image.len().saturating_sub(labeled);
+        }
+    }
+
+    if let Some(timings) = timings.as_deref_mut() {
+        timings.clustering = stopwatch.split();
+    }
+
+    enforce_connectivity(
+        width_i,
+        height_i,
+        wrap,
+        options.connectivity,
+        &mut labels,
+        counts.map(Vec::as_mut_slice),
+    );
+    if let Some(timings) = timings {
+        timings.connectivity = stopwatch.split();
+    }
+    check_superpixel_count(k, &labels, options.count_tolerance)?;
 
     Ok(labels)
 }
 
-// Enforce connectivity if algorithm fails to do so, iterate in WNES order.
-// BSDS300-images\BSDS300\images\test\295087.jpg (desert rocks with tree)
-// showed some stray white pixels at k=1000, m=10.
-fn enforce_connectivity(width: i64, height: i64, labels: &mut [usize]) {
+// Enforce connectivity if algorithm fails to do so, iterate in clockwise order
+// from West. BSDS300-images\BSDS300\images\test\295087.jpg (desert rocks
+// with tree) showed some stray white pixels at k=1000, m=10.
+fn enforce_connectivity(
+    width: i64,
+    height: i64,
+    wrap: bool,
+    connectivity: Connectivity,
+    labels: &mut [usize],
+    mut counts: Option<&mut [usize]>,
+) {
+    let offsets = connectivity.offsets();
     for y in 0..height {
         for x in 0..width {
-            if let Some(first) = get_in_bounds(width, height, x, y, labels) {
-                let neighbors = [
-                    get_in_bounds(width, height, x - 1, y, labels).copied(),
-                    get_in_bounds(width, height, x, y - 1, labels).copied(),
-                    get_in_bounds(width, height, x + 1, y, labels).copied(),
-                    get_in_bounds(width, height, x, y + 1, labels).copied(),
-                ];
-                if !neighbors.iter().any(|&n| n == Some(*first)) {
-                    for &n in neighbors.iter().flatten() {
+            // Unlabeled pixels are neither replaced nor used as replacements
+            if let Some(&first) = get_in_bounds(width, height, x, y, labels).filter(|&&l| l != 0) {
+                let mut neighbors = offsets.iter().filter_map(|&(d_x, d_y)| {
+                    get_in_bounds(width, height, wrap_x(width, x + d_x, wrap), y + d_y, labels)
+                        .copied()
+                        .filter(|&n| n != 0)
+                });
+                if !neighbors.clone().any(|n| n == first) {
+                    // The last neighbor found replaces the stray label
+                    if let Some(n) = neighbors.next_back() {
                         // We know this pixel is inbounds from `if let`
                         *get_mut_in_bounds(width, height, x, y, labels).unwrap() = n;
+                        if let Some(counts) = counts.as_deref_mut() {
+                            if let Some(count) = counts.get_mut(first) {
+                                *count = count.saturating_sub(1);
+                            }
+                            if let Some(count) = counts.get_mut(n) {
+                                *count += 1;
+                            }
+                        }
                     }
                 }
             }
@@ -312,6 +940,7 @@ struct SnicElement {
 
 impl Eq for SnicElement {}
 
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl PartialOrd for SnicElement {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
@@ -333,6 +962,7 @@ struct NonNanFloat(f64);
 
 impl Eq for NonNanFloat {}
 
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl PartialOrd for NonNanFloat {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
@@ -0,0 +1,143 @@
+//! Deterministic synthetic images and label comparisons for tests and
+//! benchmarks.
+//!
+//! These functions are available with the `testing` feature. The images are
+//! the same for the same arguments on every platform, so they can be used as
+//! reproducible inputs without bundling image files. The
+//! [`assert_same_partition`](crate::assert_same_partition) macro compares
+//! labels which may be numbered differently, such as after changes to an
+//! algorithm.
+use crate::{error::ScError, image::HashMap, pixel_count};
+
+use palette::{white_point::D65, Lab};
+
+/// Generate a smooth color gradient.
+///
+/// Lightness increases from top to bottom, `a` increases from left to right,
+/// and `b` decreases from left to right.
+pub fn test_gradient(width: u32, height: u32) -> Result<Vec<Lab<D65, f64>>, ScError> {
+    let mut image = Vec::new();
+    image.try_reserve_exact(pixel_count(width, height)?)?;
+
+    // Avoid dividing by zero for single row or column images
+    let x_max = f64::from(width.saturating_sub(1).max(1));
+    let y_max = f64::from(height.saturating_sub(1).max(1));
+    for y in 0..height {
+        let l = 20.0 + 60.0 * f64::from(y) / y_max;
+        image.extend((0..width).map(|x| {
+            let t = f64::from(x) / x_max;
+            Lab::new(l, -60.0 + 120.0 * t, 60.0 - 120.0 * t)
+        }));
+    }
+
+    Ok(image)
+}
+
+/// Generate a checkerboard of two colors with square cells of `cell` pixels.
+///
+/// The cell at the top left corner is dark gray and the alternating cells are
+/// light gray.
+///
+/// `cell` must not be `0`.
+pub fn test_checkerboard(
+    width: u32,
+    height: u32,
+    cell: u32,
+) -> Result<Vec<Lab<D65, f64>>, ScError> {
+    if cell == 0 {
+        return Err(ScError::General("Checkerboard cell size cannot be 0"));
+    }
+
+    let dark = Lab::new(25.0, 0.0, 0.0);
+    let light = Lab::new(75.0, 0.0, 0.0);
+    let mut image = Vec::new();
+    image.try_reserve_exact(pixel_count(width, height)?)?;
+
+    for y in 0..height {
+        image.extend((0..width).map(|x| {
+            if (x / cell) % 2 == (y / cell) % 2 {
+                dark
+            } else {
+                light
+            }
+        }));
+    }
+
+    Ok(image)
+}
+
+/// Find the first index where two label slices induce different partitions of
+/// the pixels, ignoring how the labels are numbered.
+///
+/// Two slices induce the same partition if there is a one-to-one mapping
+/// between their labels. If the slices have different lengths, the length of
+/// the shorter one is returned when the shared part matches.
+///
+/// Use [`label_diff`](crate::image::label_diff) to mark every pixel which
+/// changed instead of only the first.
+pub fn partition_mismatch(lhs: &[usize], rhs: &[usize]) -> Option<usize> {
+    let mut forward = HashMap::<usize, usize>::default();
+    let mut backward = HashMap::<usize, usize>::default();
+
+    for (idx, (&l, &r)) in lhs.iter().zip(rhs.iter()).enumerate() {
+        if *forward.entry(l).or_insert(r) != r || *backward.entry(r).or_insert(l) != l {
+            return Some(idx);
+        }
+    }
+
+    (lhs.len() != rhs.len()).then(|| lhs.len().min(rhs.len()))
+}
+
+/// Check whether two label slices induce the same partition of the pixels,
+/// ignoring how the labels are numbered. See [`partition_mismatch`].
+pub fn same_partition(lhs: &[usize], rhs: &[usize]) -> bool {
+    partition_mismatch(lhs, rhs).is_none()
+}
+
+/// Assert that two label slices of an image `width` pixels wide induce the
+/// same partition of the pixels, ignoring how the labels are numbered.
+///
+/// On failure, the panic message contains the coordinates and labels of the
+/// first pixel where the partitions diverge.
+///
+/// ```
+/// use simple_clustering::assert_same_partition;
+///
+/// let lhs = [0, 0, 1, 1, 2, 2];
+/// let rhs = [5, 5, 3, 3, 4, 4];
+/// assert_same_partition!(lhs, rhs, 2);
+/// ```
+///
+/// ```should_panic
+/// use simple_clustering::assert_same_partition;
+///
+/// let lhs = [0, 0, 1, 1];
+/// let rhs = [0, 1, 1, 1];
+/// // Panics with "partitions diverge at (1, 0): left label 0, right label 1"
+/// assert_same_partition!(lhs, rhs, 2);
+/// ```
+#[macro_export]
+macro_rules! assert_same_partition {
+    ($left:expr, $right:expr, $width:expr $(,)?) => {{
+        let left: &[usize] = &$left;
+        let right: &[usize] = &$right;
+        let width = usize::try_from($width).expect("width does not fit in usize");
+        assert!(width != 0, "width must not be 0");
+        if left.len() != right.len() {
+            panic!(
+                "label lengths differ: left {}, right {}",
+                left.len(),
+                right.len()
+            );
+        }
+        if let Some(idx) = $crate::testing::partition_mismatch(left, right) {
+            panic!(
+                "partitions diverge at ({}, {}): left label {}, right label {}",
+                idx % width,
+                idx / width,
+                left[idx],
+                right[idx]
+            );
+        }
+    }};
+}